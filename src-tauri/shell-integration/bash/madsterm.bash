@@ -0,0 +1,57 @@
+# Madsterm shell integration for bash, loaded with `bash --rcfile`. Reads the user's
+# usual startup files, then reports prompts, commands and the working directory
+# with OSC 133 and OSC 7.
+
+if [[ -n "$MADSTERM_BASH_LOGIN" ]]; then
+    unset MADSTERM_BASH_LOGIN
+    # --rcfile only works for non-login shells, so do what a login shell would: read
+    # the profile files now and ~/.bash_logout on the way out. Set first, so an EXIT
+    # trap of the user's own takes over
+    trap '[[ -r ~/.bash_logout ]] && . ~/.bash_logout' EXIT
+    [[ -r /etc/profile ]] && . /etc/profile
+    for __madsterm_rc in ~/.bash_profile ~/.bash_login ~/.profile; do
+        if [[ -r "$__madsterm_rc" ]]; then
+            . "$__madsterm_rc"
+            break
+        fi
+    done
+    unset __madsterm_rc
+else
+    [[ -r /etc/bash.bashrc ]] && . /etc/bash.bashrc
+    [[ -r ~/.bashrc ]] && . ~/.bashrc
+fi
+
+if [[ $- == *i* && -z "$__madsterm_integrated" ]]; then
+    __madsterm_integrated=1
+
+    __madsterm_urlencode() {
+        local LC_ALL=C str="$1" out="" c i
+        for (( i = 0; i < ${#str}; i++ )); do
+            c="${str:i:1}"
+            case "$c" in
+                [A-Za-z0-9/._~-]) out+="$c" ;;
+                *) printf -v c '%%%02X' "'$c"; out+="$c" ;;
+            esac
+        done
+        printf '%s' "$out"
+    }
+
+    __madsterm_prompt() {
+        local status=$?
+        # Outside a command this is ignored, so it is safe to send on every prompt
+        printf '\e]133;D;%s\a' "$status"
+        printf '\e]7;file://%s%s\a' "$HOSTNAME" "$(__madsterm_urlencode "$PWD")"
+        printf '\e]133;A\a'
+        # Prompt frameworks may rebuild PS1, so the end-of-prompt mark is re-added as needed
+        [[ "$PS1" == *'133;B'* ]] || PS1+='\[\e]133;B\a\]'
+        return "$status"
+    }
+
+    # PS0 is printed after a command is read and before it runs (bash 4.4 and later)
+    PS0+='\e]133;C\a'
+    if [[ "$(declare -p PROMPT_COMMAND 2>/dev/null)" == "declare -a"* ]]; then
+        PROMPT_COMMAND=(__madsterm_prompt "${PROMPT_COMMAND[@]}")
+    else
+        PROMPT_COMMAND="__madsterm_prompt${PROMPT_COMMAND:+; $PROMPT_COMMAND}"
+    fi
+fi
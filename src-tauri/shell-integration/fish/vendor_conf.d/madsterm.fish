@@ -0,0 +1,33 @@
+# Madsterm shell integration for fish, found through XDG_DATA_DIRS. Reports prompts,
+# commands and the working directory with OSC 133 and OSC 7.
+
+# Take this directory back out, so programs started from the shell never see it
+if set -q MADSTERM_FISH_DATA_DIR
+    set -l dirs
+    for dir in (string split : -- "$XDG_DATA_DIRS")
+        test "$dir" = "$MADSTERM_FISH_DATA_DIR"; or set -a dirs $dir
+    end
+    if set -q dirs[1]
+        set -gx XDG_DATA_DIRS (string join : -- $dirs)
+    else
+        set -e XDG_DATA_DIRS
+    end
+    set -e MADSTERM_FISH_DATA_DIR
+end
+
+if status is-interactive; and not set -q __madsterm_integrated
+    set -g __madsterm_integrated 1
+
+    function __madsterm_preexec --on-event fish_preexec
+        printf '\e]133;C;cmdline_url=%s\a' (string escape --style=url -- $argv[1])
+    end
+
+    function __madsterm_postexec --on-event fish_postexec
+        printf '\e]133;D;%s\a' $status
+    end
+
+    function __madsterm_prompt --on-event fish_prompt
+        printf '\e]7;file://%s%s\a' $hostname (string escape --style=url -- $PWD)
+        printf '\e]133;A\a'
+    end
+end
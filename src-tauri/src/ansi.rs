@@ -0,0 +1,115 @@
+//! Reduces terminal output to plain text by dropping escape sequences and control
+//! characters. Works incrementally, so sequences may be split across chunks.
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+const BS: u8 = 0x08;
+const CAN: u8 = 0x18;
+const SUB: u8 = 0x1a;
+const DEL: u8 = 0x7f;
+
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    /// `ESC` followed by intermediate bytes, e.g. the `(` of `ESC ( B`
+    EscapeIntermediate,
+    Csi,
+    /// OSC, DCS, SOS, PM or APC, all running until BEL or ST
+    String,
+    StringEscape,
+}
+
+#[derive(Default)]
+pub struct AnsiStripper {
+    state: State,
+}
+
+impl AnsiStripper {
+    /// Append the printable text of `data` to `out`. Backspaces erase the previous
+    /// character still in `out`, so echoed line editing mostly comes out as typed
+    pub fn feed(&mut self, data: &[u8], out: &mut Vec<u8>) {
+        for &byte in data {
+            self.state = match (self.state, byte) {
+                (State::Ground, ESC) => State::Escape,
+                (State::Ground, b'\n' | b'\t') => {
+                    out.push(byte);
+                    State::Ground
+                }
+                (State::Ground, BS) => {
+                    erase_char(out);
+                    State::Ground
+                }
+                (State::Ground, 0..=0x1f | DEL) => State::Ground,
+                (State::Ground, _) => {
+                    out.push(byte);
+                    State::Ground
+                }
+                (State::Escape, b'[') => State::Csi,
+                (State::Escape, b']' | b'P' | b'X' | b'^' | b'_') => State::String,
+                (State::Escape | State::EscapeIntermediate, 0x20..=0x2f) => {
+                    State::EscapeIntermediate
+                }
+                (State::Escape | State::EscapeIntermediate, _) => State::Ground,
+                (State::Csi, 0x40..=0x7e | CAN | SUB) => State::Ground,
+                (State::Csi, ESC) => State::Escape,
+                (State::Csi, _) => State::Csi,
+                (State::String, BEL | CAN | SUB) => State::Ground,
+                (State::String, ESC) => State::StringEscape,
+                (State::String, _) => State::String,
+                (State::StringEscape, b'\\') => State::Ground,
+                (State::StringEscape, ESC) => State::Escape,
+                (State::StringEscape, _) => State::Ground,
+            };
+        }
+    }
+}
+
+/// Plain text of a complete piece of output
+pub fn strip_ansi(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    AnsiStripper::default().feed(data, &mut out);
+    out
+}
+
+/// Remove the last character, including all bytes of a multi-byte UTF-8 sequence
+fn erase_char(out: &mut Vec<u8>) {
+    if out.last().is_none_or(|&b| b == b'\n') {
+        return;
+    }
+    while let Some(b) = out.pop() {
+        if b & 0xC0 != 0x80 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_escape_sequences() {
+        let data =
+            b"\x1b[1;31mred\x1b[0m \x1b]0;title\x07plain\x1b(B \x1b]8;;http://x\x1b\\link\r\n";
+        assert_eq!(strip_ansi(data), b"red plain link\n");
+    }
+
+    #[test]
+    fn test_sequences_split_across_chunks() {
+        let mut stripper = AnsiStripper::default();
+        let mut out = Vec::new();
+        stripper.feed(b"a\x1b[3", &mut out);
+        stripper.feed(b"8;5;1mb\x1b]0;t", &mut out);
+        stripper.feed(b"itle\x07c", &mut out);
+        assert_eq!(out, b"abc");
+    }
+
+    #[test]
+    fn test_backspace_erases_characters() {
+        assert_eq!(strip_ansi(b"lss\x08 \x08"), b"ls");
+        assert_eq!(strip_ansi("é\x08x".as_bytes()), b"x");
+        assert_eq!(strip_ansi(b"a\n\x08b"), b"a\nb");
+    }
+}
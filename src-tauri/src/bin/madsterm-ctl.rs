@@ -0,0 +1,13 @@
+// Command-line client for the app's remote control socket, for scripting tabs
+fn main() {
+    #[cfg(unix)]
+    {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        std::process::exit(madsterm_lib::run_ctl(&args));
+    }
+    #[cfg(not(unix))]
+    {
+        eprintln!("madsterm-ctl: not supported on this platform");
+        std::process::exit(1);
+    }
+}
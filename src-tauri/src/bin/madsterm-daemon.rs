@@ -0,0 +1,13 @@
+// Background process that keeps terminal sessions alive while the app is closed
+fn main() {
+    #[cfg(unix)]
+    if let Err(e) = madsterm_lib::run_daemon() {
+        eprintln!("madsterm-daemon: {}", e);
+        std::process::exit(1);
+    }
+    #[cfg(not(unix))]
+    {
+        eprintln!("madsterm-daemon: not supported on this platform");
+        std::process::exit(1);
+    }
+}
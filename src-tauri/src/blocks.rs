@@ -0,0 +1,223 @@
+//! Command blocks, delimited by OSC 133 shell integration marks: `A` where the
+//! prompt starts, `B` where the command line starts, `C` where its output starts
+//! and `D;<exit code>` where it finished.
+
+use crate::ansi::strip_ansi;
+use crate::osc::percent_decode;
+use crate::scrollback::Scrollback;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Blocks kept per session; older ones are forgotten first
+const MAX_BLOCKS: usize = 1000;
+
+/// One command and its output. Offsets count bytes from the start of the session's
+/// output, so they stay valid as scrollback is trimmed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandBlock {
+    pub id: u64,
+    /// The command line as run, empty if the shell did not report it
+    pub command: String,
+    /// Offset of the block's prompt
+    pub start: u64,
+    /// Offset where the command's output begins
+    pub output_start: u64,
+    /// Offset where the output ends, `None` while the command is running
+    pub end: Option<u64>,
+    pub exit_code: Option<i32>,
+    /// When the command started, in milliseconds since the Unix epoch
+    pub started_at: u64,
+    pub duration_ms: Option<u64>,
+    /// Working directory the command ran in
+    pub cwd: Option<String>,
+}
+
+#[derive(Default)]
+pub struct BlockTracker {
+    blocks: VecDeque<CommandBlock>,
+    next_id: u64,
+    prompt_start: Option<u64>,
+    input_start: Option<u64>,
+    /// Set while the newest block's command is running
+    running: Option<Instant>,
+}
+
+impl BlockTracker {
+    /// Apply an OSC payload spanning `range` of the stream; anything but OSC 133 is ignored.
+    /// `scrollback` must already hold the output up to the end of the sequence
+    pub fn handle(
+        &mut self,
+        payload: &[u8],
+        range: Range<u64>,
+        scrollback: &Scrollback,
+        cwd: Option<&str>,
+    ) {
+        let Some(mark) = payload.strip_prefix(b"133;") else {
+            return;
+        };
+        let mut fields = mark.split(|&b| b == b';');
+        match fields.next() {
+            Some(b"A") => {
+                // A prompt without a `D` first means the shell never reported the exit
+                self.finish(range.start, None);
+                self.prompt_start = Some(range.start);
+                self.input_start = None;
+            }
+            Some(b"B") => self.input_start = Some(range.end),
+            Some(b"C") => {
+                self.finish(range.start, None);
+                let command = fields
+                    .find_map(reported_command)
+                    .or_else(|| {
+                        let typed = scrollback.range(self.input_start?, range.start);
+                        Some(
+                            String::from_utf8_lossy(&strip_ansi(&typed))
+                                .trim()
+                                .to_string(),
+                        )
+                    })
+                    .unwrap_or_default();
+                self.start_block(command, range, cwd);
+            }
+            Some(b"D") => {
+                let exit_code = fields
+                    .next()
+                    .and_then(|code| std::str::from_utf8(code).ok()?.parse().ok());
+                self.finish(range.start, exit_code);
+            }
+            _ => {}
+        }
+    }
+
+    fn start_block(&mut self, command: String, range: Range<u64>, cwd: Option<&str>) {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        if self.blocks.len() == MAX_BLOCKS {
+            self.blocks.pop_front();
+        }
+        self.blocks.push_back(CommandBlock {
+            id: self.next_id,
+            command,
+            start: self.prompt_start.take().unwrap_or(range.start),
+            output_start: range.end,
+            end: None,
+            exit_code: None,
+            started_at,
+            duration_ms: None,
+            cwd: cwd.map(str::to_string),
+        });
+        self.next_id += 1;
+        self.input_start = None;
+        self.running = Some(Instant::now());
+    }
+
+    fn finish(&mut self, end: u64, exit_code: Option<i32>) {
+        let Some(started) = self.running.take() else {
+            return;
+        };
+        if let Some(block) = self.blocks.back_mut() {
+            block.end = Some(end);
+            block.exit_code = exit_code;
+            block.duration_ms = Some(started.elapsed().as_millis() as u64);
+        }
+    }
+
+    pub fn list(&self) -> Vec<CommandBlock> {
+        self.blocks.iter().cloned().collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&CommandBlock> {
+        self.blocks.iter().find(|block| block.id == id)
+    }
+}
+
+/// Command line passed along with `C`, as `cmdline=<text>` or `cmdline_url=<percent-encoded>`
+fn reported_command(field: &[u8]) -> Option<String> {
+    let field = std::str::from_utf8(field).ok()?;
+    if let Some(encoded) = field.strip_prefix("cmdline_url=") {
+        return percent_decode(encoded);
+    }
+    field.strip_prefix("cmdline=").map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osc::{OscScanner, Sequence};
+    use crate::scrollback::ScrollbackLimits;
+
+    struct Session {
+        scrollback: Scrollback,
+        osc: OscScanner,
+        blocks: BlockTracker,
+    }
+
+    impl Session {
+        fn new() -> Self {
+            Session {
+                scrollback: Scrollback::new(ScrollbackLimits::default()),
+                osc: OscScanner::default(),
+                blocks: BlockTracker::default(),
+            }
+        }
+
+        fn feed(&mut self, data: &[u8]) {
+            self.scrollback.push(data);
+            let (scrollback, blocks) = (&self.scrollback, &mut self.blocks);
+            self.osc.feed(data, |sequence| {
+                if let Sequence::Osc { payload, range } = sequence {
+                    blocks.handle(payload, range, scrollback, Some("/tmp"))
+                }
+            });
+        }
+
+        fn output(&self, block: &CommandBlock) -> Vec<u8> {
+            let end = block.end.unwrap_or(self.scrollback.end_offset());
+            self.scrollback.range(block.output_start, end)
+        }
+    }
+
+    #[test]
+    fn test_records_typed_command_and_output() {
+        let mut session = Session::new();
+        session.feed(b"\x1b]133;A\x07$ \x1b]133;B\x07ls -x\x08la\r\n\x1b]133;C\x07");
+        session.feed(b"file1\r\nfile2\r\n\x1b]133;D;2\x07\x1b]133;A\x07$ ");
+
+        let blocks = session.blocks.list();
+        assert_eq!(blocks.len(), 1);
+        let block = &blocks[0];
+        assert_eq!(block.command, "ls -la");
+        assert_eq!(block.start, 0);
+        assert_eq!(block.exit_code, Some(2));
+        assert!(block.duration_ms.is_some());
+        assert_eq!(block.cwd.as_deref(), Some("/tmp"));
+        assert_eq!(session.output(block), b"file1\r\nfile2\r\n");
+    }
+
+    #[test]
+    fn test_running_block_is_open() {
+        let mut session = Session::new();
+        session.feed(b"\x1b]133;A\x07$ \x1b]133;B\x07sleep 9\r\n\x1b]133;C\x07zz");
+        let block = session.blocks.get(0).unwrap();
+        assert_eq!(block.end, None);
+        assert_eq!(block.exit_code, None);
+        assert_eq!(session.output(block), b"zz");
+    }
+
+    #[test]
+    fn test_reported_command_wins_and_missing_d_closes_block() {
+        let mut session = Session::new();
+        session.feed(b"\x1b]133;A\x07\x1b]133;B\x07x\x1b]133;C;cmdline_url=echo%20hi\x07hi\n");
+        session.feed(b"\x1b]133;A\x07");
+        let block = session.blocks.get(0).unwrap();
+        assert_eq!(block.command, "echo hi");
+        assert!(block.end.is_some());
+        assert_eq!(block.exit_code, None);
+        assert_eq!(session.output(block), b"hi\n");
+    }
+}
@@ -47,7 +47,7 @@ const SKIP_DIRS: &[&str] = &[
     "__pycache__",
 ];
 
-fn build_search_pattern(
+pub(crate) fn build_search_pattern(
     query: &str,
     case_sensitive: bool,
     whole_word: bool,
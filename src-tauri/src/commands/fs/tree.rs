@@ -83,8 +83,8 @@ pub fn build_tree(
         }
 
         // Sort: directories first (alphabetical), then files (alphabetical)
-        dirs.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-        files.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        dirs.sort_by_key(|a| a.name.to_lowercase());
+        files.sort_by_key(|a| a.name.to_lowercase());
 
         dirs.append(&mut files);
         Some(dirs)
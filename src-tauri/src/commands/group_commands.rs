@@ -0,0 +1,104 @@
+use crate::commands::pty_commands::validate_session_id;
+use crate::daemon::DaemonState;
+use crate::error::AppError;
+use crate::groups::SessionGroup;
+use crate::pty_manager::PtyManager;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tauri::State;
+
+type PtyState = Arc<Mutex<PtyManager>>;
+
+fn validate_group_id(group_id: &str) -> Result<(), AppError> {
+    if uuid::Uuid::parse_str(group_id).is_err() {
+        return Err(AppError::InvalidInput(
+            "Invalid group ID format".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn validate_members(session_ids: &[String]) -> Result<(), AppError> {
+    if session_ids.is_empty() {
+        return Err(AppError::InvalidInput(
+            "A group needs at least one session".to_string(),
+        ));
+    }
+    session_ids
+        .iter()
+        .try_for_each(|id| validate_session_id(id))
+}
+
+/// Group sessions so that input written to any of them goes to all. Returns the group ID
+#[tauri::command]
+pub fn pty_group_create(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_ids: Vec<String>,
+) -> Result<String, AppError> {
+    validate_members(&session_ids)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.create_group(&session_ids);
+    }
+    state.lock().create_group(&session_ids)
+}
+
+/// Replace the sessions in a group
+#[tauri::command]
+pub fn pty_group_set_members(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    group_id: String,
+    session_ids: Vec<String>,
+) -> Result<(), AppError> {
+    validate_group_id(&group_id)?;
+    validate_members(&session_ids)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.set_group_members(&group_id, &session_ids);
+    }
+    state.lock().set_group_members(&group_id, &session_ids)
+}
+
+#[tauri::command]
+pub fn pty_group_dissolve(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    group_id: String,
+) -> Result<(), AppError> {
+    validate_group_id(&group_id)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.dissolve_group(&group_id);
+    }
+    state.lock().dissolve_group(&group_id)
+}
+
+#[tauri::command]
+pub fn pty_group_list(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+) -> Result<Vec<SessionGroup>, AppError> {
+    if let Some(client) = daemon.as_ref() {
+        return client.groups();
+    }
+    Ok(state.lock().groups())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_group_id() {
+        assert!(validate_group_id(&uuid::Uuid::new_v4().to_string()).is_ok());
+        let err = validate_group_id("../etc").unwrap_err();
+        assert_eq!(err.to_string(), "Invalid group ID format");
+    }
+
+    #[test]
+    fn test_validate_members() {
+        let id = uuid::Uuid::new_v4().to_string();
+        assert!(validate_members(&[id]).is_ok());
+        assert!(validate_members(&[]).is_err());
+        assert!(validate_members(&["../etc".to_string()]).is_err());
+    }
+}
@@ -1,3 +1,10 @@
 pub mod fs;
 pub mod git_commands;
+pub mod group_commands;
+pub mod monitor_commands;
+pub mod profile_commands;
 pub mod pty_commands;
+pub mod recording_commands;
+pub mod run_commands;
+pub mod trigger_commands;
+pub mod workspace_commands;
@@ -0,0 +1,70 @@
+use crate::commands::pty_commands::validate_session_id;
+use crate::daemon::DaemonState;
+use crate::error::AppError;
+use crate::monitor::{MonitorSettings, MonitorStatus};
+use crate::pty_manager::PtyManager;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tauri::State;
+
+type PtyState = Arc<Mutex<PtyManager>>;
+
+const MAX_SILENCE_SECS: u64 = 24 * 60 * 60;
+
+fn validate_settings(settings: &MonitorSettings) -> Result<(), AppError> {
+    match settings.silence_secs {
+        Some(secs) if secs == 0 || secs > MAX_SILENCE_SECS => Err(AppError::InvalidInput(format!(
+            "Invalid silence threshold: {} (must be 1-{} seconds)",
+            secs, MAX_SILENCE_SECS
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Watch a session for activity or silence, reported as `pty-monitor-{id}` events.
+/// Clears earlier alerts and the bell count, e.g. once the user has seen the session
+#[tauri::command]
+pub fn pty_set_monitor(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+    settings: MonitorSettings,
+) -> Result<(), AppError> {
+    validate_session_id(&session_id)?;
+    validate_settings(&settings)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.set_monitor(&session_id, settings);
+    }
+    state.lock().set_monitor(&session_id, settings)
+}
+
+#[tauri::command]
+pub fn pty_monitor_status(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<MonitorStatus, AppError> {
+    validate_session_id(&session_id)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.monitor_status(&session_id);
+    }
+    state.lock().monitor_status(&session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_settings() {
+        let silence = |secs| MonitorSettings {
+            activity: true,
+            silence_secs: secs,
+        };
+        assert!(validate_settings(&silence(None)).is_ok());
+        assert!(validate_settings(&silence(Some(30))).is_ok());
+        assert!(validate_settings(&silence(Some(MAX_SILENCE_SECS))).is_ok());
+        assert!(validate_settings(&silence(Some(0))).is_err());
+        assert!(validate_settings(&silence(Some(MAX_SILENCE_SECS + 1))).is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use crate::commands::pty_commands::{validate_profile, validate_profile_name};
+use crate::config::ConfigState;
+use crate::error::AppError;
+use crate::profile::LaunchProfile;
+use tauri::State;
+
+/// Why the config file couldn't be read at startup, if it couldn't. Settings
+/// changes are refused until it is fixed, so the file isn't overwritten
+#[tauri::command]
+pub fn config_load_error(config: State<'_, ConfigState>) -> Option<String> {
+    config.lock().load_error()
+}
+
+#[tauri::command]
+pub fn profile_list(config: State<'_, ConfigState>) -> Vec<LaunchProfile> {
+    config.lock().config.profiles.clone()
+}
+
+/// Create or replace a launch profile by name
+#[tauri::command]
+pub fn profile_save(
+    config: State<'_, ConfigState>,
+    profile: LaunchProfile,
+) -> Result<(), AppError> {
+    validate_profile_name(&profile.name)?;
+    validate_profile(&profile)?;
+    let mut store = config.lock();
+    match store
+        .config
+        .profiles
+        .iter_mut()
+        .find(|p| p.name == profile.name)
+    {
+        Some(existing) => *existing = profile,
+        None => store.config.profiles.push(profile),
+    }
+    store.save()
+}
+
+#[tauri::command]
+pub fn profile_delete(config: State<'_, ConfigState>, name: String) -> Result<(), AppError> {
+    validate_profile_name(&name)?;
+    let mut store = config.lock();
+    let before = store.config.profiles.len();
+    store.config.profiles.retain(|p| p.name != name);
+    if store.config.profiles.len() == before {
+        return Err(AppError::NotFound(format!("Profile {} not found", name)));
+    }
+    if store.config.default_profile.as_deref() == Some(name.as_str()) {
+        store.config.default_profile = None;
+    }
+    store.save()
+}
+
+#[tauri::command]
+pub fn profile_set_default(
+    config: State<'_, ConfigState>,
+    name: Option<String>,
+) -> Result<(), AppError> {
+    let mut store = config.lock();
+    if let Some(ref name) = name {
+        if store.config.profile(name).is_none() {
+            return Err(AppError::NotFound(format!("Profile {} not found", name)));
+        }
+    }
+    store.config.default_profile = name;
+    store.save()
+}
@@ -1,13 +1,25 @@
+use crate::ansi::strip_ansi;
+use crate::blocks::CommandBlock;
+use crate::config::ConfigState;
+use crate::daemon::DaemonState;
 use crate::error::AppError;
-use crate::pty_manager::PtyManager;
+use crate::groups::WriteFailure;
+use crate::launch_args::{LaunchArgs, LaunchArgsState};
+use crate::process_info::ForegroundProcess;
+use crate::profile::LaunchProfile;
+use crate::pty_manager::{CellSize, ChannelSink, PtyManager, SessionSink, SessionSummary};
+use crate::session_log::LogSettings;
+use crate::signal::PtySignal;
 use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tauri::{AppHandle, State};
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::{AppHandle, Manager, State};
 
 type PtyState = Arc<Mutex<PtyManager>>;
 
 /// Validate that a session ID looks like a UUID v4
-fn validate_session_id(session_id: &str) -> Result<(), AppError> {
+pub(crate) fn validate_session_id(session_id: &str) -> Result<(), AppError> {
     if uuid::Uuid::parse_str(session_id).is_err() {
         return Err(AppError::InvalidInput(
             "Invalid session ID format".to_string(),
@@ -33,9 +45,29 @@ fn validate_dimensions(cols: u16, rows: u16) -> Result<(), AppError> {
     Ok(())
 }
 
+/// Validate cell pixel metrics, which are optional since not every caller knows them
+fn validate_cell_size(cell: Option<CellSize>) -> Result<(), AppError> {
+    let Some(cell) = cell else {
+        return Ok(());
+    };
+    if cell.width == 0 || cell.width > 256 {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid cell width: {} (must be 1-256)",
+            cell.width
+        )));
+    }
+    if cell.height == 0 || cell.height > 256 {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid cell height: {} (must be 1-256)",
+            cell.height
+        )));
+    }
+    Ok(())
+}
+
 /// Validate that a cwd path is an existing directory with no path traversal
-fn validate_cwd(cwd: &str) -> Result<(), AppError> {
-    let path = std::path::Path::new(cwd);
+pub(crate) fn validate_cwd(cwd: &str) -> Result<(), AppError> {
+    let path = Path::new(cwd);
     // Must be absolute path
     if !path.is_absolute() {
         return Err(AppError::InvalidInput(
@@ -55,53 +87,506 @@ fn validate_cwd(cwd: &str) -> Result<(), AppError> {
     Ok(())
 }
 
+/// Look up a bare program name in PATH
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Validate that a program is an executable file, given as an absolute path or a name on PATH
+pub(crate) fn validate_program(program: &str) -> Result<(), AppError> {
+    if program.is_empty() || program.contains('\0') {
+        return Err(AppError::InvalidInput("Invalid program name".to_string()));
+    }
+    let path = Path::new(program);
+    let resolved = if path.is_absolute() {
+        path.to_path_buf()
+    } else if program.contains(std::path::MAIN_SEPARATOR) {
+        return Err(AppError::InvalidInput(
+            "Program must be an absolute path or a name on PATH".to_string(),
+        ));
+    } else {
+        find_in_path(program)
+            .ok_or_else(|| AppError::NotFound(format!("Program not found: {}", program)))?
+    };
+    let meta = resolved
+        .metadata()
+        .map_err(|_| AppError::NotFound(format!("Program not found: {}", program)))?;
+    if !meta.is_file() {
+        return Err(AppError::InvalidInput(format!(
+            "Program is not a file: {}",
+            program
+        )));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if meta.permissions().mode() & 0o111 == 0 {
+            return Err(AppError::InvalidInput(format!(
+                "Program is not executable: {}",
+                program
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Validate that an environment variable name can be passed to a child process
+pub(crate) fn validate_env_key(key: &str) -> Result<(), AppError> {
+    if key.is_empty() || key.contains('=') || key.contains('\0') {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid environment variable name: {:?}",
+            key
+        )));
+    }
+    Ok(())
+}
+
+/// Validate a signal name sent from the frontend
+fn validate_signal(signal: &str) -> Result<PtySignal, AppError> {
+    PtySignal::from_name(signal)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unsupported signal: {:?}", signal)))
+}
+
+/// Validate a profile name used to save or look up a launch profile
+pub(crate) fn validate_profile_name(name: &str) -> Result<(), AppError> {
+    if name.trim().is_empty() || name.len() > 64 || name.chars().any(char::is_control) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid profile name: {:?} (must be 1-64 printable characters)",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Validate everything a launch profile hands to the spawned process
+pub(crate) fn validate_profile(profile: &LaunchProfile) -> Result<(), AppError> {
+    if let Some(program) = &profile.program {
+        validate_program(program)?;
+    }
+    if profile.args.iter().any(|arg| arg.contains('\0')) {
+        return Err(AppError::InvalidInput(
+            "Arguments must not contain NUL bytes".to_string(),
+        ));
+    }
+    for (key, value) in &profile.env {
+        validate_env_key(key)?;
+        if value.contains('\0') {
+            return Err(AppError::InvalidInput(format!(
+                "Environment variable {} must not contain NUL bytes",
+                key
+            )));
+        }
+    }
+    for key in &profile.env_remove {
+        validate_env_key(key)?;
+    }
+    if let Some(dir) = &profile.cwd {
+        if !dir.is_empty() {
+            validate_cwd(dir)?;
+        }
+    }
+    Ok(())
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn pty_create(
     state: State<'_, PtyState>,
+    config: State<'_, ConfigState>,
+    daemon: State<'_, DaemonState>,
     app: AppHandle,
     cols: u16,
     rows: u16,
+    cell: Option<CellSize>,
     cwd: Option<String>,
+    profile: Option<String>,
+    launch: Option<LaunchProfile>,
+    command: Option<Vec<String>>,
+    on_output: Channel<InvokeResponseBody>,
+) -> Result<String, AppError> {
+    let options = SessionOptions {
+        cols,
+        rows,
+        cell,
+        cwd,
+        profile,
+        launch,
+        command: command.unwrap_or_default(),
+    };
+    let sink = Arc::new(ChannelSink::new(app.clone(), on_output));
+    start_session(&state, &config, &daemon, &app, options, sink)
+}
+
+/// How to start a session, as given to `pty_create`
+pub(crate) struct SessionOptions {
+    pub cols: u16,
+    pub rows: u16,
+    pub cell: Option<CellSize>,
+    pub cwd: Option<String>,
+    pub profile: Option<String>,
+    pub launch: Option<LaunchProfile>,
+    /// Program and arguments to run in place of the profile's
+    pub command: Vec<String>,
+}
+
+/// Start a session with the configured launch profile, scrollback, triggers and
+/// logging, wherever sessions are hosted
+pub(crate) fn start_session(
+    state: &PtyState,
+    config: &ConfigState,
+    daemon: &DaemonState,
+    app: &AppHandle,
+    options: SessionOptions,
+    sink: Arc<dyn SessionSink>,
 ) -> Result<String, AppError> {
+    let SessionOptions {
+        cols,
+        rows,
+        cell,
+        cwd,
+        profile,
+        launch,
+        command,
+    } = options;
     validate_dimensions(cols, rows)?;
-    if let Some(ref dir) = cwd {
-        if !dir.is_empty() {
-            validate_cwd(dir)?;
+    validate_cell_size(cell)?;
+    let mut launch = config
+        .lock()
+        .config
+        .resolve_launch(profile.as_deref(), launch)?;
+    if let Some(dir) = cwd.filter(|dir| !dir.is_empty()) {
+        launch.cwd = Some(dir);
+    }
+    if let Some((program, args)) = command.split_first() {
+        launch.program = Some(program.clone());
+        launch.args = args.to_vec();
+        launch.login = None;
+        launch.one_off = true;
+    }
+    validate_profile(&launch)?;
+    let (scrollback_limits, log_settings, triggers) = {
+        let store = config.lock();
+        (
+            store.config.scrollback.clone(),
+            store.config.session_log.clone(),
+            store.triggers(),
+        )
+    };
+    let session_id = match daemon.as_ref() {
+        Some(client) => {
+            client.create_session(cols, rows, cell, &launch, &scrollback_limits, sink)?
+        }
+        None => state
+            .lock()
+            .create_session(cols, rows, cell, &launch, &scrollback_limits, sink)?,
+    };
+    if !triggers.is_empty() {
+        let set = match daemon.as_ref() {
+            Some(client) => client.set_triggers(&session_id, triggers.rules()),
+            None => state.lock().set_triggers(&session_id, triggers),
+        };
+        if let Err(e) = set {
+            log::warn!("No triggers for session {}: {}", session_id, e);
         }
     }
-    let mut mgr = state.lock();
-    mgr.create_session(cols, rows, cwd, app)
+    if log_settings.enabled {
+        // A session that can't be logged is still usable, so this only warns
+        let started =
+            log_directory(app, None, &log_settings).and_then(|dir| match daemon.as_ref() {
+                Some(client) => client.start_log(&session_id, &dir, &log_settings.rotation),
+                None => state
+                    .lock()
+                    .start_log(&session_id, &dir, &log_settings.rotation),
+            });
+        if let Err(e) = started {
+            log::warn!("Not logging session {}: {}", session_id, e);
+        }
+    }
+    Ok(session_id)
 }
 
+/// Write input to a session, or to every session in its broadcast group. Returns the
+/// group members the input could not be written to
 #[tauri::command]
 pub fn pty_write(
     state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
     session_id: String,
     data: String,
-) -> Result<(), AppError> {
+) -> Result<Vec<WriteFailure>, AppError> {
     validate_session_id(&session_id)?;
-    let mut mgr = state.lock();
-    mgr.write(&session_id, &data)
+    if let Some(client) = daemon.as_ref() {
+        return client.write(&session_id, &data);
+    }
+    // Written after the lock is released, so other sessions aren't held up meanwhile
+    let targets = state.lock().input_targets(&session_id)?;
+    targets.write(&data)
 }
 
 #[tauri::command]
 pub fn pty_resize(
     state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
     session_id: String,
     cols: u16,
     rows: u16,
+    cell: Option<CellSize>,
 ) -> Result<(), AppError> {
     validate_session_id(&session_id)?;
     validate_dimensions(cols, rows)?;
+    validate_cell_size(cell)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.resize(&session_id, cols, rows, cell);
+    }
     let mut mgr = state.lock();
-    mgr.resize(&session_id, cols, rows)
+    mgr.resize(&session_id, cols, rows, cell)
 }
 
+/// The job in the foreground of a session, e.g. to warn before closing a busy tab.
+/// Changes are also pushed as `pty-foreground-{id}` events
 #[tauri::command]
-pub fn pty_close(state: State<'_, PtyState>, session_id: String) -> Result<(), AppError> {
+pub fn pty_foreground_process(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<Option<ForegroundProcess>, AppError> {
     validate_session_id(&session_id)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.foreground(&session_id);
+    }
+    state.lock().foreground(&session_id)
+}
+
+/// The session's current working directory, from OSC 7 or the foreground job.
+/// Changes are also pushed as `pty-cwd-{id}` events
+#[tauri::command]
+pub fn pty_get_cwd(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<Option<String>, AppError> {
+    validate_session_id(&session_id)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.cwd(&session_id);
+    }
+    state.lock().cwd(&session_id)
+}
+
+/// Send a signal such as `SIGINT` or `SIGKILL` to the session's foreground process group
+#[tauri::command]
+pub fn pty_signal(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+    signal: String,
+) -> Result<(), AppError> {
+    validate_session_id(&session_id)?;
+    let signal = validate_signal(&signal)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.signal(&session_id, signal);
+    }
+    state.lock().signal(&session_id, signal)
+}
+
+/// Acknowledge output the terminal has finished processing, so the backend keeps
+/// reading the PTY; see the watermarks in `pty_manager`
+#[tauri::command]
+pub fn pty_ack(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+    bytes: usize,
+) -> Result<(), AppError> {
+    validate_session_id(&session_id)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.ack(&session_id, bytes);
+    }
+    state.lock().ack(&session_id, bytes)
+}
+
+/// Recent output of a session, wherever sessions are hosted
+pub(crate) fn scrollback(
+    state: &PtyState,
+    daemon: &DaemonState,
+    session_id: &str,
+) -> Result<Vec<u8>, AppError> {
+    validate_session_id(session_id)?;
+    match daemon.as_ref() {
+        Some(client) => client.scrollback(session_id),
+        None => state.lock().scrollback(session_id),
+    }
+}
+
+/// Recent output of a session, for rehydrating a terminal after a reload or remount.
+/// Sent as raw bytes, so UTF-8 sequences cut off at the start are left to the terminal
+#[tauri::command]
+pub fn pty_get_scrollback(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<tauri::ipc::Response, AppError> {
+    scrollback(&state, &daemon, &session_id).map(tauri::ipc::Response::new)
+}
+
+/// Commands the shell marked with OSC 133, for collapsing, copying or re-running them
+#[tauri::command]
+pub fn pty_list_blocks(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<Vec<CommandBlock>, AppError> {
+    validate_session_id(&session_id)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.blocks(&session_id);
+    }
+    state.lock().blocks(&session_id)
+}
+
+/// Output of one command block. With `plain`, escape sequences are stripped for copying
+#[tauri::command]
+pub fn pty_get_block_output(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+    block_id: u64,
+    plain: Option<bool>,
+) -> Result<String, AppError> {
+    validate_session_id(&session_id)?;
+    let mut contents = match daemon.as_ref() {
+        Some(client) => client.block_output(&session_id, block_id)?,
+        None => state.lock().block_output(&session_id, block_id)?,
+    };
+    if plain.unwrap_or(false) {
+        contents = strip_ansi(&contents);
+    }
+    Ok(String::from_utf8_lossy(&contents).into_owned())
+}
+
+/// Directory for session logs: the one asked for, else the configured one, else
+/// `sessions` in the app's log directory
+fn log_directory(
+    app: &AppHandle,
+    requested: Option<String>,
+    settings: &LogSettings,
+) -> Result<PathBuf, AppError> {
+    let Some(dir) = requested.or_else(|| settings.directory.clone()) else {
+        let logs = app
+            .path()
+            .app_log_dir()
+            .map_err(|e| AppError::Io(e.to_string()))?;
+        return Ok(logs.join("sessions"));
+    };
+    let dir = PathBuf::from(dir);
+    if !dir.is_absolute() {
+        return Err(AppError::InvalidInput(
+            "Log directory must be an absolute path".to_string(),
+        ));
+    }
+    Ok(dir)
+}
+
+/// Start logging a session's output as plain text, returning the log file's path
+#[tauri::command]
+pub fn pty_start_log(
+    state: State<'_, PtyState>,
+    config: State<'_, ConfigState>,
+    daemon: State<'_, DaemonState>,
+    app: AppHandle,
+    session_id: String,
+    directory: Option<String>,
+) -> Result<String, AppError> {
+    validate_session_id(&session_id)?;
+    let settings = config.lock().config.session_log.clone();
+    let dir = log_directory(&app, directory, &settings)?;
+    let path = match daemon.as_ref() {
+        Some(client) => client.start_log(&session_id, &dir, &settings.rotation)?,
+        None => state
+            .lock()
+            .start_log(&session_id, &dir, &settings.rotation)?,
+    };
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Stop logging a session, returning the log file's path
+#[tauri::command]
+pub fn pty_stop_log(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<String, AppError> {
+    validate_session_id(&session_id)?;
+    let path = match daemon.as_ref() {
+        Some(client) => client.stop_log(&session_id)?,
+        None => state.lock().stop_log(&session_id)?,
+    };
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+pub fn pty_close(
+    state: State<'_, PtyState>,
+    config: State<'_, ConfigState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<(), AppError> {
+    validate_session_id(&session_id)?;
+    let grace = config.lock().config.close_grace();
+    if let Some(client) = daemon.as_ref() {
+        return client.close(&session_id, grace);
+    }
     let mut mgr = state.lock();
-    mgr.close(&session_id)
+    mgr.close(&session_id, grace)
+}
+
+/// Every session the backend holds, wherever it is hosted, oldest first. Includes
+/// sessions whose process exited but that were never closed
+#[tauri::command]
+pub fn pty_list(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+) -> Result<Vec<SessionSummary>, AppError> {
+    if let Some(client) = daemon.as_ref() {
+        return client.list();
+    }
+    Ok(state.lock().list())
+}
+
+/// What the app was started with, for the first tab to open. Returns `None` once
+/// taken, or when the app was started without arguments
+#[tauri::command]
+pub fn take_launch_args(launch: State<'_, LaunchArgsState>) -> Option<LaunchArgs> {
+    launch.lock().take().filter(|args| !args.is_empty())
+}
+
+/// Sessions kept alive by the daemon, so a restarted app can reattach to them
+#[tauri::command]
+pub fn pty_daemon_sessions(
+    daemon: State<'_, DaemonState>,
+) -> Result<Vec<SessionSummary>, AppError> {
+    match daemon.as_ref() {
+        Some(client) => client.list(),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Route an existing session's output to this window, replaying its scrollback first
+#[tauri::command]
+pub fn pty_attach(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    app: AppHandle,
+    session_id: String,
+    on_output: Channel<InvokeResponseBody>,
+) -> Result<(), AppError> {
+    validate_session_id(&session_id)?;
+    let sink = Arc::new(ChannelSink::new(app, on_output));
+    if let Some(client) = daemon.as_ref() {
+        return client.attach(&session_id, sink);
+    }
+    state.lock().attach(&session_id, sink)
 }
 
 #[cfg(test)]
@@ -136,6 +621,15 @@ mod tests {
         assert!(validate_dimensions(80, 501).is_err());
     }
 
+    #[test]
+    fn test_validate_cell_size() {
+        let cell = |width, height| Some(CellSize { width, height });
+        assert!(validate_cell_size(None).is_ok());
+        assert!(validate_cell_size(cell(9, 18)).is_ok());
+        assert!(validate_cell_size(cell(0, 18)).is_err());
+        assert!(validate_cell_size(cell(9, 257)).is_err());
+    }
+
     #[test]
     fn test_validate_cwd_valid() {
         // Home directory should exist
@@ -152,4 +646,63 @@ mod tests {
     fn test_validate_cwd_relative() {
         assert!(validate_cwd("relative/path").is_err());
     }
+
+    #[test]
+    fn test_validate_program_on_path() {
+        assert!(validate_program("sh").is_ok());
+        assert!(validate_program("/bin/sh").is_ok());
+    }
+
+    #[test]
+    fn test_validate_program_invalid() {
+        assert!(validate_program("").is_err());
+        assert!(validate_program("definitely-not-a-real-program-xyz").is_err());
+        assert!(validate_program("relative/sh").is_err());
+        assert!(validate_program("/tmp").is_err());
+    }
+
+    #[test]
+    fn test_validate_env_key() {
+        assert!(validate_env_key("EDITOR").is_ok());
+        assert!(validate_env_key("").is_err());
+        assert!(validate_env_key("A=B").is_err());
+    }
+
+    #[test]
+    fn test_validate_signal() {
+        assert_eq!(validate_signal("SIGKILL").unwrap(), PtySignal::Sigkill);
+        assert_eq!(validate_signal("int").unwrap(), PtySignal::Sigint);
+        assert!(matches!(
+            validate_signal("SIGSEGV"),
+            Err(AppError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_profile_name() {
+        assert!(validate_profile_name("fish").is_ok());
+        assert!(validate_profile_name("  ").is_err());
+        assert!(validate_profile_name("bad\nname").is_err());
+        assert!(validate_profile_name(&"x".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_validate_profile() {
+        let profile = LaunchProfile {
+            program: Some("sh".to_string()),
+            cwd: Some("/tmp".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_profile(&profile).is_ok());
+
+        let bad_cwd = LaunchProfile {
+            cwd: Some("relative".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_profile(&bad_cwd).is_err());
+
+        let mut bad_env = LaunchProfile::default();
+        bad_env.env.insert("A=B".to_string(), "c".to_string());
+        assert!(validate_profile(&bad_env).is_err());
+    }
 }
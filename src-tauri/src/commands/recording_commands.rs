@@ -0,0 +1,179 @@
+use crate::commands::pty_commands::validate_session_id;
+use crate::daemon::DaemonState;
+use crate::error::AppError;
+use crate::pty_manager::{ChannelSink, PtyManager};
+use crate::recording::{self, PlaybackState};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::{AppHandle, State};
+
+type PtyState = Arc<Mutex<PtyManager>>;
+
+const MIN_PLAYBACK_SPEED: f64 = 0.1;
+const MAX_PLAYBACK_SPEED: f64 = 64.0;
+
+/// Validate where a new recording is written: an absolute path in an existing directory
+fn validate_recording_path(path: &str) -> Result<PathBuf, AppError> {
+    let path = Path::new(path);
+    if !path.is_absolute() || path.as_os_str().is_empty() {
+        return Err(AppError::InvalidInput(
+            "Recording path must be an absolute path".to_string(),
+        ));
+    }
+    if path.is_dir() {
+        return Err(AppError::InvalidInput(format!(
+            "Recording path is a directory: {}",
+            path.display()
+        )));
+    }
+    match path.parent() {
+        Some(parent) if parent.is_dir() => Ok(path.to_path_buf()),
+        _ => Err(AppError::NotFound(format!(
+            "Directory does not exist for recording: {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Validate a playback speed multiplier
+fn validate_speed(speed: f64) -> Result<(), AppError> {
+    if !(MIN_PLAYBACK_SPEED..=MAX_PLAYBACK_SPEED).contains(&speed) {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid playback speed: {} (must be {}-{})",
+            speed, MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED
+        )));
+    }
+    Ok(())
+}
+
+/// Start recording a session to an asciinema v2 `.cast` file, replacing any file at `path`
+#[tauri::command]
+pub fn pty_start_recording(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+    path: String,
+) -> Result<(), AppError> {
+    validate_session_id(&session_id)?;
+    let path = validate_recording_path(&path)?;
+    if let Some(client) = daemon.as_ref() {
+        return client.start_recording(&session_id, &path);
+    }
+    state.lock().start_recording(&session_id, &path)
+}
+
+/// Stop recording a session, returning the path of the finished recording
+#[tauri::command]
+pub fn pty_stop_recording(
+    state: State<'_, PtyState>,
+    daemon: State<'_, DaemonState>,
+    session_id: String,
+) -> Result<String, AppError> {
+    validate_session_id(&session_id)?;
+    let path = match daemon.as_ref() {
+        Some(client) => client.stop_recording(&session_id)?,
+        None => state.lock().stop_recording(&session_id)?,
+    };
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackInfo {
+    /// Identifies the playback in `pty-exit-{id}` and the playback commands
+    pub id: String,
+    /// Terminal size the recording was made at
+    pub cols: u16,
+    pub rows: u16,
+}
+
+/// Replay a `.cast` recording into a read-only terminal. Output arrives on
+/// `on_output` like a live session's, followed by a `pty-exit-{id}` event
+#[tauri::command]
+pub fn pty_play_recording(
+    playbacks: State<'_, PlaybackState>,
+    app: AppHandle,
+    path: String,
+    speed: Option<f64>,
+    on_output: Channel<InvokeResponseBody>,
+) -> Result<PlaybackInfo, AppError> {
+    let speed = speed.unwrap_or(1.0);
+    validate_speed(speed)?;
+    let path = Path::new(&path);
+    if !path.is_absolute() || !path.is_file() {
+        return Err(AppError::NotFound(format!(
+            "Recording not found: {}",
+            path.display()
+        )));
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    let sink = Arc::new(ChannelSink::new(app, on_output));
+    let (header, _) = recording::play(path, id.clone(), speed, sink, &playbacks)?;
+    Ok(PlaybackInfo {
+        id,
+        cols: header.width,
+        rows: header.height,
+    })
+}
+
+#[tauri::command]
+pub fn pty_set_playback_speed(
+    playbacks: State<'_, PlaybackState>,
+    playback_id: String,
+    speed: f64,
+) -> Result<(), AppError> {
+    validate_speed(speed)?;
+    let playbacks = playbacks.lock();
+    let playback = playbacks
+        .get(&playback_id)
+        .ok_or_else(|| AppError::NotFound(format!("Playback {} not found", playback_id)))?;
+    playback.set_speed(speed);
+    Ok(())
+}
+
+/// Stop a playback. One that already finished is forgotten and not found
+#[tauri::command]
+pub fn pty_stop_playback(
+    playbacks: State<'_, PlaybackState>,
+    playback_id: String,
+) -> Result<(), AppError> {
+    let playback = playbacks
+        .lock()
+        .remove(&playback_id)
+        .ok_or_else(|| AppError::NotFound(format!("Playback {} not found", playback_id)))?;
+    playback.stop();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_recording_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("demo.cast");
+        assert!(validate_recording_path(file.to_str().unwrap()).is_ok());
+        assert!(validate_recording_path("demo.cast").is_err());
+        assert!(validate_recording_path(dir.path().to_str().unwrap()).is_err());
+        let missing = dir.path().join("missing").join("demo.cast");
+        assert!(matches!(
+            validate_recording_path(missing.to_str().unwrap()),
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_speed() {
+        assert!(validate_speed(1.0).is_ok());
+        assert!(validate_speed(0.1).is_ok());
+        assert!(validate_speed(64.0).is_ok());
+        assert!(validate_speed(0.0).is_err());
+        assert!(validate_speed(-2.0).is_err());
+        assert!(validate_speed(f64::NAN).is_err());
+        assert!(validate_speed(f64::INFINITY).is_err());
+    }
+}
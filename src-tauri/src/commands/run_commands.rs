@@ -0,0 +1,138 @@
+use crate::commands::pty_commands::{validate_cwd, validate_env_key, validate_program};
+use crate::error::AppError;
+use crate::runner::{self, RunControl, RunRequest, RunResult, RunSink, RunState};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::State;
+
+/// Longest timeout a command may be given
+const MAX_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Sends a command's stdout and stderr to the webview over separate channels
+struct ChannelRunSink {
+    stdout: Channel<InvokeResponseBody>,
+    stderr: Channel<InvokeResponseBody>,
+}
+
+impl RunSink for ChannelRunSink {
+    fn stdout(&self, data: &[u8]) {
+        let _ = self.stdout.send(InvokeResponseBody::Raw(data.to_vec()));
+    }
+
+    fn stderr(&self, data: &[u8]) {
+        let _ = self.stderr.send(InvokeResponseBody::Raw(data.to_vec()));
+    }
+}
+
+/// Validate a run ID chosen by the frontend, which must look like a UUID
+fn validate_run_id(run_id: &str) -> Result<(), AppError> {
+    if uuid::Uuid::parse_str(run_id).is_err() {
+        return Err(AppError::InvalidInput("Invalid run ID format".to_string()));
+    }
+    Ok(())
+}
+
+fn validate_timeout(timeout_ms: Option<u64>) -> Result<Option<Duration>, AppError> {
+    let Some(timeout_ms) = timeout_ms else {
+        return Ok(None);
+    };
+    let timeout = Duration::from_millis(timeout_ms);
+    if timeout.is_zero() || timeout > MAX_TIMEOUT {
+        return Err(AppError::InvalidInput(format!(
+            "Invalid timeout: {}ms (must be 1-{}ms)",
+            timeout_ms,
+            MAX_TIMEOUT.as_millis()
+        )));
+    }
+    Ok(Some(timeout))
+}
+
+/// Run a command without a terminal, streaming its stdout and stderr, and resolve with
+/// how it exited. `run_id` is picked by the caller so it can cancel the run meanwhile
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn run_command(
+    runs: State<'_, RunState>,
+    run_id: String,
+    program: String,
+    args: Option<Vec<String>>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    timeout_ms: Option<u64>,
+    on_stdout: Channel<InvokeResponseBody>,
+    on_stderr: Channel<InvokeResponseBody>,
+) -> Result<RunResult, AppError> {
+    validate_run_id(&run_id)?;
+    validate_program(&program)?;
+    if let Some(dir) = &cwd {
+        validate_cwd(dir)?;
+    }
+    let env = env.unwrap_or_default();
+    for key in env.keys() {
+        validate_env_key(key)?;
+    }
+    let request = RunRequest {
+        program,
+        args: args.unwrap_or_default(),
+        cwd,
+        env,
+        timeout: validate_timeout(timeout_ms)?,
+    };
+
+    let control = Arc::new(RunControl::default());
+    {
+        let mut runs = runs.lock();
+        if runs.contains_key(&run_id) {
+            return Err(AppError::InvalidInput(format!(
+                "Run {} is already running",
+                run_id
+            )));
+        }
+        runs.insert(run_id.clone(), control.clone());
+    }
+    let sink = Arc::new(ChannelRunSink {
+        stdout: on_stdout,
+        stderr: on_stderr,
+    });
+    let result =
+        tauri::async_runtime::spawn_blocking(move || runner::run(&request, &control, sink)).await;
+    runs.lock().remove(&run_id);
+    result.map_err(|e| AppError::Io(e.to_string()))?
+}
+
+/// Stop a running command: SIGTERM, then SIGKILL if it doesn't exit. Its `run_command`
+/// call still resolves, with `cancelled` set
+#[tauri::command]
+pub fn run_cancel(runs: State<'_, RunState>, run_id: String) -> Result<(), AppError> {
+    validate_run_id(&run_id)?;
+    let runs = runs.lock();
+    let control = runs
+        .get(&run_id)
+        .ok_or_else(|| AppError::NotFound(format!("Run {} not found", run_id)))?;
+    control.cancel();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_timeout() {
+        assert_eq!(validate_timeout(None).unwrap(), None);
+        assert_eq!(
+            validate_timeout(Some(1500)).unwrap(),
+            Some(Duration::from_millis(1500))
+        );
+        assert!(validate_timeout(Some(0)).is_err());
+        assert!(validate_timeout(Some(MAX_TIMEOUT.as_millis() as u64 + 1)).is_err());
+    }
+
+    #[test]
+    fn test_validate_run_id() {
+        assert!(validate_run_id(&uuid::Uuid::new_v4().to_string()).is_ok());
+        assert!(validate_run_id("run-1").is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::config::ConfigState;
+use crate::daemon::DaemonState;
+use crate::error::AppError;
+use crate::pty_manager::PtyManager;
+use crate::triggers::{TriggerAction, TriggerRule, TriggerSet};
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tauri::State;
+
+type PtyState = Arc<Mutex<PtyManager>>;
+
+const MAX_PATTERN_LEN: usize = 1024;
+const MAX_RESPONSE_LEN: usize = 1024;
+
+/// Check the rules' limits and compile them
+fn validate_rules(rules: &[TriggerRule]) -> Result<TriggerSet, AppError> {
+    for rule in rules {
+        if rule.pattern.is_empty() || rule.pattern.len() > MAX_PATTERN_LEN {
+            return Err(AppError::InvalidInput(format!(
+                "Trigger pattern must be 1-{} bytes",
+                MAX_PATTERN_LEN
+            )));
+        }
+        if let TriggerAction::Respond { input } = &rule.action {
+            if input.is_empty() || input.len() > MAX_RESPONSE_LEN {
+                return Err(AppError::InvalidInput(format!(
+                    "Trigger response must be 1-{} bytes",
+                    MAX_RESPONSE_LEN
+                )));
+            }
+        }
+    }
+    TriggerSet::compile(rules)
+}
+
+#[tauri::command]
+pub fn trigger_list(config: State<'_, ConfigState>) -> Vec<TriggerRule> {
+    config.lock().config.triggers.clone()
+}
+
+/// Replace the trigger rules, for new sessions and the ones already running
+#[tauri::command]
+pub fn trigger_set(
+    state: State<'_, PtyState>,
+    config: State<'_, ConfigState>,
+    daemon: State<'_, DaemonState>,
+    rules: Vec<TriggerRule>,
+) -> Result<(), AppError> {
+    let compiled = validate_rules(&rules)?;
+    let triggers = {
+        let mut store = config.lock();
+        store.set_triggers(compiled)?;
+        store.triggers()
+    };
+    match daemon.as_ref() {
+        Some(client) => client
+            .list()?
+            .iter()
+            .try_for_each(|session| client.set_triggers(&session.id, &rules)),
+        None => {
+            let manager = state.lock();
+            manager
+                .session_ids()
+                .iter()
+                .try_for_each(|id| manager.set_triggers(id, triggers.clone()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, action: TriggerAction) -> TriggerRule {
+        TriggerRule {
+            name: String::new(),
+            pattern: pattern.to_string(),
+            ignore_case: false,
+            whole_word: false,
+            action,
+        }
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        assert!(validate_rules(&[]).is_ok());
+        assert!(validate_rules(&[rule("done", TriggerAction::Bell)]).is_ok());
+        assert!(validate_rules(&[rule("", TriggerAction::Bell)]).is_err());
+        assert!(validate_rules(&[rule(&"a".repeat(1025), TriggerAction::Bell)]).is_err());
+        assert!(validate_rules(&[rule("[", TriggerAction::Bell)]).is_err());
+        let respond = |input: &str| TriggerAction::Respond {
+            input: input.to_string(),
+        };
+        assert!(validate_rules(&[rule(r"\[y/N\]", respond("y\r"))]).is_ok());
+        assert!(validate_rules(&[rule(r"\[y/N\]", respond(""))]).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use crate::commands::pty_commands::{start_session, validate_cwd, SessionOptions};
+use crate::config::ConfigState;
+use crate::daemon::DaemonState;
+use crate::error::AppError;
+use crate::pty_manager::{NullSink, PtyManager, SessionSummary};
+use crate::workspace::{Workspace, WorkspaceState, SAVE_INTERVAL};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, State};
+
+type PtyState = Arc<Mutex<PtyManager>>;
+
+/// Size restored sessions start at, until the frontend attaches and resizes them
+const DEFAULT_COLS: u16 = 80;
+const DEFAULT_ROWS: u16 = 24;
+
+/// A session started by `restore_workspace`, to be shown with `pty_attach`
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoredTab {
+    pub session_id: String,
+    pub title: Option<String>,
+    /// Output saved from the previous run, for the tab to show before the new output
+    pub scrollback: Option<String>,
+}
+
+fn list(state: &PtyState, daemon: &DaemonState) -> Result<Vec<SessionSummary>, AppError> {
+    match daemon.as_ref() {
+        Some(client) => client.list(),
+        None => Ok(state.lock().list()),
+    }
+}
+
+/// Write the workspace manifest for the sessions running now. Does nothing until
+/// the saved one has been restored, which it would otherwise overwrite
+pub(crate) fn save(app: &AppHandle) -> Result<(), AppError> {
+    let state = app.state::<PtyState>();
+    let daemon = app.state::<DaemonState>();
+    let workspace = app.state::<WorkspaceState>();
+    if !workspace.restore_attempted() {
+        return Ok(());
+    }
+    let scrollback_bytes = app
+        .state::<ConfigState>()
+        .lock()
+        .config
+        .workspace_scrollback_kb as usize
+        * 1024;
+    let sessions = list(&state, &daemon)?;
+    let order = workspace.order.lock().clone();
+    let manifest = Workspace::capture(sessions, &order, scrollback_bytes, |id| {
+        match daemon.as_ref() {
+            Some(client) => client.scrollback(id).ok(),
+            None => state.lock().scrollback(id).ok(),
+        }
+    });
+    manifest.save(&workspace.path)
+}
+
+/// Save the workspace every `SAVE_INTERVAL` for as long as the app runs
+pub(crate) fn spawn_autosave(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(SAVE_INTERVAL);
+        if let Err(e) = save(&app) {
+            log::warn!("Failed to save workspace: {}", e);
+        }
+    });
+}
+
+/// Save the workspace now, with the tabs in the order the frontend shows them
+#[tauri::command]
+pub fn save_workspace(
+    app: AppHandle,
+    workspace: State<'_, WorkspaceState>,
+    tab_order: Vec<String>,
+) -> Result<(), AppError> {
+    *workspace.order.lock() = tab_order;
+    save(&app)
+}
+
+/// Start the sessions saved in the workspace manifest, in their saved order, for
+/// the frontend to show with `pty_attach`. Starts nothing if sessions are already
+/// running, e.g. kept by the daemon or still there after the window reloaded
+#[tauri::command]
+pub fn restore_workspace(
+    state: State<'_, PtyState>,
+    config: State<'_, ConfigState>,
+    daemon: State<'_, DaemonState>,
+    workspace: State<'_, WorkspaceState>,
+    app: AppHandle,
+) -> Result<Vec<RestoredTab>, AppError> {
+    workspace.set_restore_attempted();
+    if !list(&state, &daemon)?.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(saved) = Workspace::load(&workspace.path) else {
+        return Ok(Vec::new());
+    };
+    let mut restored = Vec::new();
+    for tab in saved.tabs {
+        let mut launch = tab.launch;
+        // A directory removed since falls back to the profile's, then the home directory
+        let valid = |dir: &String| validate_cwd(dir).is_ok();
+        launch.cwd = tab.cwd.filter(valid).or(launch.cwd.filter(valid));
+        let options = SessionOptions {
+            cols: DEFAULT_COLS,
+            rows: DEFAULT_ROWS,
+            cell: None,
+            cwd: None,
+            profile: None,
+            launch: Some(launch),
+            command: Vec::new(),
+        };
+        // Output waits in the scrollback until the frontend attaches the tab
+        let session_id =
+            match start_session(&state, &config, &daemon, &app, options, Arc::new(NullSink)) {
+                Ok(session_id) => session_id,
+                Err(e) => {
+                    log::warn!("Failed to restore tab {:?}: {}", tab.title, e);
+                    continue;
+                }
+            };
+        if let Some(title) = &tab.title {
+            let set = match daemon.as_ref() {
+                Some(client) => client.set_title(&session_id, title),
+                None => state.lock().set_title(&session_id, title),
+            };
+            if let Err(e) = set {
+                log::warn!("Failed to restore title of {}: {}", session_id, e);
+            }
+        }
+        restored.push(RestoredTab {
+            session_id,
+            title: tab.title,
+            scrollback: tab.scrollback,
+        });
+    }
+    *workspace.order.lock() = restored.iter().map(|tab| tab.session_id.clone()).collect();
+    Ok(restored)
+}
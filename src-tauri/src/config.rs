@@ -0,0 +1,256 @@
+use crate::error::AppError;
+use crate::profile::LaunchProfile;
+use crate::scrollback::ScrollbackLimits;
+use crate::session_log::LogSettings;
+use crate::triggers::{TriggerRule, TriggerSet};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub type ConfigState = Arc<Mutex<ConfigStore>>;
+
+/// Backend settings persisted as JSON in the app config directory
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppConfig {
+    pub profiles: Vec<LaunchProfile>,
+    /// Profile used by `pty_create` when the caller names none
+    pub default_profile: Option<String>,
+    pub scrollback: ScrollbackLimits,
+    /// Host sessions in the background daemon so they outlive the app window
+    pub session_daemon: bool,
+    /// How long a closed session's processes get to exit after SIGHUP before
+    /// they are killed
+    pub close_grace_ms: Option<u64>,
+    pub session_log: LogSettings,
+    /// Rules matched against every session's output
+    pub triggers: Vec<TriggerRule>,
+    /// Kilobytes of each session's recent output saved with the workspace; 0 saves none
+    pub workspace_scrollback_kb: u32,
+}
+
+const DEFAULT_CLOSE_GRACE: Duration = Duration::from_secs(2);
+
+impl AppConfig {
+    pub fn close_grace(&self) -> Duration {
+        self.close_grace_ms
+            .map(Duration::from_millis)
+            .unwrap_or(DEFAULT_CLOSE_GRACE)
+    }
+
+    pub fn profile(&self, name: &str) -> Option<&LaunchProfile> {
+        self.profiles.iter().find(|p| p.name == name)
+    }
+
+    /// Pick the launch profile for a new session: an inline profile wins, then a
+    /// named one, then the configured default, then the plain default shell
+    pub fn resolve_launch(
+        &self,
+        name: Option<&str>,
+        inline: Option<LaunchProfile>,
+    ) -> Result<LaunchProfile, AppError> {
+        if let Some(profile) = inline {
+            return Ok(profile);
+        }
+        if let Some(name) = name {
+            return self
+                .profile(name)
+                .cloned()
+                .ok_or_else(|| AppError::NotFound(format!("Profile {} not found", name)));
+        }
+        Ok(self
+            .default_profile
+            .as_deref()
+            .and_then(|name| self.profile(name))
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+pub struct ConfigStore {
+    path: PathBuf,
+    pub config: AppConfig,
+    /// Why the file couldn't be loaded. Saving is refused while set, so the defaults
+    /// used instead never overwrite the user's file
+    load_error: Option<String>,
+    /// `config.triggers` compiled, for every session to share
+    triggers: Arc<TriggerSet>,
+}
+
+impl ConfigStore {
+    /// Load the config file, falling back to defaults if it is missing or unreadable
+    pub fn load(path: PathBuf) -> Self {
+        let loaded = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(AppConfig::default()),
+            Err(e) => Err(e.to_string()),
+        };
+        let (config, load_error) = match loaded {
+            Ok(config) => (config, None),
+            Err(e) => {
+                log::warn!("Ignoring invalid config {}: {}", path.display(), e);
+                (AppConfig::default(), Some(e))
+            }
+        };
+        let triggers = TriggerSet::compile(&config.triggers).unwrap_or_else(|e| {
+            log::warn!("Ignoring the configured triggers: {}", e);
+            TriggerSet::default()
+        });
+        ConfigStore {
+            path,
+            config,
+            load_error,
+            triggers: Arc::new(triggers),
+        }
+    }
+
+    pub fn triggers(&self) -> Arc<TriggerSet> {
+        self.triggers.clone()
+    }
+
+    /// Replace the trigger rules and save them
+    pub fn set_triggers(&mut self, triggers: TriggerSet) -> Result<(), AppError> {
+        self.config.triggers = triggers.rules().to_vec();
+        self.triggers = Arc::new(triggers);
+        self.save()
+    }
+
+    /// Why the config file couldn't be loaded, if it couldn't
+    pub fn load_error(&self) -> Option<String> {
+        self.load_error
+            .as_ref()
+            .map(|e| format!("Could not read {}: {}", self.path.display(), e))
+    }
+
+    pub fn save(&self) -> Result<(), AppError> {
+        if let Some(e) = self.load_error() {
+            return Err(AppError::InvalidInput(format!(
+                "{}. Settings are not saved until it is fixed and the app restarted",
+                e
+            )));
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let contents =
+            serde_json::to_string_pretty(&self.config).map_err(|e| AppError::Io(e.to_string()))?;
+        // Write to a sibling file first so a crash never leaves a truncated config
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn profile(name: &str, program: &str) -> LaunchProfile {
+        LaunchProfile {
+            name: name.to_string(),
+            program: Some(program.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_load_missing_file_uses_defaults() {
+        let dir = TempDir::new().unwrap();
+        let store = ConfigStore::load(dir.path().join("config.json"));
+        assert!(store.config.profiles.is_empty());
+        assert!(store.config.default_profile.is_none());
+        assert!(store.load_error().is_none());
+    }
+
+    #[test]
+    fn test_load_invalid_file_uses_defaults_and_keeps_the_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "{ not json").unwrap();
+        let mut store = ConfigStore::load(path.clone());
+        assert!(store.config.profiles.is_empty());
+        assert!(store.load_error().unwrap().contains("config.json"));
+
+        store.config.profiles.push(profile("fish", "fish"));
+        assert!(store.save().is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{ not json");
+    }
+
+    #[test]
+    fn test_load_partial_file_fills_defaults() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, r#"{ "scrollback": { "maxLines": 500 } }"#).unwrap();
+        let store = ConfigStore::load(path);
+        assert_eq!(store.config.scrollback.max_lines, 500);
+        assert_eq!(
+            store.config.scrollback.max_bytes,
+            ScrollbackLimits::default().max_bytes
+        );
+        assert_eq!(store.config.session_log, LogSettings::default());
+    }
+
+    #[test]
+    fn test_save_and_reload() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested").join("config.json");
+        let mut store = ConfigStore::load(path.clone());
+        store.config.profiles.push(profile("fish", "fish"));
+        store.config.default_profile = Some("fish".to_string());
+        store.save().unwrap();
+
+        let reloaded = ConfigStore::load(path);
+        assert_eq!(reloaded.config.profiles, vec![profile("fish", "fish")]);
+        assert_eq!(reloaded.config.default_profile.as_deref(), Some("fish"));
+    }
+
+    #[test]
+    fn test_resolve_launch_precedence() {
+        let config = AppConfig {
+            profiles: vec![profile("fish", "fish"), profile("nu", "nu")],
+            default_profile: Some("nu".to_string()),
+            ..Default::default()
+        };
+
+        let inline = profile("inline", "htop");
+        assert_eq!(
+            config
+                .resolve_launch(Some("fish"), Some(inline.clone()))
+                .unwrap(),
+            inline
+        );
+        assert_eq!(
+            config.resolve_launch(Some("fish"), None).unwrap(),
+            profile("fish", "fish")
+        );
+        assert_eq!(
+            config.resolve_launch(None, None).unwrap(),
+            profile("nu", "nu")
+        );
+        assert!(config.resolve_launch(Some("missing"), None).is_err());
+    }
+
+    #[test]
+    fn test_resolve_launch_falls_back_to_default_shell() {
+        let config = AppConfig {
+            default_profile: Some("gone".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.resolve_launch(None, None).unwrap(),
+            LaunchProfile::default()
+        );
+    }
+
+    #[test]
+    fn test_close_grace_defaults_and_overrides() {
+        let mut config = AppConfig::default();
+        assert_eq!(config.close_grace(), DEFAULT_CLOSE_GRACE);
+        config.close_grace_ms = Some(250);
+        assert_eq!(config.close_grace(), Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,191 @@
+//! `madsterm-ctl`: sends one remote control request to the running app and prints
+//! the result.
+
+use super::{socket_path, RpcRequest, RpcResponse};
+use serde_json::{json, Map, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+
+const USAGE: &str = "\
+usage: madsterm-ctl <command> [args]
+
+commands:
+  list                                    list sessions as JSON
+  open-tab [--cwd DIR] [--profile NAME] [--title TITLE]
+                                          open a tab and print its session ID
+  send-text [--enter] <session-id> <text> type text into a session
+  get-scrollback [--plain] <session-id>   print a session's recent output
+  set-title <session-id> <title>          set a tab's title
+  call <method> [params-json]             send any JSON-RPC request
+
+The socket is found in $MADSTERM_CONTROL_SOCKET, else in the runtime directory.";
+
+/// Run `madsterm-ctl` with its arguments, returning the exit code
+pub fn run_ctl(args: &[String]) -> i32 {
+    let (method, params) = match parse_args(args) {
+        Ok(request) => request,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 2;
+        }
+    };
+    let path = match socket_path() {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("madsterm-ctl: can't use the runtime directory: {}", e);
+            return 1;
+        }
+    };
+    match call(&path, &method, params) {
+        Ok(Value::Null) => 0,
+        Ok(Value::String(text)) => {
+            print!("{}", text);
+            if !text.ends_with('\n') {
+                println!();
+            }
+            0
+        }
+        Ok(value) => {
+            println!("{:#}", value);
+            0
+        }
+        Err(message) => {
+            eprintln!("madsterm-ctl: {}", message);
+            1
+        }
+    }
+}
+
+/// Send one request and wait for its response
+pub fn call(path: &Path, method: &str, params: Value) -> Result<Value, String> {
+    let mut stream = UnixStream::connect(path)
+        .map_err(|e| format!("can't reach madsterm at {}: {}", path.display(), e))?;
+    let request = RpcRequest {
+        jsonrpc: "2.0".to_string(),
+        id: json!(1),
+        method: method.to_string(),
+        params,
+    };
+    let mut line = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+    line.push('\n');
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| e.to_string())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .map_err(|e| e.to_string())?;
+    let response: RpcResponse =
+        serde_json::from_str(&reply).map_err(|e| format!("bad response: {}", e))?;
+    match response.error {
+        Some(error) => Err(error.message),
+        None => Ok(response.result.unwrap_or(Value::Null)),
+    }
+}
+
+/// Turn command-line arguments into a method and its params
+fn parse_args(args: &[String]) -> Result<(String, Value), String> {
+    let (command, rest) = args.split_first().ok_or(USAGE)?;
+    let mut flags = Map::new();
+    let mut positional = Vec::new();
+    let mut rest = rest.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--cwd" | "--profile" | "--title" => {
+                let value = rest
+                    .next()
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                flags.insert(arg[2..].to_string(), json!(value));
+            }
+            "--enter" | "--plain" => {
+                flags.insert(arg[2..].to_string(), json!(true));
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let wrong_args = || format!("wrong arguments for {}\n\n{}", command, USAGE);
+    let request = match (command.as_str(), positional.as_slice()) {
+        ("list", []) => ("list", Value::Null),
+        ("open-tab", []) => ("open_tab", Value::Object(flags)),
+        ("send-text", [session_id, text]) => {
+            let mut text = text.to_string();
+            if flags.contains_key("enter") {
+                text.push('\r');
+            }
+            (
+                "send_text",
+                json!({ "sessionId": session_id, "text": text }),
+            )
+        }
+        ("get-scrollback", [session_id]) => (
+            "get_scrollback",
+            json!({ "sessionId": session_id, "plain": flags.contains_key("plain") }),
+        ),
+        ("set-title", [session_id, title]) => (
+            "set_title",
+            json!({ "sessionId": session_id, "title": title }),
+        ),
+        ("call", [method]) => (*method, Value::Null),
+        ("call", [method, params]) => {
+            let params = serde_json::from_str(params)
+                .map_err(|e| format!("params are not valid JSON: {}", e))?;
+            (*method, params)
+        }
+        ("list" | "open-tab" | "send-text" | "get-scrollback" | "set-title" | "call", _) => {
+            return Err(wrong_args())
+        }
+        _ => return Err(format!("unknown command: {}\n\n{}", command, USAGE)),
+    };
+    Ok((request.0.to_string(), request.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<(String, Value), String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse(&["list"]).unwrap(), ("list".to_string(), Value::Null));
+        assert_eq!(
+            parse(&["open-tab", "--cwd", "/srv/api", "--title", "api"]).unwrap(),
+            (
+                "open_tab".to_string(),
+                json!({ "cwd": "/srv/api", "title": "api" })
+            )
+        );
+        assert_eq!(
+            parse(&["send-text", "--enter", "abc", "npm run dev"])
+                .unwrap()
+                .1,
+            json!({ "sessionId": "abc", "text": "npm run dev\r" })
+        );
+        assert_eq!(
+            parse(&["get-scrollback", "abc", "--plain"]).unwrap().1,
+            json!({ "sessionId": "abc", "plain": true })
+        );
+        assert_eq!(
+            parse(&["call", "set_title", r#"{"sessionId":"abc","title":"db"}"#]).unwrap(),
+            (
+                "set_title".to_string(),
+                json!({ "sessionId": "abc", "title": "db" })
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_arguments() {
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["frobnicate"]).is_err());
+        assert!(parse(&["send-text", "abc"]).is_err());
+        assert!(parse(&["open-tab", "--cwd"]).is_err());
+        assert!(parse(&["call", "list", "{oops"]).is_err());
+    }
+}
@@ -0,0 +1,451 @@
+//! Remote control: scripts drive the running app over a per-user Unix socket with
+//! JSON-RPC 2.0, one request per line, e.g. to open tabs and type into them.
+//! `madsterm-ctl` is the command-line client.
+
+mod cli;
+
+pub use cli::run_ctl;
+
+use crate::ansi::strip_ansi;
+use crate::commands::pty_commands::{self, start_session, validate_session_id, SessionOptions};
+use crate::config::ConfigState;
+use crate::daemon::{self, DaemonState};
+use crate::error::AppError;
+use crate::exit_status::ExitInfo;
+use crate::launch_args::LaunchArgs;
+use crate::pty_manager::{PtyManager, SessionSink};
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+type PtyState = Arc<Mutex<PtyManager>>;
+
+/// Overrides where the control socket is, for the app and `madsterm-ctl` alike
+pub const SOCKET_ENV: &str = "MADSTERM_CONTROL_SOCKET";
+
+/// Size of a remotely opened tab until the terminal it is shown in resizes it
+const DEFAULT_COLS: u16 = 80;
+const DEFAULT_ROWS: u16 = 24;
+const MAX_TITLE_LEN: usize = 256;
+/// How long a remotely opened session waits for a window to show it, which may
+/// still be loading when the app was just started
+const ATTACH_TIMEOUT: Duration = Duration::from_secs(10);
+
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+/// The method was understood but the operation failed
+const APP_ERROR: i32 = -32000;
+
+/// Where the control socket is. The default one is only used once the runtime
+/// directory checks out, since whoever controls it could stand in for the app
+pub fn socket_path() -> std::io::Result<PathBuf> {
+    match std::env::var_os(SOCKET_ENV) {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => Ok(daemon::ensure_runtime_dir()?.join("control.sock")),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl From<AppError> for RpcError {
+    fn from(e: AppError) -> Self {
+        RpcError {
+            code: APP_ERROR,
+            message: e.to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+/// Runs one method with its params
+type Dispatch = dyn Fn(&str, Value) -> Result<Value, RpcError> + Send + Sync;
+
+/// Serve remote control requests for the app. If another instance already holds
+/// the socket, this one goes without
+pub fn start(app: AppHandle) {
+    let listener = match socket_path().and_then(|path| daemon::bind_socket(&path)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::warn!("Remote control unavailable: {}", e);
+            return;
+        }
+    };
+    let dispatch: Arc<Dispatch> = Arc::new(move |method, params| dispatch(&app, method, params));
+    std::thread::spawn(move || serve(listener, dispatch));
+}
+
+fn serve(listener: UnixListener, dispatch: Arc<Dispatch>) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let dispatch = dispatch.clone();
+                std::thread::spawn(move || handle_client(stream, &*dispatch));
+            }
+            Err(e) => log::warn!("Failed to accept remote control client: {}", e),
+        }
+    }
+}
+
+fn handle_client(stream: UnixStream, dispatch: &Dispatch) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            log::warn!("Failed to set up remote control client: {}", e);
+            return;
+        }
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = respond(&line, dispatch);
+        let Ok(mut reply) = serde_json::to_string(&response) else {
+            break;
+        };
+        reply.push('\n');
+        if writer.write_all(reply.as_bytes()).is_err() {
+            break;
+        }
+    }
+}
+
+/// Answer one request line
+fn respond(line: &str, dispatch: &Dispatch) -> RpcResponse {
+    let reply = |id: Value, outcome: Result<Value, RpcError>| {
+        let (result, error) = match outcome {
+            Ok(value) => (Some(value), None),
+            Err(error) => (None, Some(error)),
+        };
+        RpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }
+    };
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => return reply(Value::Null, Err(rpc_error(PARSE_ERROR, e))),
+    };
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    let request = match serde_json::from_value::<RpcRequest>(value) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        Ok(_) => {
+            let error = rpc_error(INVALID_REQUEST, "jsonrpc must be \"2.0\"");
+            return reply(id, Err(error));
+        }
+        Err(e) => return reply(id, Err(rpc_error(INVALID_REQUEST, e))),
+    };
+    reply(request.id, dispatch(&request.method, request.params))
+}
+
+fn rpc_error(code: i32, message: impl ToString) -> RpcError {
+    RpcError {
+        code,
+        message: message.to_string(),
+    }
+}
+
+/// Decode a method's params, treating missing params as an empty object
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Null => Value::Object(Default::default()),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|e| rpc_error(INVALID_PARAMS, e))
+}
+
+fn to_result<T: Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(|e| rpc_error(APP_ERROR, e))
+}
+
+fn validate_title(title: &str) -> Result<(), AppError> {
+    if title.chars().count() > MAX_TITLE_LEN || title.chars().any(char::is_control) {
+        return Err(AppError::InvalidInput(format!(
+            "Title must be at most {} characters, without control characters",
+            MAX_TITLE_LEN
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenTabParams {
+    cwd: Option<String>,
+    profile: Option<String>,
+    title: Option<String>,
+    /// Program and arguments to run in place of the profile's
+    #[serde(default)]
+    command: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendTextParams {
+    session_id: String,
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScrollbackParams {
+    session_id: String,
+    /// Strip escape sequences, leaving the text as it reads on screen
+    #[serde(default)]
+    plain: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetTitleParams {
+    session_id: String,
+    title: String,
+}
+
+/// Sent as `control-open-tab` for the frontend to show a remotely opened session,
+/// which it attaches to with `pty_attach`
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct OpenedTab {
+    session_id: String,
+    title: Option<String>,
+}
+
+fn dispatch(app: &AppHandle, method: &str, raw: Value) -> Result<Value, RpcError> {
+    let state = app.state::<PtyState>();
+    let daemon = app.state::<DaemonState>();
+    match method {
+        "list" => to_result(pty_commands::pty_list(state, daemon)?),
+        "open_tab" => to_result(open_tab(app, params(raw)?)?),
+        "send_text" => {
+            let p: SendTextParams = params(raw)?;
+            to_result(pty_commands::pty_write(
+                state,
+                daemon,
+                p.session_id,
+                p.text,
+            )?)
+        }
+        "get_scrollback" => {
+            let p: ScrollbackParams = params(raw)?;
+            let output = pty_commands::scrollback(&state, &daemon, &p.session_id)?;
+            if p.plain {
+                return to_result(String::from_utf8_lossy(&strip_ansi(&output)));
+            }
+            to_result(String::from_utf8_lossy(&output))
+        }
+        "set_title" => {
+            let p: SetTitleParams = params(raw)?;
+            set_title(&state, &daemon, &p.session_id, &p.title)?;
+            to_result(())
+        }
+        _ => Err(rpc_error(
+            METHOD_NOT_FOUND,
+            format!("Unknown method: {}", method),
+        )),
+    }
+}
+
+/// Hand launch arguments to an instance that is already running, which opens a tab
+/// for them. Returns `Ok(false)` when there is no instance to take them
+pub fn forward(args: &LaunchArgs) -> Result<bool, String> {
+    let Ok(path) = socket_path() else {
+        return Ok(false);
+    };
+    if UnixStream::connect(&path).is_err() {
+        return Ok(false);
+    }
+    let params = serde_json::to_value(args).map_err(|e| e.to_string())?;
+    cli::call(&path, "open_tab", params).map(|_| true)
+}
+
+/// Stands in for a remotely opened session's terminal until the frontend attaches
+/// one, which drops this and so tells `open_tab` the tab is showing
+struct PendingTab {
+    _attached: mpsc::Sender<()>,
+}
+
+impl SessionSink for PendingTab {
+    fn output(&self, _session_id: &str, _data: &[u8]) {}
+
+    fn exit(&self, _session_id: &str, _info: &ExitInfo) {}
+
+    fn is_connected(&self) -> bool {
+        false
+    }
+}
+
+/// Start a session and ask the frontend to show it in a new tab, returning its ID
+/// once a tab has attached to it
+fn open_tab(app: &AppHandle, p: OpenTabParams) -> Result<String, AppError> {
+    if let Some(title) = &p.title {
+        validate_title(title)?;
+    }
+    let state = app.state::<PtyState>();
+    let config = app.state::<ConfigState>();
+    let daemon = app.state::<DaemonState>();
+    let options = SessionOptions {
+        cols: DEFAULT_COLS,
+        rows: DEFAULT_ROWS,
+        cell: None,
+        cwd: p.cwd,
+        profile: p.profile,
+        launch: None,
+        command: p.command,
+    };
+    // Output waits in the scrollback until the frontend attaches the tab
+    let (attached_tx, attached) = mpsc::channel();
+    let pending = Arc::new(PendingTab {
+        _attached: attached_tx,
+    });
+    let session_id = start_session(&state, &config, &daemon, app, options, pending)?;
+    if let Some(title) = &p.title {
+        set_title(&state, &daemon, &session_id, title)?;
+    }
+    let tab = OpenedTab {
+        session_id: session_id.clone(),
+        title: p.title,
+    };
+    let shown = app.emit("control-open-tab", &tab).is_ok()
+        && attached.recv_timeout(ATTACH_TIMEOUT) == Err(mpsc::RecvTimeoutError::Disconnected);
+    if !shown {
+        // Nothing would ever show or close the session
+        let _ = pty_commands::pty_close(state, config, daemon, session_id);
+        return Err(AppError::Pty("No window opened the tab".to_string()));
+    }
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    Ok(session_id)
+}
+
+fn set_title(
+    state: &PtyState,
+    daemon: &DaemonState,
+    session_id: &str,
+    title: &str,
+) -> Result<(), AppError> {
+    validate_session_id(session_id)?;
+    validate_title(title)?;
+    match daemon.as_ref() {
+        Some(client) => client.set_title(session_id, title),
+        None => state.lock().set_title(session_id, title),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn echo(method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "echo" => Ok(params),
+            "fail" => Err(AppError::NotFound("Session x not found".to_string()).into()),
+            _ => Err(rpc_error(METHOD_NOT_FOUND, method)),
+        }
+    }
+
+    fn error_code(response: &RpcResponse) -> Option<i32> {
+        response.error.as_ref().map(|e| e.code)
+    }
+
+    #[test]
+    fn test_respond_reports_protocol_errors() {
+        let response = respond("{not json", &echo);
+        assert_eq!(error_code(&response), Some(PARSE_ERROR));
+        assert_eq!(response.id, Value::Null);
+
+        let response = respond(r#"{"jsonrpc":"1.0","id":4,"method":"echo"}"#, &echo);
+        assert_eq!(error_code(&response), Some(INVALID_REQUEST));
+        assert_eq!(response.id, json!(4));
+        let response = respond(r#"{"jsonrpc":"2.0","id":5}"#, &echo);
+        assert_eq!(error_code(&response), Some(INVALID_REQUEST));
+
+        let response = respond(r#"{"jsonrpc":"2.0","id":6,"method":"nope"}"#, &echo);
+        assert_eq!(error_code(&response), Some(METHOD_NOT_FOUND));
+        let response = respond(r#"{"jsonrpc":"2.0","id":7,"method":"fail"}"#, &echo);
+        assert_eq!(error_code(&response), Some(APP_ERROR));
+        assert_eq!(response.error.unwrap().message, "Session x not found");
+    }
+
+    #[test]
+    fn test_respond_echoes_id_and_result() {
+        let line = r#"{"jsonrpc":"2.0","id":"a","method":"echo","params":{"text":"hi"}}"#;
+        let response = respond(line, &echo);
+        assert_eq!(response.id, json!("a"));
+        assert_eq!(response.result, Some(json!({"text": "hi"})));
+        assert!(response.error.is_none());
+    }
+
+    #[test]
+    fn test_params_accept_missing_params() {
+        let p: OpenTabParams = params(Value::Null).unwrap();
+        assert!(p.cwd.is_none() && p.title.is_none());
+        let e = params::<SendTextParams>(json!({"sessionId": "x"}))
+            .err()
+            .unwrap();
+        assert_eq!(e.code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_validate_title() {
+        assert!(validate_title("api server").is_ok());
+        assert!(validate_title("").is_ok());
+        assert!(validate_title("bad\x1b]0;title").is_err());
+        assert!(validate_title(&"x".repeat(MAX_TITLE_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_client_round_trip_over_socket() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("control.sock");
+        let listener = daemon::bind_socket(&path).unwrap();
+        std::thread::spawn(move || serve(listener, Arc::new(echo)));
+
+        let result = cli::call(&path, "echo", json!({"n": 1})).unwrap();
+        assert_eq!(result, json!({"n": 1}));
+        let error = cli::call(&path, "fail", Value::Null).unwrap_err();
+        assert!(error.contains("Session x not found"));
+        // The socket is taken while served
+        assert!(daemon::bind_socket(&path).is_err());
+    }
+}
@@ -0,0 +1,576 @@
+use super::protocol::{ClientMessage, Request, ServerMessage};
+use super::{socket_path, DAEMON_BINARY};
+use crate::blocks::CommandBlock;
+use crate::error::AppError;
+use crate::exit_status::ExitInfo;
+use crate::groups::{SessionGroup, WriteFailure};
+use crate::monitor::{MonitorSettings, MonitorStatus};
+use crate::process_info::ForegroundProcess;
+use crate::profile::LaunchProfile;
+use crate::pty_manager::{CellSize, SessionSink, SessionSummary};
+use crate::scrollback::ScrollbackLimits;
+use crate::session_log::LogRotation;
+use crate::signal::PtySignal;
+use crate::triggers::TriggerRule;
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Weak};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+type ResponseSender = mpsc::Sender<Result<serde_json::Value, AppError>>;
+
+/// One connection to the daemon, replaced by a new one if the daemon goes away
+struct Connection {
+    stream: Mutex<UnixStream>,
+    pending: Mutex<HashMap<u64, ResponseSender>>,
+    /// Cleared by the reader once the daemon hangs up
+    connected: AtomicBool,
+}
+
+/// Connection to the session daemon, proxying PTY operations and routing its events to sinks
+pub struct DaemonClient {
+    path: PathBuf,
+    /// Start a daemon when reconnecting finds none, as `connect_or_spawn` does
+    spawn: bool,
+    conn: Mutex<Arc<Connection>>,
+    sinks: Mutex<HashMap<String, Arc<dyn SessionSink>>>,
+    next_id: AtomicU64,
+    this: Weak<DaemonClient>,
+}
+
+impl DaemonClient {
+    /// Connect to a daemon already listening at `path`, never starting one
+    #[cfg(test)]
+    pub fn connect(path: &Path) -> Result<Arc<Self>, AppError> {
+        Self::open(path, false)
+    }
+
+    /// Connect to the running daemon, starting one first if there is none
+    pub fn connect_or_spawn() -> Result<Arc<Self>, AppError> {
+        Self::open(&socket_path(), true)
+    }
+
+    fn open(path: &Path, spawn: bool) -> Result<Arc<Self>, AppError> {
+        let stream = open_stream(path, spawn)?;
+        let reader = stream.try_clone()?;
+        let client = Arc::new_cyclic(|this: &Weak<DaemonClient>| DaemonClient {
+            path: path.to_path_buf(),
+            spawn,
+            conn: Mutex::new(Connection::new(stream)),
+            sinks: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            this: this.clone(),
+        });
+        client.start_reader(reader, &client.conn.lock());
+        Ok(client)
+    }
+
+    fn start_reader(&self, reader: UnixStream, conn: &Arc<Connection>) {
+        let (client, c) = (self.this.clone(), Arc::downgrade(conn));
+        std::thread::spawn(move || run_client_reader(reader, client, c));
+    }
+
+    /// The live connection, reconnecting first if the daemon went away. Sessions
+    /// it hosted are gone with it, but new ones can still be started
+    fn connection(&self) -> Result<Arc<Connection>, AppError> {
+        let mut conn = self.conn.lock();
+        if !conn.connected.load(Ordering::SeqCst) {
+            let stream = open_stream(&self.path, self.spawn)?;
+            let reader = stream.try_clone()?;
+            *conn = Connection::new(stream);
+            self.start_reader(reader, &conn);
+        }
+        Ok(conn.clone())
+    }
+
+    fn call<T: DeserializeOwned>(&self, request: Request) -> Result<T, AppError> {
+        let conn = self.connection()?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel();
+        conn.pending.lock().insert(id, tx);
+
+        let mut line = serde_json::to_string(&ClientMessage { id, request })
+            .map_err(|e| AppError::Pty(e.to_string()))?;
+        line.push('\n');
+        if let Err(e) = conn.stream.lock().write_all(line.as_bytes()) {
+            conn.pending.lock().remove(&id);
+            return Err(e.into());
+        }
+
+        let value = rx
+            .recv_timeout(REQUEST_TIMEOUT)
+            .map_err(|_| AppError::Pty("Session daemon did not respond".to_string()))??;
+        serde_json::from_value(value).map_err(|e| AppError::Pty(e.to_string()))
+    }
+
+    pub fn create_session(
+        &self,
+        cols: u16,
+        rows: u16,
+        cell: Option<CellSize>,
+        launch: &LaunchProfile,
+        scrollback: &ScrollbackLimits,
+        sink: Arc<dyn SessionSink>,
+    ) -> Result<String, AppError> {
+        let session_id: String = self.call(Request::Create {
+            cols,
+            rows,
+            cell,
+            launch: launch.clone(),
+            scrollback: scrollback.clone(),
+        })?;
+        self.attach(&session_id, sink)?;
+        Ok(session_id)
+    }
+
+    /// Start receiving a session's output; the daemon replays its scrollback first
+    pub fn attach(&self, session_id: &str, sink: Arc<dyn SessionSink>) -> Result<(), AppError> {
+        self.sinks.lock().insert(session_id.to_string(), sink);
+        let result = self.call(Request::Attach {
+            session_id: session_id.to_string(),
+        });
+        if result.is_err() {
+            self.sinks.lock().remove(session_id);
+        }
+        result
+    }
+
+    /// Write input, fanned out to the session's broadcast group if it is in one
+    pub fn write(&self, session_id: &str, data: &str) -> Result<Vec<WriteFailure>, AppError> {
+        self.call(Request::Write {
+            session_id: session_id.to_string(),
+            data: data.to_string(),
+        })
+    }
+
+    pub fn resize(
+        &self,
+        session_id: &str,
+        cols: u16,
+        rows: u16,
+        cell: Option<CellSize>,
+    ) -> Result<(), AppError> {
+        self.call(Request::Resize {
+            session_id: session_id.to_string(),
+            cols,
+            rows,
+            cell,
+        })
+    }
+
+    pub fn foreground(&self, session_id: &str) -> Result<Option<ForegroundProcess>, AppError> {
+        self.call(Request::Foreground {
+            session_id: session_id.to_string(),
+        })
+    }
+
+    pub fn cwd(&self, session_id: &str) -> Result<Option<String>, AppError> {
+        self.call(Request::Cwd {
+            session_id: session_id.to_string(),
+        })
+    }
+
+    pub fn signal(&self, session_id: &str, signal: PtySignal) -> Result<(), AppError> {
+        self.call(Request::Signal {
+            session_id: session_id.to_string(),
+            signal,
+        })
+    }
+
+    pub fn ack(&self, session_id: &str, bytes: usize) -> Result<(), AppError> {
+        self.call(Request::Ack {
+            session_id: session_id.to_string(),
+            bytes,
+        })
+    }
+
+    pub fn scrollback(&self, session_id: &str) -> Result<Vec<u8>, AppError> {
+        self.call(Request::Scrollback {
+            session_id: session_id.to_string(),
+        })
+    }
+
+    pub fn blocks(&self, session_id: &str) -> Result<Vec<CommandBlock>, AppError> {
+        self.call(Request::Blocks {
+            session_id: session_id.to_string(),
+        })
+    }
+
+    pub fn block_output(&self, session_id: &str, block_id: u64) -> Result<Vec<u8>, AppError> {
+        self.call(Request::BlockOutput {
+            session_id: session_id.to_string(),
+            block_id,
+        })
+    }
+
+    pub fn start_recording(&self, session_id: &str, path: &Path) -> Result<(), AppError> {
+        self.call(Request::StartRecording {
+            session_id: session_id.to_string(),
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn stop_recording(&self, session_id: &str) -> Result<PathBuf, AppError> {
+        self.call(Request::StopRecording {
+            session_id: session_id.to_string(),
+        })
+    }
+
+    pub fn create_group(&self, members: &[String]) -> Result<String, AppError> {
+        self.call(Request::CreateGroup {
+            members: members.to_vec(),
+        })
+    }
+
+    pub fn set_group_members(&self, group_id: &str, members: &[String]) -> Result<(), AppError> {
+        self.call(Request::SetGroupMembers {
+            group_id: group_id.to_string(),
+            members: members.to_vec(),
+        })
+    }
+
+    pub fn dissolve_group(&self, group_id: &str) -> Result<(), AppError> {
+        self.call(Request::DissolveGroup {
+            group_id: group_id.to_string(),
+        })
+    }
+
+    pub fn groups(&self) -> Result<Vec<SessionGroup>, AppError> {
+        self.call(Request::Groups)
+    }
+
+    pub fn start_log(
+        &self,
+        session_id: &str,
+        dir: &Path,
+        rotation: &LogRotation,
+    ) -> Result<PathBuf, AppError> {
+        self.call(Request::StartLog {
+            session_id: session_id.to_string(),
+            dir: dir.to_path_buf(),
+            rotation: rotation.clone(),
+        })
+    }
+
+    pub fn stop_log(&self, session_id: &str) -> Result<PathBuf, AppError> {
+        self.call(Request::StopLog {
+            session_id: session_id.to_string(),
+        })
+    }
+
+    pub fn set_triggers(&self, session_id: &str, rules: &[TriggerRule]) -> Result<(), AppError> {
+        self.call(Request::SetTriggers {
+            session_id: session_id.to_string(),
+            rules: rules.to_vec(),
+        })
+    }
+
+    pub fn set_title(&self, session_id: &str, title: &str) -> Result<(), AppError> {
+        self.call(Request::SetTitle {
+            session_id: session_id.to_string(),
+            title: title.to_string(),
+        })
+    }
+
+    pub fn set_monitor(&self, session_id: &str, settings: MonitorSettings) -> Result<(), AppError> {
+        self.call(Request::SetMonitor {
+            session_id: session_id.to_string(),
+            settings,
+        })
+    }
+
+    pub fn monitor_status(&self, session_id: &str) -> Result<MonitorStatus, AppError> {
+        self.call(Request::MonitorStatus {
+            session_id: session_id.to_string(),
+        })
+    }
+
+    pub fn close(&self, session_id: &str, grace: Duration) -> Result<(), AppError> {
+        self.sinks.lock().remove(session_id);
+        self.call(Request::Close {
+            session_id: session_id.to_string(),
+            grace_ms: grace.as_millis() as u64,
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<SessionSummary>, AppError> {
+        self.call(Request::List)
+    }
+}
+
+impl Drop for DaemonClient {
+    fn drop(&mut self) {
+        // Also ends the reader thread, which holds its own handle to the socket
+        let _ = (self.conn.lock().stream.lock()).shutdown(std::net::Shutdown::Both);
+    }
+}
+
+fn spawn_daemon(socket: &Path) -> Result<(), AppError> {
+    use std::os::unix::process::CommandExt;
+
+    let daemon = std::env::current_exe()?.with_file_name(DAEMON_BINARY);
+    let mut child = std::process::Command::new(&daemon)
+        .arg(socket)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        // Own process group, so signals aimed at the app don't reach the daemon's shells
+        .process_group(0)
+        .spawn()
+        .map_err(|e| {
+            AppError::Pty(format!(
+                "Failed to start session daemon {}: {}",
+                daemon.display(),
+                e
+            ))
+        })?;
+    // Reap the daemon if it ever exits while the app is still running
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+impl Connection {
+    fn new(stream: UnixStream) -> Arc<Self> {
+        Arc::new(Connection {
+            stream: Mutex::new(stream),
+            pending: Mutex::new(HashMap::new()),
+            connected: AtomicBool::new(true),
+        })
+    }
+}
+
+/// Connect to the daemon at `path`. With `spawn`, the runtime directory is checked
+/// first and a daemon is started if none is running
+fn open_stream(path: &Path, spawn: bool) -> Result<UnixStream, AppError> {
+    if !spawn {
+        return Ok(UnixStream::connect(path)?);
+    }
+    // Checked before connecting too, so a socket planted by another user is never used
+    super::ensure_runtime_dir()?;
+    if let Ok(stream) = UnixStream::connect(path) {
+        return Ok(stream);
+    }
+    spawn_daemon(path)?;
+    // Give the daemon a moment to bind its socket
+    for _ in 0..50 {
+        std::thread::sleep(Duration::from_millis(50));
+        if let Ok(stream) = UnixStream::connect(path) {
+            return Ok(stream);
+        }
+    }
+    Err(AppError::Pty("Session daemon did not start".to_string()))
+}
+
+fn run_client_reader(stream: UnixStream, client: Weak<DaemonClient>, conn: Weak<Connection>) {
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let (Some(client), Some(conn)) = (client.upgrade(), conn.upgrade()) else {
+            return;
+        };
+        match serde_json::from_str::<ServerMessage>(&line) {
+            Ok(ServerMessage::Response { id, result }) => {
+                if let Some(tx) = conn.pending.lock().remove(&id) {
+                    let _ = tx.send(result);
+                }
+            }
+            Ok(ServerMessage::Output { session_id, data }) => {
+                let sink = client.sinks.lock().get(&session_id).cloned();
+                if let Some(sink) = sink {
+                    sink.output(&session_id, &data);
+                }
+            }
+            Ok(ServerMessage::Exit { session_id, info }) => {
+                let sink = client.sinks.lock().remove(&session_id);
+                if let Some(sink) = sink {
+                    sink.exit(&session_id, &info);
+                }
+            }
+            Ok(ServerMessage::ForegroundChanged {
+                session_id,
+                process,
+            }) => {
+                let sink = client.sinks.lock().get(&session_id).cloned();
+                if let Some(sink) = sink {
+                    sink.foreground(&session_id, &process);
+                }
+            }
+            Ok(ServerMessage::CwdChanged { session_id, cwd }) => {
+                let sink = client.sinks.lock().get(&session_id).cloned();
+                if let Some(sink) = sink {
+                    sink.cwd(&session_id, &cwd);
+                }
+            }
+            Ok(ServerMessage::TitleChanged { session_id, title }) => {
+                let sink = client.sinks.lock().get(&session_id).cloned();
+                if let Some(sink) = sink {
+                    sink.title(&session_id, &title);
+                }
+            }
+            Ok(ServerMessage::Bell { session_id }) => {
+                let sink = client.sinks.lock().get(&session_id).cloned();
+                if let Some(sink) = sink {
+                    sink.bell(&session_id);
+                }
+            }
+            Ok(ServerMessage::Notify {
+                session_id,
+                notification,
+            }) => {
+                let sink = client.sinks.lock().get(&session_id).cloned();
+                if let Some(sink) = sink {
+                    sink.notify(&session_id, &notification);
+                }
+            }
+            Ok(ServerMessage::Trigger { session_id, fired }) => {
+                let sink = client.sinks.lock().get(&session_id).cloned();
+                if let Some(sink) = sink {
+                    sink.trigger(&session_id, &fired);
+                }
+            }
+            Ok(ServerMessage::Monitor { session_id, alert }) => {
+                let sink = client.sinks.lock().get(&session_id).cloned();
+                if let Some(sink) = sink {
+                    sink.monitor(&session_id, &alert);
+                }
+            }
+            Err(e) => log::warn!("Ignoring malformed daemon message: {}", e),
+        }
+    }
+
+    // The daemon went away: fail outstanding requests and end every attached session
+    let (Some(client), Some(conn)) = (client.upgrade(), conn.upgrade()) else {
+        return;
+    };
+    conn.pending.lock().clear();
+    let info = ExitInfo {
+        reader_error: Some("Lost connection to the session daemon".to_string()),
+        ..Default::default()
+    };
+    let sinks: Vec<_> = client.sinks.lock().drain().collect();
+    for (session_id, sink) in sinks {
+        sink.exit(&session_id, &info);
+    }
+    // Only now, so sessions attached over the next connection aren't ended above
+    conn.connected.store(false, Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{wait_until, CollectingSink};
+    use std::os::unix::net::UnixListener;
+    use tempfile::TempDir;
+
+    fn start_daemon(dir: &TempDir) -> std::path::PathBuf {
+        let path = dir.path().join("d.sock");
+        let p = path.clone();
+        std::thread::spawn(move || super::super::run(&p));
+        wait_until("daemon socket", || path.exists());
+        path
+    }
+
+    #[test]
+    fn test_sessions_survive_client_reconnect() {
+        let dir = TempDir::new().unwrap();
+        let path = start_daemon(&dir);
+        let launch = LaunchProfile {
+            program: Some("sh".to_string()),
+            args: vec!["-c".to_string(), "echo daemon-ok; exec cat".to_string()],
+            ..Default::default()
+        };
+
+        let first = DaemonClient::connect(&path).unwrap();
+        let sink = Arc::new(CollectingSink::default());
+        let id = first
+            .create_session(
+                80,
+                24,
+                None,
+                &launch,
+                &ScrollbackLimits::default(),
+                sink.clone(),
+            )
+            .unwrap();
+        sink.wait_for_output("daemon-ok");
+        drop(first);
+
+        // A new client sees the live session and gets its scrollback on attach
+        let second = DaemonClient::connect(&path).unwrap();
+        let sessions = second.list().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, id);
+        assert!(sessions[0].alive);
+
+        let replay = Arc::new(CollectingSink::default());
+        second.attach(&id, replay.clone()).unwrap();
+        replay.wait_for_output("daemon-ok");
+
+        second.write(&id, "echo-after-attach\n").unwrap();
+        replay.wait_for_output("echo-after-attach");
+
+        // EOF ends `cat`, and the exit reaches the attached sink
+        second.write(&id, "\x04").unwrap();
+        replay.wait_for_exit();
+
+        second.close(&id, Duration::from_secs(1)).unwrap();
+        assert!(second.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reconnects_after_the_daemon_dies() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("d.sock");
+        // Stands in for a daemon that dies once the app has connected
+        let listener = UnixListener::bind(&path).unwrap();
+        let client = DaemonClient::connect(&path).unwrap();
+        drop(listener.accept().unwrap());
+        drop(listener);
+        std::fs::remove_file(&path).unwrap();
+        wait_until("the lost connection", || {
+            !client.conn.lock().connected.load(Ordering::SeqCst)
+        });
+
+        let p = path.clone();
+        std::thread::spawn(move || super::super::run(&p));
+        wait_until("daemon socket", || path.exists());
+        let launch = LaunchProfile {
+            program: Some("sh".to_string()),
+            args: vec!["-c".to_string(), "echo back; exec cat".to_string()],
+            ..Default::default()
+        };
+        let sink = Arc::new(CollectingSink::default());
+        let id = client
+            .create_session(
+                80,
+                24,
+                None,
+                &launch,
+                &ScrollbackLimits::default(),
+                sink.clone(),
+            )
+            .unwrap();
+        sink.wait_for_output("back");
+        client.close(&id, Duration::from_secs(1)).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_session_is_not_found() {
+        let dir = TempDir::new().unwrap();
+        let path = start_daemon(&dir);
+        let client = DaemonClient::connect(&path).unwrap();
+        let id = uuid::Uuid::new_v4().to_string();
+        assert!(matches!(client.write(&id, "x"), Err(AppError::NotFound(_))));
+    }
+}
@@ -0,0 +1,292 @@
+//! Session daemon: a separate process that owns the PTYs so shells survive app restarts.
+//! The app talks to it over a per-user Unix socket using newline-delimited JSON.
+
+#[cfg(unix)]
+mod client;
+pub mod protocol;
+#[cfg(unix)]
+mod server;
+
+#[cfg(unix)]
+pub use client::DaemonClient;
+#[cfg(unix)]
+pub use server::run;
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Client for the session daemon, `None` when sessions are hosted in-process
+pub type DaemonState = Option<Arc<DaemonClient>>;
+
+/// Name of the daemon executable, installed next to the app binary
+pub const DAEMON_BINARY: &str = "madsterm-daemon";
+
+/// Per-user directory holding the app's sockets
+pub fn runtime_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("madsterm"),
+        #[cfg(unix)]
+        None => std::env::temp_dir().join(format!("madsterm-{}", unsafe { libc::getuid() })),
+        #[cfg(not(unix))]
+        None => std::env::temp_dir().join("madsterm"),
+    }
+}
+
+/// Create the runtime directory, readable only by the current user. Fails if the
+/// directory already exists but someone else could have planted files in it
+pub fn ensure_runtime_dir() -> std::io::Result<PathBuf> {
+    let dir = runtime_dir();
+    ensure_private_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Create `dir` if needed and check it is a real directory that only the current
+/// user owns and can use
+pub fn ensure_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        // Not following symlinks, so a link to someone else's directory is refused
+        let meta = std::fs::symlink_metadata(dir)?;
+        let uid = unsafe { libc::geteuid() };
+        if !meta.file_type().is_dir() || meta.uid() != uid || meta.mode() & 0o777 != 0o700 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::PermissionDenied,
+                format!(
+                    "{} must be a directory owned by the current user with mode 0700",
+                    dir.display()
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn socket_path() -> PathBuf {
+    runtime_dir().join("daemon.sock")
+}
+
+/// Listen on a socket only the current user can connect to, replacing a stale one
+/// but failing if something is still serving it
+#[cfg(unix)]
+pub fn bind_socket(path: &Path) -> std::io::Result<UnixListener> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                format!("{} is already in use", path.display()),
+            ));
+        }
+        // Left behind by a process that died
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// Sessions are always hosted in-process where Unix sockets are unavailable
+#[cfg(not(unix))]
+pub enum DaemonClient {}
+
+#[cfg(not(unix))]
+mod unsupported {
+    use super::DaemonClient;
+    use crate::blocks::CommandBlock;
+    use crate::error::AppError;
+    use crate::groups::{SessionGroup, WriteFailure};
+    use crate::monitor::{MonitorSettings, MonitorStatus};
+    use crate::process_info::ForegroundProcess;
+    use crate::profile::LaunchProfile;
+    use crate::pty_manager::{CellSize, SessionSink, SessionSummary};
+    use crate::scrollback::ScrollbackLimits;
+    use crate::session_log::LogRotation;
+    use crate::signal::PtySignal;
+    use crate::triggers::TriggerRule;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    impl DaemonClient {
+        pub fn connect_or_spawn() -> Result<Arc<Self>, AppError> {
+            Err(AppError::Pty(
+                "The session daemon is not supported on this platform".to_string(),
+            ))
+        }
+
+        pub fn create_session(
+            &self,
+            _cols: u16,
+            _rows: u16,
+            _cell: Option<CellSize>,
+            _launch: &LaunchProfile,
+            _scrollback: &ScrollbackLimits,
+            _sink: Arc<dyn SessionSink>,
+        ) -> Result<String, AppError> {
+            match *self {}
+        }
+
+        pub fn attach(
+            &self,
+            _session_id: &str,
+            _sink: Arc<dyn SessionSink>,
+        ) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn write(&self, _session_id: &str, _data: &str) -> Result<Vec<WriteFailure>, AppError> {
+            match *self {}
+        }
+
+        pub fn create_group(&self, _members: &[String]) -> Result<String, AppError> {
+            match *self {}
+        }
+
+        pub fn set_group_members(
+            &self,
+            _group_id: &str,
+            _members: &[String],
+        ) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn dissolve_group(&self, _group_id: &str) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn groups(&self) -> Result<Vec<SessionGroup>, AppError> {
+            match *self {}
+        }
+
+        pub fn resize(
+            &self,
+            _session_id: &str,
+            _cols: u16,
+            _rows: u16,
+            _cell: Option<CellSize>,
+        ) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn foreground(&self, _session_id: &str) -> Result<Option<ForegroundProcess>, AppError> {
+            match *self {}
+        }
+
+        pub fn cwd(&self, _session_id: &str) -> Result<Option<String>, AppError> {
+            match *self {}
+        }
+
+        pub fn signal(&self, _session_id: &str, _signal: PtySignal) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn ack(&self, _session_id: &str, _bytes: usize) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn scrollback(&self, _session_id: &str) -> Result<Vec<u8>, AppError> {
+            match *self {}
+        }
+
+        pub fn blocks(&self, _session_id: &str) -> Result<Vec<CommandBlock>, AppError> {
+            match *self {}
+        }
+
+        pub fn block_output(&self, _session_id: &str, _block_id: u64) -> Result<Vec<u8>, AppError> {
+            match *self {}
+        }
+
+        pub fn start_recording(&self, _session_id: &str, _path: &Path) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn stop_recording(&self, _session_id: &str) -> Result<PathBuf, AppError> {
+            match *self {}
+        }
+
+        pub fn start_log(
+            &self,
+            _session_id: &str,
+            _dir: &Path,
+            _rotation: &LogRotation,
+        ) -> Result<PathBuf, AppError> {
+            match *self {}
+        }
+
+        pub fn stop_log(&self, _session_id: &str) -> Result<PathBuf, AppError> {
+            match *self {}
+        }
+
+        pub fn set_triggers(
+            &self,
+            _session_id: &str,
+            _rules: &[TriggerRule],
+        ) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn set_title(&self, _session_id: &str, _title: &str) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn set_monitor(
+            &self,
+            _session_id: &str,
+            _settings: MonitorSettings,
+        ) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn monitor_status(&self, _session_id: &str) -> Result<MonitorStatus, AppError> {
+            match *self {}
+        }
+
+        pub fn close(&self, _session_id: &str, _grace: Duration) -> Result<(), AppError> {
+            match *self {}
+        }
+
+        pub fn list(&self) -> Result<Vec<SessionSummary>, AppError> {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_private_dir_is_created_and_checked() {
+        let base = tempfile::tempdir().unwrap();
+        let dir = base.path().join("madsterm");
+        ensure_private_dir(&dir).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        // Existing and still private
+        ensure_private_dir(&dir).unwrap();
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(ensure_private_dir(&dir).is_err());
+
+        let target = base.path().join("elsewhere");
+        std::fs::create_dir(&target).unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o700)).unwrap();
+        let link = base.path().join("link");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        assert!(ensure_private_dir(&link).is_err());
+    }
+}
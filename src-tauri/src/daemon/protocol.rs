@@ -0,0 +1,238 @@
+use crate::error::AppError;
+use crate::exit_status::ExitInfo;
+use crate::monitor::{MonitorAlert, MonitorSettings};
+use crate::osc::Notification;
+use crate::process_info::ForegroundProcess;
+use crate::profile::LaunchProfile;
+use crate::pty_manager::CellSize;
+use crate::scrollback::ScrollbackLimits;
+use crate::session_log::LogRotation;
+use crate::signal::PtySignal;
+use crate::triggers::{TriggerMatch, TriggerRule};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Operations the app asks the daemon to perform on its sessions
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Request {
+    Create {
+        cols: u16,
+        rows: u16,
+        #[serde(default)]
+        cell: Option<CellSize>,
+        launch: LaunchProfile,
+        scrollback: ScrollbackLimits,
+    },
+    /// Stream a session's output to this connection, starting with its scrollback
+    Attach {
+        session_id: String,
+    },
+    Write {
+        session_id: String,
+        data: String,
+    },
+    Resize {
+        session_id: String,
+        cols: u16,
+        rows: u16,
+        #[serde(default)]
+        cell: Option<CellSize>,
+    },
+    Signal {
+        session_id: String,
+        signal: PtySignal,
+    },
+    Foreground {
+        session_id: String,
+    },
+    Cwd {
+        session_id: String,
+    },
+    /// The app's consumer has processed this much of the session's output
+    Ack {
+        session_id: String,
+        bytes: usize,
+    },
+    Scrollback {
+        session_id: String,
+    },
+    Blocks {
+        session_id: String,
+    },
+    BlockOutput {
+        session_id: String,
+        block_id: u64,
+    },
+    StartRecording {
+        session_id: String,
+        path: PathBuf,
+    },
+    StopRecording {
+        session_id: String,
+    },
+    CreateGroup {
+        members: Vec<String>,
+    },
+    SetGroupMembers {
+        group_id: String,
+        members: Vec<String>,
+    },
+    DissolveGroup {
+        group_id: String,
+    },
+    Groups,
+    StartLog {
+        session_id: String,
+        dir: PathBuf,
+        rotation: LogRotation,
+    },
+    StopLog {
+        session_id: String,
+    },
+    SetTriggers {
+        session_id: String,
+        rules: Vec<TriggerRule>,
+    },
+    SetTitle {
+        session_id: String,
+        title: String,
+    },
+    SetMonitor {
+        session_id: String,
+        settings: MonitorSettings,
+    },
+    MonitorStatus {
+        session_id: String,
+    },
+    Close {
+        session_id: String,
+        grace_ms: u64,
+    },
+    List,
+}
+
+/// A request line sent by the app, tagged with an id echoed in the response
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClientMessage {
+    pub id: u64,
+    pub request: Request,
+}
+
+/// A line sent by the daemon: either a response or an unsolicited session event
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Response {
+        id: u64,
+        result: Result<serde_json::Value, AppError>,
+    },
+    Output {
+        session_id: String,
+        #[serde(with = "base64_bytes")]
+        data: Vec<u8>,
+    },
+    Exit {
+        session_id: String,
+        info: ExitInfo,
+    },
+    ForegroundChanged {
+        session_id: String,
+        process: ForegroundProcess,
+    },
+    CwdChanged {
+        session_id: String,
+        cwd: String,
+    },
+    TitleChanged {
+        session_id: String,
+        title: String,
+    },
+    Bell {
+        session_id: String,
+    },
+    Notify {
+        session_id: String,
+        notification: Notification,
+    },
+    Trigger {
+        session_id: String,
+        fired: TriggerMatch,
+    },
+    Monitor {
+        session_id: String,
+        alert: MonitorAlert,
+    },
+}
+
+/// PTY output is arbitrary bytes, carried as base64 to keep each message a single JSON line
+mod base64_bytes {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_message_roundtrip() {
+        let message = ClientMessage {
+            id: 7,
+            request: Request::Resize {
+                session_id: "abc".to_string(),
+                cols: 120,
+                rows: 40,
+                cell: Some(CellSize {
+                    width: 9,
+                    height: 18,
+                }),
+            },
+        };
+        let line = serde_json::to_string(&message).unwrap();
+        assert!(line.contains(r#""method":"resize""#));
+        let parsed: ClientMessage = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed, message);
+    }
+
+    #[test]
+    fn test_output_bytes_roundtrip() {
+        let data = vec![0x1b, b'[', b'm', 0xff, 0xe2, 0x94, 0x00, b'\n'];
+        let message = ServerMessage::Output {
+            session_id: "abc".to_string(),
+            data: data.clone(),
+        };
+        let line = serde_json::to_string(&message).unwrap();
+        assert!(!line.contains('\n'));
+        match serde_json::from_str::<ServerMessage>(&line).unwrap() {
+            ServerMessage::Output { data: parsed, .. } => assert_eq!(parsed, data),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_error_response_roundtrip() {
+        let message = ServerMessage::Response {
+            id: 3,
+            result: Err(AppError::NotFound("Session x not found".to_string())),
+        };
+        let line = serde_json::to_string(&message).unwrap();
+        match serde_json::from_str::<ServerMessage>(&line).unwrap() {
+            ServerMessage::Response {
+                id: 3,
+                result: Err(AppError::NotFound(msg)),
+            } => assert_eq!(msg, "Session x not found"),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+}
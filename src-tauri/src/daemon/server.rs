@@ -0,0 +1,370 @@
+use super::protocol::{ClientMessage, Request, ServerMessage};
+use crate::error::AppError;
+use crate::exit_status::ExitInfo;
+use crate::monitor::MonitorAlert;
+use crate::osc::Notification;
+use crate::process_info::ForegroundProcess;
+use crate::pty_manager::{NullSink, PtyManager, SessionSink};
+use crate::triggers::{TriggerMatch, TriggerRule, TriggerSet};
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+/// Messages queued for an app before it counts as no longer reading and is dropped
+const SEND_QUEUE_DEPTH: usize = 256;
+/// How often the accept loop checks whether the daemon has become idle
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// A connected app instance. Doubles as the sink for every session it attached to
+struct ClientConnection {
+    /// Queue to the connection's writer thread, so an app that stops reading stalls
+    /// only its own messages rather than every session's output
+    queue: mpsc::SyncSender<Vec<u8>>,
+    stream: UnixStream,
+    connected: AtomicBool,
+}
+
+impl ClientConnection {
+    fn new(stream: UnixStream) -> std::io::Result<Arc<Self>> {
+        let mut writer = stream.try_clone()?;
+        let (queue, rx) = mpsc::sync_channel::<Vec<u8>>(SEND_QUEUE_DEPTH);
+        let conn = Arc::new(ClientConnection {
+            queue,
+            stream,
+            connected: AtomicBool::new(true),
+        });
+        let c = Arc::downgrade(&conn);
+        std::thread::spawn(move || {
+            // Ends once the connection is dropped and its sender with it
+            for line in rx {
+                if writer.write_all(&line).is_err() {
+                    if let Some(conn) = c.upgrade() {
+                        conn.disconnect();
+                    }
+                    break;
+                }
+            }
+        });
+        Ok(conn)
+    }
+
+    fn send(&self, message: &ServerMessage) {
+        if !self.connected.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut line = match serde_json::to_string(message) {
+            Ok(line) => line,
+            Err(e) => {
+                log::warn!("Failed to encode daemon message: {}", e);
+                return;
+            }
+        };
+        line.push('\n');
+        if self.queue.try_send(line.into_bytes()).is_err() {
+            log::warn!("Dropping an app that stopped reading from the daemon");
+            self.disconnect();
+        }
+    }
+
+    /// Stop sending, and hang up so the app sees the connection end and reconnects
+    fn disconnect(&self) {
+        self.connected.store(false, Ordering::Relaxed);
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+impl SessionSink for ClientConnection {
+    fn output(&self, session_id: &str, data: &[u8]) {
+        self.send(&ServerMessage::Output {
+            session_id: session_id.to_string(),
+            data: data.to_vec(),
+        });
+    }
+
+    fn exit(&self, session_id: &str, info: &ExitInfo) {
+        self.send(&ServerMessage::Exit {
+            session_id: session_id.to_string(),
+            info: info.clone(),
+        });
+    }
+
+    fn foreground(&self, session_id: &str, process: &ForegroundProcess) {
+        self.send(&ServerMessage::ForegroundChanged {
+            session_id: session_id.to_string(),
+            process: process.clone(),
+        });
+    }
+
+    fn cwd(&self, session_id: &str, cwd: &str) {
+        self.send(&ServerMessage::CwdChanged {
+            session_id: session_id.to_string(),
+            cwd: cwd.to_string(),
+        });
+    }
+
+    fn title(&self, session_id: &str, title: &str) {
+        self.send(&ServerMessage::TitleChanged {
+            session_id: session_id.to_string(),
+            title: title.to_string(),
+        });
+    }
+
+    fn bell(&self, session_id: &str) {
+        self.send(&ServerMessage::Bell {
+            session_id: session_id.to_string(),
+        });
+    }
+
+    fn notify(&self, session_id: &str, notification: &Notification) {
+        self.send(&ServerMessage::Notify {
+            session_id: session_id.to_string(),
+            notification: notification.clone(),
+        });
+    }
+
+    fn trigger(&self, session_id: &str, fired: &TriggerMatch) {
+        self.send(&ServerMessage::Trigger {
+            session_id: session_id.to_string(),
+            fired: fired.clone(),
+        });
+    }
+
+    fn monitor(&self, session_id: &str, alert: &MonitorAlert) {
+        self.send(&ServerMessage::Monitor {
+            session_id: session_id.to_string(),
+            alert: alert.clone(),
+        });
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+}
+
+/// Apps connected, and whether the last one to leave found nothing left to serve
+#[derive(Default)]
+struct Clients {
+    connected: usize,
+    left_idle: bool,
+}
+
+struct Daemon {
+    manager: Mutex<PtyManager>,
+    /// Held across accepting and registering a client, so the daemon can't decide it
+    /// is idle while an app is connecting
+    clients: Mutex<Clients>,
+    /// The rules last compiled, reused while each session is sent the same ones
+    triggers: Mutex<Arc<TriggerSet>>,
+}
+
+impl Daemon {
+    fn triggers(&self, rules: &[TriggerRule]) -> Result<Arc<TriggerSet>, AppError> {
+        let mut triggers = self.triggers.lock();
+        if triggers.rules() != rules {
+            *triggers = Arc::new(TriggerSet::compile(rules)?);
+        }
+        Ok(triggers.clone())
+    }
+
+    /// No shell is left running
+    fn sessions_ended(&self) -> bool {
+        !self.manager.lock().any_alive()
+    }
+}
+
+/// Serve sessions on `socket_path` until the daemon becomes idle
+pub fn run(socket_path: &Path) -> std::io::Result<()> {
+    let listener = super::bind_socket(socket_path)?;
+    serve(listener, socket_path)
+}
+
+fn serve(listener: UnixListener, socket_path: &Path) -> std::io::Result<()> {
+    let daemon = Arc::new(Daemon {
+        manager: Mutex::new(PtyManager::new()),
+        clients: Mutex::new(Clients::default()),
+        triggers: Mutex::default(),
+    });
+    // Polled rather than blocking, so going idle never needs a connection to wake it
+    listener.set_nonblocking(true)?;
+    let mut unlinked = false;
+    loop {
+        let mut clients = daemon.clients.lock();
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                if clients.connected == 0 && clients.left_idle && daemon.sessions_ended() {
+                    if unlinked {
+                        break;
+                    }
+                    // New apps start a daemon of their own from here on. One more pass
+                    // picks up any app that connected just before
+                    let _ = std::fs::remove_file(socket_path);
+                    unlinked = true;
+                    continue;
+                }
+                drop(clients);
+                std::thread::sleep(ACCEPT_POLL);
+                continue;
+            }
+            Err(e) => {
+                log::warn!("Failed to accept daemon client: {}", e);
+                drop(clients);
+                std::thread::sleep(ACCEPT_POLL);
+                continue;
+            }
+        };
+        clients.connected += 1;
+        clients.left_idle = false;
+        drop(clients);
+        let d = daemon.clone();
+        std::thread::spawn(move || {
+            handle_client(stream, &d);
+            let mut clients = d.clients.lock();
+            clients.connected -= 1;
+            clients.left_idle = clients.connected == 0;
+        });
+    }
+    Ok(())
+}
+
+fn handle_client(stream: UnixStream, daemon: &Daemon) {
+    // Accepted streams inherit the listener's non-blocking mode on some platforms
+    let conn = match stream
+        .set_nonblocking(false)
+        .and_then(|_| stream.try_clone())
+        .and_then(ClientConnection::new)
+    {
+        Ok(conn) => conn,
+        Err(e) => {
+            log::warn!("Failed to set up daemon client: {}", e);
+            return;
+        }
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: ClientMessage = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                log::warn!("Ignoring malformed daemon request: {}", e);
+                continue;
+            }
+        };
+        let result = dispatch(daemon, &conn, message.request);
+        conn.send(&ServerMessage::Response {
+            id: message.id,
+            result,
+        });
+    }
+    conn.disconnect();
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<serde_json::Value, AppError> {
+    serde_json::to_value(value).map_err(|e| AppError::Pty(e.to_string()))
+}
+
+fn dispatch(
+    daemon: &Daemon,
+    conn: &Arc<ClientConnection>,
+    request: Request,
+) -> Result<serde_json::Value, AppError> {
+    let manager = &daemon.manager;
+    match request {
+        Request::Create {
+            cols,
+            rows,
+            cell,
+            launch,
+            scrollback,
+        } => {
+            // Output is buffered in the scrollback until the client attaches
+            let id = manager.lock().create_session(
+                cols,
+                rows,
+                cell,
+                &launch,
+                &scrollback,
+                Arc::new(NullSink),
+            )?;
+            to_value(id)
+        }
+        Request::Attach { session_id } => {
+            to_value(manager.lock().attach(&session_id, conn.clone())?)
+        }
+        Request::Write { session_id, data } => {
+            let targets = manager.lock().input_targets(&session_id)?;
+            to_value(targets.write(&data)?)
+        }
+        Request::Resize {
+            session_id,
+            cols,
+            rows,
+            cell,
+        } => to_value(manager.lock().resize(&session_id, cols, rows, cell)?),
+        Request::Foreground { session_id } => to_value(manager.lock().foreground(&session_id)?),
+        Request::Cwd { session_id } => to_value(manager.lock().cwd(&session_id)?),
+        Request::Signal { session_id, signal } => {
+            to_value(manager.lock().signal(&session_id, signal)?)
+        }
+        Request::Ack { session_id, bytes } => to_value(manager.lock().ack(&session_id, bytes)?),
+        Request::Scrollback { session_id } => to_value(manager.lock().scrollback(&session_id)?),
+        Request::Blocks { session_id } => to_value(manager.lock().blocks(&session_id)?),
+        Request::BlockOutput {
+            session_id,
+            block_id,
+        } => to_value(manager.lock().block_output(&session_id, block_id)?),
+        Request::StartRecording { session_id, path } => {
+            to_value(manager.lock().start_recording(&session_id, &path)?)
+        }
+        Request::StopRecording { session_id } => {
+            to_value(manager.lock().stop_recording(&session_id)?)
+        }
+        Request::CreateGroup { members } => to_value(manager.lock().create_group(&members)?),
+        Request::SetGroupMembers { group_id, members } => {
+            to_value(manager.lock().set_group_members(&group_id, &members)?)
+        }
+        Request::DissolveGroup { group_id } => to_value(manager.lock().dissolve_group(&group_id)?),
+        Request::Groups => to_value(manager.lock().groups()),
+        Request::StartLog {
+            session_id,
+            dir,
+            rotation,
+        } => to_value(manager.lock().start_log(&session_id, &dir, &rotation)?),
+        Request::StopLog { session_id } => to_value(manager.lock().stop_log(&session_id)?),
+        Request::SetTriggers { session_id, rules } => {
+            let triggers = daemon.triggers(&rules)?;
+            to_value(manager.lock().set_triggers(&session_id, triggers)?)
+        }
+        Request::SetTitle { session_id, title } => {
+            to_value(manager.lock().set_title(&session_id, &title)?)
+        }
+        Request::SetMonitor {
+            session_id,
+            settings,
+        } => to_value(manager.lock().set_monitor(&session_id, settings)?),
+        Request::MonitorStatus { session_id } => {
+            to_value(manager.lock().monitor_status(&session_id)?)
+        }
+        Request::Close {
+            session_id,
+            grace_ms,
+        } => to_value(
+            manager
+                .lock()
+                .close(&session_id, Duration::from_millis(grace_ms))?,
+        ),
+        Request::List => to_value(manager.lock().list()),
+    }
+}
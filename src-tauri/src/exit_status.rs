@@ -0,0 +1,142 @@
+use parking_lot::{Condvar, Mutex};
+use portable_pty::Child;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How a session ended, sent with the `pty-exit-{id}` event
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExitInfo {
+    /// Exit code, if the process exited on its own
+    pub exit_code: Option<i32>,
+    /// Number of the signal that terminated the process
+    pub signal: Option<i32>,
+    /// Name of that signal, e.g. `SIGKILL`
+    pub signal_name: Option<String>,
+    /// Set when reading the PTY failed rather than ending in a hangup
+    pub reader_error: Option<String>,
+}
+
+/// Exit status of a session's child, filled in by its waiter thread
+#[derive(Default)]
+pub struct ChildStatus {
+    info: Mutex<Option<ExitInfo>>,
+    exited: Condvar,
+}
+
+impl ChildStatus {
+    pub fn set(&self, info: ExitInfo) {
+        *self.info.lock() = Some(info);
+        self.exited.notify_all();
+    }
+
+    /// The exit status, or `None` while the child is still running
+    pub fn get(&self) -> Option<ExitInfo> {
+        self.info.lock().clone()
+    }
+
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<ExitInfo> {
+        let deadline = Instant::now() + timeout;
+        let mut info = self.info.lock();
+        while info.is_none() {
+            if self.exited.wait_until(&mut info, deadline).timed_out() {
+                break;
+            }
+        }
+        info.clone()
+    }
+}
+
+/// Block until the child exits and reap it, so it never lingers as a zombie
+#[cfg(unix)]
+pub fn wait_for_exit(mut child: Box<dyn Child + Send + Sync>) -> ExitInfo {
+    let Some(pid) = child.process_id() else {
+        return wait_portable(child.as_mut());
+    };
+    // Waited on directly because portable-pty only reports the signal as a description
+    let mut status = 0;
+    loop {
+        let rc = unsafe { libc::waitpid(pid as libc::pid_t, &mut status, 0) };
+        if rc == -1 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            log::warn!("Failed to wait for process {}: {}", pid, err);
+            return ExitInfo::default();
+        }
+        break;
+    }
+
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        ExitInfo {
+            signal: Some(signal),
+            signal_name: crate::signal::signal_name(signal).map(str::to_string),
+            ..Default::default()
+        }
+    } else {
+        ExitInfo {
+            exit_code: Some(libc::WEXITSTATUS(status)),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn wait_for_exit(mut child: Box<dyn Child + Send + Sync>) -> ExitInfo {
+    wait_portable(child.as_mut())
+}
+
+fn wait_portable(child: &mut (dyn Child + Send + Sync)) -> ExitInfo {
+    match child.wait() {
+        Ok(status) => ExitInfo {
+            exit_code: Some(status.exit_code() as i32),
+            ..Default::default()
+        },
+        Err(e) => {
+            log::warn!("Failed to wait for child process: {}", e);
+            ExitInfo::default()
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+
+    fn run(script: &str) -> ExitInfo {
+        let pair = native_pty_system().openpty(PtySize::default()).unwrap();
+        let mut cmd = CommandBuilder::new("sh");
+        cmd.args(["-c", script]);
+        let child = pair.slave.spawn_command(cmd).unwrap();
+        wait_for_exit(child)
+    }
+
+    #[test]
+    fn test_exit_code_is_reported() {
+        let info = run("exit 3");
+        assert_eq!(info.exit_code, Some(3));
+        assert_eq!(info.signal, None);
+    }
+
+    #[test]
+    fn test_terminating_signal_is_reported() {
+        let info = run("kill -TERM $$");
+        assert_eq!(info.exit_code, None);
+        assert_eq!(info.signal, Some(libc::SIGTERM));
+        assert_eq!(info.signal_name.as_deref(), Some("SIGTERM"));
+    }
+
+    #[test]
+    fn test_wait_timeout_returns_status_once_set() {
+        let status = ChildStatus::default();
+        assert_eq!(status.wait_timeout(Duration::from_millis(10)), None);
+        status.set(ExitInfo {
+            exit_code: Some(0),
+            ..Default::default()
+        });
+        assert_eq!(status.get().unwrap().exit_code, Some(0));
+    }
+}
@@ -0,0 +1,164 @@
+//! Broadcast groups: sessions whose input is synchronized, so typing into any
+//! member is written to all of them. A session belongs to at most one group.
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionGroup {
+    pub id: String,
+    pub members: Vec<String>,
+}
+
+/// A member that a broadcast could not be written to
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteFailure {
+    pub session_id: String,
+    pub error: String,
+}
+
+#[derive(Default)]
+pub struct Groups {
+    groups: HashMap<String, Vec<String>>,
+}
+
+impl Groups {
+    pub fn create(&mut self, members: &[String]) -> Result<String, AppError> {
+        let members = self.check_members(None, members)?;
+        let id = Uuid::new_v4().to_string();
+        self.groups.insert(id.clone(), members);
+        Ok(id)
+    }
+
+    /// Replace a group's members
+    pub fn set_members(&mut self, group_id: &str, members: &[String]) -> Result<(), AppError> {
+        if !self.groups.contains_key(group_id) {
+            return Err(not_found(group_id));
+        }
+        let members = self.check_members(Some(group_id), members)?;
+        self.groups.insert(group_id.to_string(), members);
+        Ok(())
+    }
+
+    pub fn dissolve(&mut self, group_id: &str) -> Result<(), AppError> {
+        self.groups
+            .remove(group_id)
+            .map(|_| ())
+            .ok_or_else(|| not_found(group_id))
+    }
+
+    /// Members of the group `session_id` is in, itself included
+    pub fn members_with(&self, session_id: &str) -> Option<&[String]> {
+        self.groups
+            .values()
+            .find(|members| members.iter().any(|member| member == session_id))
+            .map(Vec::as_slice)
+    }
+
+    /// Forget a closed session, dissolving its group if nobody is left
+    pub fn remove_session(&mut self, session_id: &str) {
+        for members in self.groups.values_mut() {
+            members.retain(|member| member != session_id);
+        }
+        self.groups.retain(|_, members| !members.is_empty());
+    }
+
+    pub fn list(&self) -> Vec<SessionGroup> {
+        let mut groups: Vec<SessionGroup> = self
+            .groups
+            .iter()
+            .map(|(id, members)| SessionGroup {
+                id: id.clone(),
+                members: members.clone(),
+            })
+            .collect();
+        groups.sort_by(|a, b| a.id.cmp(&b.id));
+        groups
+    }
+
+    /// Deduplicate `members`, refusing sessions that already belong to another group
+    fn check_members(
+        &self,
+        group_id: Option<&str>,
+        members: &[String],
+    ) -> Result<Vec<String>, AppError> {
+        let mut unique: Vec<String> = Vec::with_capacity(members.len());
+        for member in members {
+            if unique.contains(member) {
+                continue;
+            }
+            let other = self
+                .groups
+                .iter()
+                .find(|(id, existing)| Some(id.as_str()) != group_id && existing.contains(member));
+            if let Some((other, _)) = other {
+                return Err(AppError::InvalidInput(format!(
+                    "Session {} is already in group {}",
+                    member, other
+                )));
+            }
+            unique.push(member.clone());
+        }
+        if unique.is_empty() {
+            return Err(AppError::InvalidInput(
+                "A group needs at least one session".to_string(),
+            ));
+        }
+        Ok(unique)
+    }
+}
+
+fn not_found(group_id: &str) -> AppError {
+    AppError::NotFound(format!("Group {} not found", group_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_create_modify_and_dissolve() {
+        let mut groups = Groups::default();
+        let id = groups.create(&ids(&["a", "b", "a"])).unwrap();
+        assert_eq!(groups.members_with("b"), Some(&ids(&["a", "b"])[..]));
+        assert_eq!(groups.members_with("c"), None);
+
+        groups.set_members(&id, &ids(&["b", "c"])).unwrap();
+        assert_eq!(groups.members_with("a"), None);
+        assert_eq!(groups.list()[0].members, ids(&["b", "c"]));
+
+        groups.dissolve(&id).unwrap();
+        assert!(groups.list().is_empty());
+        assert!(matches!(groups.dissolve(&id), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_sessions_belong_to_one_group() {
+        let mut groups = Groups::default();
+        let first = groups.create(&ids(&["a", "b"])).unwrap();
+        assert!(groups.create(&ids(&["b", "c"])).is_err());
+        let second = groups.create(&ids(&["c"])).unwrap();
+        assert!(groups.set_members(&second, &ids(&["a", "c"])).is_err());
+        // Re-listing a group's own members is fine
+        assert!(groups.set_members(&first, &ids(&["b", "a"])).is_ok());
+        assert!(groups.create(&[]).is_err());
+    }
+
+    #[test]
+    fn test_removing_last_member_dissolves_group() {
+        let mut groups = Groups::default();
+        groups.create(&ids(&["a", "b"])).unwrap();
+        groups.remove_session("a");
+        assert_eq!(groups.list()[0].members, ids(&["b"]));
+        groups.remove_session("b");
+        assert!(groups.list().is_empty());
+    }
+}
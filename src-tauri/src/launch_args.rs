@@ -0,0 +1,181 @@
+//! Command-line arguments of the `madsterm` binary, which say what its first tab
+//! (or, when the app is already running, its new tab) should start.
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const USAGE: &str = "\
+usage: madsterm [--cwd DIR] [--profile NAME] [PATH] [-e CMD ARGS...]
+
+  --cwd DIR        start in DIR
+  --profile NAME   start with the named launch profile
+  PATH             start in PATH, or in its directory if it is a file
+  -e CMD ARGS...   run CMD instead of the profile's program; takes the rest of the line
+
+When madsterm is already running, the tab opens in that window instead.";
+
+/// What to open, as given on the command line
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LaunchArgs {
+    /// Absolute directory to start in
+    pub cwd: Option<String>,
+    pub profile: Option<String>,
+    /// Program and arguments to run in place of the profile's
+    pub command: Vec<String>,
+}
+
+/// This instance's own arguments, until the frontend takes them for its first tab
+pub type LaunchArgsState = Mutex<Option<LaunchArgs>>;
+
+impl LaunchArgs {
+    /// No arguments were given, so the app starts as usual
+    pub fn is_empty(&self) -> bool {
+        self.cwd.is_none() && self.profile.is_none() && self.command.is_empty()
+    }
+}
+
+/// Parse the arguments after the program name, or `None` when they ask for help.
+/// Relative paths are resolved against `base`, the invoking process's working
+/// directory, since a running instance that they get forwarded to has its own
+pub fn parse(args: &[String], base: &Path) -> Result<Option<LaunchArgs>, String> {
+    let mut launch = LaunchArgs::default();
+    let mut path = None;
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--cwd" | "--profile" => {
+                let value = rest
+                    .next()
+                    .filter(|value| !value.is_empty())
+                    .ok_or_else(|| format!("{} needs a value", arg))?;
+                if arg == "--cwd" {
+                    launch.cwd = Some(resolve_dir(base, value, false)?);
+                } else {
+                    launch.profile = Some(value.clone());
+                }
+            }
+            "-e" => {
+                launch.command = rest.by_ref().cloned().collect();
+                if launch.command.is_empty() {
+                    return Err("-e needs a command".to_string());
+                }
+            }
+            "-h" | "--help" => return Ok(None),
+            // macOS adds a process serial number when launched from the Finder
+            _ if arg.starts_with("-psn_") => {}
+            _ if arg.starts_with('-') => {
+                return Err(format!("unknown option: {}", arg));
+            }
+            _ if path.is_some() => return Err(format!("unexpected argument: {}", arg)),
+            _ => path = Some(arg),
+        }
+    }
+    if let Some(path) = path {
+        if launch.cwd.is_some() {
+            return Err("give either --cwd or a path, not both".to_string());
+        }
+        launch.cwd = Some(resolve_dir(base, path, true)?);
+    }
+    Ok(Some(launch))
+}
+
+/// Make `path` an absolute, existing directory, stepping up from a file if allowed
+fn resolve_dir(base: &Path, path: &str, allow_file: bool) -> Result<String, String> {
+    let full = base
+        .join(path)
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let dir = match full.parent() {
+        Some(parent) if allow_file && !full.is_dir() => parent.to_path_buf(),
+        _ => full,
+    };
+    if !dir.is_dir() {
+        return Err(format!("{}: not a directory", path));
+    }
+    dir.into_os_string()
+        .into_string()
+        .map_err(|_| format!("{}: path is not valid UTF-8", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_in(base: &Path, args: &[&str]) -> Result<LaunchArgs, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse(&args, base).map(|launch| launch.expect("help was not asked for"))
+    }
+
+    fn canonical(path: &Path) -> Option<String> {
+        Some(path.canonicalize().unwrap().to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn test_parse_paths_and_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        std::fs::create_dir(base.join("api")).unwrap();
+        std::fs::write(base.join("api/main.rs"), "").unwrap();
+
+        assert_eq!(parse_in(base, &[]).unwrap(), LaunchArgs::default());
+        assert!(parse_in(base, &["-psn_0_12345"]).unwrap().is_empty());
+        assert_eq!(parse_in(base, &["."]).unwrap().cwd, canonical(base));
+        assert_eq!(
+            parse_in(base, &["api/main.rs"]).unwrap().cwd,
+            canonical(&base.join("api"))
+        );
+        assert_eq!(
+            parse_in(
+                base,
+                &[
+                    "--cwd",
+                    "api",
+                    "--profile",
+                    "fish",
+                    "-e",
+                    "cargo",
+                    "run",
+                    "--release"
+                ]
+            )
+            .unwrap(),
+            LaunchArgs {
+                cwd: canonical(&base.join("api")),
+                profile: Some("fish".to_string()),
+                command: vec![
+                    "cargo".to_string(),
+                    "run".to_string(),
+                    "--release".to_string()
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path();
+        std::fs::write(base.join("notes.txt"), "").unwrap();
+
+        assert!(parse_in(base, &["--frobnicate"]).is_err());
+        assert!(parse_in(base, &["--cwd"]).is_err());
+        assert!(parse_in(base, &["-e"]).is_err());
+        assert!(parse_in(base, &["missing"]).is_err());
+        assert!(parse_in(base, &["--cwd", "notes.txt"]).is_err());
+        assert!(parse_in(base, &["--cwd", ".", "."]).is_err());
+        assert!(parse_in(base, &[".", "."]).is_err());
+    }
+
+    #[test]
+    fn test_parse_help() {
+        let base = Path::new("/");
+        for help in ["-h", "--help"] {
+            assert_eq!(parse(&[help.to_string()], base), Ok(None));
+        }
+        // After -e it belongs to the command
+        let args = ["-e", "ls", "--help"].map(str::to_string);
+        assert!(parse(&args, base).unwrap().is_some());
+    }
+}
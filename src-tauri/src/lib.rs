@@ -1,25 +1,142 @@
+mod ansi;
+mod blocks;
 mod commands;
+mod config;
+#[cfg(unix)]
+mod control;
+mod daemon;
 pub mod error;
+mod exit_status;
+mod groups;
+mod launch_args;
+mod monitor;
+mod osc;
+mod process_info;
+mod profile;
 mod pty_manager;
+mod recording;
+mod runner;
+mod scrollback;
+mod session_log;
+mod shell_integration;
+mod signal;
+#[cfg(test)]
+mod test_support;
+mod triggers;
+mod workspace;
 
 pub use error::AppError;
 
 use parking_lot::Mutex;
 use std::sync::Arc;
+use tauri::Manager;
+
+type PtyState = Arc<Mutex<pty_manager::PtyManager>>;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let pty_state = Arc::new(Mutex::new(pty_manager::PtyManager::new()));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let base = std::env::current_dir().unwrap_or_default();
+    let launch = match launch_args::parse(&args, &base) {
+        Ok(Some(launch)) => launch,
+        Ok(None) => {
+            println!("{}", launch_args::USAGE);
+            return;
+        }
+        Err(message) => {
+            eprintln!("madsterm: {}\n\n{}", message, launch_args::USAGE);
+            std::process::exit(2);
+        }
+    };
+    // A second `madsterm` opens its tab in the running window instead
+    #[cfg(unix)]
+    match control::forward(&launch) {
+        Ok(true) => return,
+        Ok(false) => {}
+        Err(message) => {
+            eprintln!("madsterm: {}", message);
+            std::process::exit(1);
+        }
+    }
+
+    let pty_state: PtyState = Arc::new(Mutex::new(pty_manager::PtyManager::new()));
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .manage(pty_state)
+        .manage(recording::PlaybackState::default())
+        .manage(runner::RunState::default())
+        .manage(launch_args::LaunchArgsState::new(Some(launch)))
+        .setup(|app| {
+            let config_path = app.path().app_config_dir()?.join("config.json");
+            let config_state: config::ConfigState =
+                Arc::new(Mutex::new(config::ConfigStore::load(config_path)));
+            let use_daemon = config_state.lock().config.session_daemon;
+            app.manage(config_state);
+            let workspace_path = app.path().app_data_dir()?.join(workspace::FILE_NAME);
+            app.manage(workspace::WorkspaceState::new(workspace_path));
+
+            let daemon_state: daemon::DaemonState = if use_daemon {
+                match daemon::DaemonClient::connect_or_spawn() {
+                    Ok(client) => Some(client),
+                    Err(e) => {
+                        log::warn!(
+                            "Session daemon unavailable, hosting sessions in-process: {}",
+                            e
+                        );
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            app.manage(daemon_state);
+            #[cfg(unix)]
+            control::start(app.handle().clone());
+            commands::workspace_commands::spawn_autosave(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             commands::pty_commands::pty_create,
             commands::pty_commands::pty_write,
             commands::pty_commands::pty_resize,
+            commands::pty_commands::pty_ack,
+            commands::pty_commands::pty_signal,
+            commands::pty_commands::pty_foreground_process,
+            commands::pty_commands::pty_get_cwd,
+            commands::pty_commands::pty_list_blocks,
+            commands::pty_commands::pty_get_block_output,
+            commands::pty_commands::pty_start_log,
+            commands::group_commands::pty_group_create,
+            commands::group_commands::pty_group_set_members,
+            commands::group_commands::pty_group_dissolve,
+            commands::group_commands::pty_group_list,
+            commands::pty_commands::pty_stop_log,
+            commands::monitor_commands::pty_set_monitor,
+            commands::monitor_commands::pty_monitor_status,
+            commands::run_commands::run_command,
+            commands::run_commands::run_cancel,
+            commands::recording_commands::pty_start_recording,
+            commands::recording_commands::pty_stop_recording,
+            commands::recording_commands::pty_play_recording,
+            commands::recording_commands::pty_set_playback_speed,
+            commands::recording_commands::pty_stop_playback,
             commands::pty_commands::pty_close,
+            commands::pty_commands::pty_get_scrollback,
+            commands::pty_commands::pty_list,
+            commands::pty_commands::pty_daemon_sessions,
+            commands::pty_commands::pty_attach,
+            commands::pty_commands::take_launch_args,
+            commands::profile_commands::profile_list,
+            commands::profile_commands::profile_save,
+            commands::profile_commands::profile_delete,
+            commands::profile_commands::profile_set_default,
+            commands::profile_commands::config_load_error,
+            commands::trigger_commands::trigger_list,
+            commands::trigger_commands::trigger_set,
+            commands::workspace_commands::save_workspace,
+            commands::workspace_commands::restore_workspace,
             commands::git_commands::git_branch,
             commands::git_commands::git_status,
             commands::git_commands::git_diff,
@@ -30,6 +147,40 @@ pub fn run() {
             commands::fs::get_shell_name,
             commands::fs::search_in_files,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                if let Err(e) = commands::workspace_commands::save(app) {
+                    log::warn!("Failed to save workspace: {}", e);
+                }
+                // Hang up in-process sessions the same way closing a tab does;
+                // daemon-hosted ones are meant to outlive the app
+                let grace = app
+                    .state::<config::ConfigState>()
+                    .lock()
+                    .config
+                    .close_grace();
+                app.state::<PtyState>().lock().close_all(grace);
+            }
+        });
+}
+
+/// Entry point of the `madsterm-daemon` binary
+#[cfg(unix)]
+pub fn run_daemon() -> std::io::Result<()> {
+    let socket = match std::env::args_os().nth(1) {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            daemon::ensure_runtime_dir()?;
+            daemon::socket_path()
+        }
+    };
+    daemon::run(&socket)
+}
+
+/// Entry point of the `madsterm-ctl` binary, returning its exit code
+#[cfg(unix)]
+pub fn run_ctl(args: &[String]) -> i32 {
+    control::run_ctl(args)
 }
@@ -0,0 +1,182 @@
+//! Activity, silence and bell monitoring, so the app can flag sessions that need
+//! attention while they are out of sight.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// What a session is watched for, set with `pty_set_monitor`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MonitorSettings {
+    /// Alert on the first output after monitoring starts
+    pub activity: bool,
+    /// Alert once output stops for this many seconds
+    pub silence_secs: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorStatus {
+    /// Bells rung since monitoring started
+    pub bells: u32,
+    /// When the session last printed anything, in milliseconds since the Unix epoch
+    pub last_output_ms: Option<u64>,
+    /// Output arrived since monitoring started
+    pub activity: bool,
+    /// Output has stopped for the silence threshold
+    pub silent: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum MonitorKind {
+    Activity,
+    Silence,
+}
+
+/// Reported in `pty-monitor-{id}` when a watched condition is first met
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorAlert {
+    pub kind: MonitorKind,
+    pub status: MonitorStatus,
+}
+
+#[derive(Default)]
+pub struct Monitor {
+    settings: MonitorSettings,
+    last_output: Option<(Instant, SystemTime)>,
+    bells: u32,
+    activity: bool,
+    silent: bool,
+}
+
+impl Monitor {
+    /// Start watching with new settings, clearing earlier alerts and the bell count
+    pub fn configure(&mut self, settings: MonitorSettings) {
+        self.settings = settings;
+        self.bells = 0;
+        self.activity = false;
+        self.silent = false;
+    }
+
+    /// Note that the session printed something, returning an alert if that is the
+    /// activity being watched for
+    pub fn output(&mut self, now: Instant) -> Option<MonitorAlert> {
+        self.last_output = Some((now, SystemTime::now()));
+        self.silent = false;
+        if !self.settings.activity || self.activity {
+            return None;
+        }
+        self.activity = true;
+        Some(self.alert(MonitorKind::Activity))
+    }
+
+    pub fn bell(&mut self) {
+        self.bells = self.bells.saturating_add(1);
+    }
+
+    /// Alert if the session has just been quiet for the silence threshold. Fires
+    /// once per quiet spell
+    pub fn check_silence(&mut self, now: Instant) -> Option<MonitorAlert> {
+        let threshold = Duration::from_secs(self.settings.silence_secs?);
+        let (last, _) = self.last_output?;
+        if self.silent || now.duration_since(last) < threshold {
+            return None;
+        }
+        self.silent = true;
+        Some(self.alert(MonitorKind::Silence))
+    }
+
+    pub fn status(&self) -> MonitorStatus {
+        MonitorStatus {
+            bells: self.bells,
+            last_output_ms: self.last_output.map(|(_, at)| {
+                at.duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64
+            }),
+            activity: self.activity,
+            silent: self.silent,
+        }
+    }
+
+    fn alert(&self, kind: MonitorKind) -> MonitorAlert {
+        MonitorAlert {
+            kind,
+            status: self.status(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_alerts_once_until_reconfigured() {
+        let mut monitor = Monitor::default();
+        let now = Instant::now();
+        assert!(monitor.output(now).is_none());
+
+        monitor.configure(MonitorSettings {
+            activity: true,
+            silence_secs: None,
+        });
+        let alert = monitor.output(now).unwrap();
+        assert_eq!(alert.kind, MonitorKind::Activity);
+        assert!(alert.status.activity && alert.status.last_output_ms.is_some());
+        assert!(monitor.output(now).is_none());
+
+        monitor.configure(MonitorSettings {
+            activity: true,
+            silence_secs: None,
+        });
+        assert!(!monitor.status().activity);
+        assert!(monitor.output(now).is_some());
+    }
+
+    #[test]
+    fn test_silence_alerts_once_per_quiet_spell() {
+        let mut monitor = Monitor::default();
+        monitor.configure(MonitorSettings {
+            activity: false,
+            silence_secs: Some(5),
+        });
+        let start = Instant::now();
+        // Nothing printed yet, so nothing has gone quiet
+        assert!(monitor
+            .check_silence(start + Duration::from_secs(60))
+            .is_none());
+
+        monitor.output(start);
+        assert!(monitor
+            .check_silence(start + Duration::from_secs(4))
+            .is_none());
+        let alert = monitor
+            .check_silence(start + Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(alert.kind, MonitorKind::Silence);
+        assert!(alert.status.silent);
+        assert!(monitor
+            .check_silence(start + Duration::from_secs(9))
+            .is_none());
+
+        let later = start + Duration::from_secs(10);
+        monitor.output(later);
+        assert!(!monitor.status().silent);
+        assert!(monitor
+            .check_silence(later + Duration::from_secs(5))
+            .is_some());
+    }
+
+    #[test]
+    fn test_bells_are_counted_from_configure() {
+        let mut monitor = Monitor::default();
+        monitor.bell();
+        monitor.bell();
+        assert_eq!(monitor.status().bells, 2);
+        monitor.configure(MonitorSettings::default());
+        assert_eq!(monitor.status().bells, 0);
+    }
+}
@@ -0,0 +1,287 @@
+//! Incremental scanner for OSC (Operating System Command) escape sequences and bells
+//! in PTY output. Sequences may be split across reads, so the scanner keeps its
+//! state between chunks.
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Longest OSC payload kept; longer sequences (e.g. inline images) are skipped
+const MAX_OSC_LEN: usize = 4096;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+const CAN: u8 = 0x18;
+const SUB: u8 = 0x1a;
+
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    Osc,
+    /// Saw ESC inside an OSC, which is either ST (`ESC \`) or an aborted sequence
+    OscEscape,
+}
+
+/// Something the scanner found in the output
+#[derive(Debug, PartialEq)]
+pub enum Sequence<'a> {
+    /// A complete OSC, e.g. `7;file://host/tmp` for `ESC ] 7;file://host/tmp BEL`,
+    /// with the stream offsets the whole sequence spans
+    Osc {
+        payload: &'a [u8],
+        range: Range<u64>,
+    },
+    /// A BEL outside of any escape sequence
+    Bell,
+}
+
+#[derive(Default)]
+pub struct OscScanner {
+    state: State,
+    payload: Vec<u8>,
+    overflow: bool,
+    /// Stream offset of the next byte fed
+    position: u64,
+    /// Stream offset of the ESC that began the current sequence
+    start: u64,
+}
+
+impl OscScanner {
+    /// Scan a chunk of output, calling `on_sequence` for every sequence completed in it
+    pub fn feed(&mut self, data: &[u8], mut on_sequence: impl FnMut(Sequence<'_>)) {
+        for &byte in data {
+            let offset = self.position;
+            self.position += 1;
+            self.state = match (self.state, byte) {
+                (State::Ground | State::Escape, ESC) => {
+                    self.start = offset;
+                    State::Escape
+                }
+                (State::Ground, BEL) => {
+                    on_sequence(Sequence::Bell);
+                    State::Ground
+                }
+                (State::Ground, _) => State::Ground,
+                (State::Escape, b']') => {
+                    self.payload.clear();
+                    self.overflow = false;
+                    State::Osc
+                }
+                (State::Escape, _) => State::Ground,
+                (State::Osc, BEL) | (State::OscEscape, b'\\') => {
+                    if !self.overflow {
+                        on_sequence(Sequence::Osc {
+                            payload: &self.payload,
+                            range: self.start..self.position,
+                        });
+                    }
+                    State::Ground
+                }
+                (State::Osc, ESC) => State::OscEscape,
+                (State::Osc, CAN | SUB) => State::Ground,
+                (State::Osc, _) => {
+                    if self.payload.len() < MAX_OSC_LEN {
+                        self.payload.push(byte);
+                    } else {
+                        self.overflow = true;
+                    }
+                    State::Osc
+                }
+                // An ESC that isn't ST aborts the OSC and may start a new sequence
+                (State::OscEscape, b']') => {
+                    self.start = offset - 1;
+                    self.payload.clear();
+                    self.overflow = false;
+                    State::Osc
+                }
+                (State::OscEscape, ESC) => {
+                    self.start = offset;
+                    State::Escape
+                }
+                (State::OscEscape, _) => State::Ground,
+            };
+        }
+    }
+}
+
+/// Window title set by OSC 0 (icon name and title) or OSC 2 (title)
+pub fn osc_title(payload: &[u8]) -> Option<String> {
+    let title = payload
+        .strip_prefix(b"0;")
+        .or_else(|| payload.strip_prefix(b"2;"))?;
+    Some(String::from_utf8_lossy(title).into_owned())
+}
+
+/// A desktop notification requested by a program in the session
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub title: Option<String>,
+    pub body: String,
+}
+
+/// Notification from OSC 9 (`9;body`, as in iTerm2) or OSC 777 (`777;notify;title;body`)
+pub fn osc_notification(payload: &[u8]) -> Option<Notification> {
+    let payload = String::from_utf8_lossy(payload);
+    if let Some(body) = payload.strip_prefix("9;") {
+        // ConEmu reuses OSC 9 with numeric subcommands, e.g. `9;4;1;50` for progress
+        let first = body.split(';').next().unwrap_or_default();
+        if !first.is_empty() && first.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        return Some(Notification {
+            title: None,
+            body: body.to_string(),
+        });
+    }
+    let rest = payload.strip_prefix("777;notify;")?;
+    let (title, body) = rest.split_once(';').unwrap_or((rest, ""));
+    Some(Notification {
+        title: Some(title.to_string()),
+        body: body.to_string(),
+    })
+}
+
+/// Directory reported by an OSC 7 payload (`7;file://host/path`), if it is on this machine
+pub fn osc7_cwd(payload: &[u8], local_host: Option<&str>) -> Option<String> {
+    let payload = std::str::from_utf8(payload).ok()?;
+    let url = payload.strip_prefix("7;")?;
+    let rest = url
+        .strip_prefix("file://")
+        .or_else(|| url.strip_prefix("kitty-shell-cwd://"))?;
+    let (host, path) = rest.split_at(rest.find('/')?);
+    let is_local = host.is_empty()
+        || host.eq_ignore_ascii_case("localhost")
+        || local_host.is_none_or(|local| host.eq_ignore_ascii_case(local));
+    if !is_local {
+        // A shell on a remote machine, e.g. over ssh; its path means nothing here
+        return None;
+    }
+    let path = percent_decode(path)?;
+    (!path.is_empty()).then_some(path)
+}
+
+pub fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan_chunks(chunks: &[&[u8]]) -> Vec<String> {
+        let mut scanner = OscScanner::default();
+        let mut found = Vec::new();
+        for chunk in chunks {
+            scanner.feed(chunk, |sequence| match sequence {
+                Sequence::Osc { payload, .. } => {
+                    found.push(String::from_utf8_lossy(payload).into_owned())
+                }
+                Sequence::Bell => found.push("BEL".to_string()),
+            });
+        }
+        found
+    }
+
+    #[test]
+    fn test_scans_bel_and_st_terminated_sequences() {
+        let found = scan_chunks(&[b"a\x1b]0;title\x07b\x1b]7;file:///tmp\x1b\\c"]);
+        assert_eq!(found, vec!["0;title", "7;file:///tmp"]);
+    }
+
+    #[test]
+    fn test_reports_bells_outside_sequences_only() {
+        let found = scan_chunks(&[b"\x07a\x1b]0;t\x07\x1b[5m\x07"]);
+        assert_eq!(found, vec!["BEL", "0;t", "BEL"]);
+    }
+
+    #[test]
+    fn test_sequences_split_across_chunks() {
+        let found = scan_chunks(&[b"x\x1b", b"]7;file:", b"///home/u\x1b", b"\\y"]);
+        assert_eq!(found, vec!["7;file:///home/u"]);
+    }
+
+    #[test]
+    fn test_reports_stream_offsets() {
+        let mut scanner = OscScanner::default();
+        let mut ranges = Vec::new();
+        let mut on_sequence = |sequence: Sequence<'_>| {
+            if let Sequence::Osc { range, .. } = sequence {
+                ranges.push(range)
+            }
+        };
+        scanner.feed(b"ab\x1b]133;A\x07c\x1b]13", &mut on_sequence);
+        scanner.feed(b"3;B\x1b\\", &mut on_sequence);
+        assert_eq!(ranges, vec![2..10, 11..20]);
+    }
+
+    #[test]
+    fn test_ignores_other_escapes_and_aborted_sequences() {
+        let found = scan_chunks(&[b"\x1b[31mred\x1b]0;lost\x18\x1b]0;ok\x07"]);
+        assert_eq!(found, vec!["0;ok"]);
+    }
+
+    #[test]
+    fn test_skips_oversized_sequences() {
+        let mut data = b"\x1b]1337;File=".to_vec();
+        data.extend(std::iter::repeat_n(b'A', MAX_OSC_LEN * 2));
+        data.extend_from_slice(b"\x07\x1b]0;next\x07");
+        assert_eq!(scan_chunks(&[&data]), vec!["0;next"]);
+    }
+
+    #[test]
+    fn test_osc_title() {
+        assert_eq!(osc_title(b"0;vim main.rs").as_deref(), Some("vim main.rs"));
+        assert_eq!(osc_title(b"2;").as_deref(), Some(""));
+        assert_eq!(osc_title(b"1;icon"), None);
+    }
+
+    #[test]
+    fn test_osc_notification() {
+        assert_eq!(
+            osc_notification(b"9;Build finished"),
+            Some(Notification {
+                title: None,
+                body: "Build finished".to_string()
+            })
+        );
+        assert_eq!(
+            osc_notification(b"777;notify;make;done; 0 errors"),
+            Some(Notification {
+                title: Some("make".to_string()),
+                body: "done; 0 errors".to_string()
+            })
+        );
+        assert_eq!(osc_notification(b"9;4;1;50"), None);
+        assert_eq!(osc_notification(b"777;preexec"), None);
+    }
+
+    #[test]
+    fn test_osc7_cwd() {
+        assert_eq!(
+            osc7_cwd(b"7;file://box/home/u/my%20dir", Some("box")).as_deref(),
+            Some("/home/u/my dir")
+        );
+        assert_eq!(
+            osc7_cwd(b"7;file:///tmp", Some("box")).as_deref(),
+            Some("/tmp")
+        );
+        assert_eq!(osc7_cwd(b"7;file://elsewhere/tmp", Some("box")), None);
+        assert_eq!(osc7_cwd(b"0;file:///tmp", None), None);
+        assert_eq!(osc7_cwd(b"7;file:///bad%zz", None), None);
+    }
+}
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Minimum time between two foreground checks of the same session
+pub const FOREGROUND_POLL: Duration = Duration::from_millis(250);
+
+/// The job currently in the foreground of a session's terminal
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ForegroundProcess {
+    /// Process group leader of the foreground job
+    pub pid: u32,
+    /// Executable name, empty where /proc is unavailable
+    pub name: String,
+    /// Full command line, empty where /proc is unavailable
+    pub argv: Vec<String>,
+    /// Whether the foreground job is the session's shell itself, i.e. it is idle at a prompt
+    pub is_shell: bool,
+    /// Working directory of the job, where /proc is available
+    pub cwd: Option<String>,
+}
+
+impl ForegroundProcess {
+    pub fn describe(pid: u32, shell_pid: Option<u32>) -> Self {
+        ForegroundProcess {
+            pid,
+            name: process_name(pid).unwrap_or_default(),
+            argv: process_argv(pid).unwrap_or_default(),
+            is_shell: Some(pid) == shell_pid,
+            cwd: process_cwd(pid),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn process_name(pid: u32) -> Option<String> {
+    let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+    Some(comm.trim_end_matches('\n').to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn process_argv(pid: u32) -> Option<Vec<String>> {
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    Some(
+        cmdline
+            .split(|&b| b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).into_owned())
+            .collect(),
+    )
+}
+
+#[cfg(target_os = "linux")]
+pub fn process_cwd(pid: u32) -> Option<String> {
+    let cwd = std::fs::read_link(format!("/proc/{}/cwd", pid)).ok()?;
+    Some(cwd.to_string_lossy().into_owned())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_name(_pid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_argv(_pid: u32) -> Option<Vec<String>> {
+    None
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn process_cwd(_pid: u32) -> Option<String> {
+    None
+}
+
+/// This machine's hostname, for telling local OSC 7 paths from remote ones
+#[cfg(unix)]
+pub fn local_hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8(buf[..len].to_vec()).ok()
+}
+
+#[cfg(not(unix))]
+pub fn local_hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+/// Process group in the foreground of the terminal on `fd`
+#[cfg(unix)]
+pub fn foreground_pgid(fd: std::os::fd::RawFd) -> Option<u32> {
+    let pgid = unsafe { libc::tcgetpgrp(fd) };
+    (pgid > 0).then_some(pgid as u32)
+}
+
+/// Tracks a session's foreground job from its output thread, reporting changes.
+/// Holds its own copy of the master fd so it never outlives the descriptor it polls
+pub struct ForegroundWatcher {
+    #[cfg(unix)]
+    fd: Option<std::os::fd::OwnedFd>,
+    shell_pid: Option<u32>,
+    last: Option<ForegroundProcess>,
+    last_checked: Option<Instant>,
+}
+
+impl ForegroundWatcher {
+    #[cfg(unix)]
+    pub fn new(master: &dyn portable_pty::MasterPty, shell_pid: Option<u32>) -> Self {
+        use std::os::fd::BorrowedFd;
+
+        let fd = master.as_raw_fd().and_then(|fd| {
+            // Safety: the master fd is open for as long as `master` is borrowed
+            let borrowed = unsafe { BorrowedFd::borrow_raw(fd) };
+            borrowed.try_clone_to_owned().ok()
+        });
+        ForegroundWatcher {
+            fd,
+            shell_pid,
+            last: None,
+            last_checked: None,
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn new(_master: &dyn portable_pty::MasterPty, shell_pid: Option<u32>) -> Self {
+        ForegroundWatcher {
+            shell_pid,
+            last: None,
+            last_checked: None,
+        }
+    }
+
+    #[cfg(unix)]
+    fn current(&self) -> Option<ForegroundProcess> {
+        use std::os::fd::AsRawFd;
+
+        let pgid = foreground_pgid(self.fd.as_ref()?.as_raw_fd())?;
+        Some(ForegroundProcess::describe(pgid, self.shell_pid))
+    }
+
+    #[cfg(not(unix))]
+    fn current(&self) -> Option<ForegroundProcess> {
+        None
+    }
+
+    /// The new foreground job if it changed since the last check. Rate limited, so
+    /// it is cheap to call after every output flush
+    pub fn poll(&mut self) -> Option<ForegroundProcess> {
+        let now = Instant::now();
+        if self
+            .last_checked
+            .is_some_and(|checked| now.duration_since(checked) < FOREGROUND_POLL)
+        {
+            return None;
+        }
+        self.last_checked = Some(now);
+        let current = self.current()?;
+        if self.last.as_ref() == Some(&current) {
+            return None;
+        }
+        self.last = Some(current.clone());
+        Some(current)
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_reads_proc() {
+        let pid = std::process::id();
+        let process = ForegroundProcess::describe(pid, Some(pid));
+        assert_eq!(process.pid, pid);
+        assert!(!process.name.is_empty());
+        assert!(!process.argv.is_empty());
+        assert!(process.is_shell);
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(process.cwd.as_deref(), cwd.to_str());
+    }
+
+    #[test]
+    fn test_describe_missing_process_is_empty() {
+        let process = ForegroundProcess::describe(u32::MAX, None);
+        assert!(process.name.is_empty());
+        assert!(process.argv.is_empty());
+        assert!(!process.is_shell);
+        assert!(process.cwd.is_none());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::shell_integration::{self, Shell};
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A named recipe for spawning a PTY session: what to run, where, and with which environment
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LaunchProfile {
+    pub name: String,
+    /// Program to run. `None` runs the user's default shell
+    pub program: Option<String>,
+    pub args: Vec<String>,
+    /// Extra environment variables, applied on top of the terminal defaults
+    pub env: BTreeMap<String, String>,
+    /// Variables removed from the inherited environment
+    pub env_remove: Vec<String>,
+    pub cwd: Option<String>,
+    /// Start as a login shell. Defaults to on for the default shell and off for custom programs
+    pub login: Option<bool>,
+    /// Load the bundled integration into bash, zsh and fish so they report prompts,
+    /// commands and their cwd. Defaults to on; only applies to shells started without args
+    pub shell_integration: Option<bool>,
+    /// Started for a one-off command, e.g. with `madsterm -e`, so it isn't run again
+    /// when the workspace is restored
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub one_off: bool,
+}
+
+/// The user's shell, used when a profile changes how the default shell is started
+fn default_shell() -> String {
+    #[cfg(unix)]
+    {
+        std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+    }
+    #[cfg(windows)]
+    {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
+    }
+}
+
+impl LaunchProfile {
+    pub fn is_login(&self) -> bool {
+        self.login.unwrap_or(self.program.is_none())
+    }
+
+    /// Shell whose bundled integration should be loaded into the session, if any
+    fn integrated_shell(&self) -> Option<Shell> {
+        if !self.shell_integration.unwrap_or(true) || !self.args.is_empty() {
+            return None;
+        }
+        Shell::detect(&self.program.clone().unwrap_or_else(default_shell))
+    }
+
+    pub fn build_command(&self) -> CommandBuilder {
+        let login = self.is_login();
+        let integration = self
+            .integrated_shell()
+            .and_then(|shell| Some((shell, shell_integration::install()?)));
+        // Bash ignores --rcfile in login shells, so its script reads the login and
+        // logout files itself to keep a login profile's startup and exit the same
+        let bash_rcfile = matches!(integration, Some((Shell::Bash, _)));
+        let mut cmd = match &self.program {
+            // portable-pty already starts the default shell as a login shell
+            None if login && self.args.is_empty() && !bash_rcfile => {
+                CommandBuilder::new_default_prog()
+            }
+            program => {
+                let mut cmd = CommandBuilder::new(program.clone().unwrap_or_else(default_shell));
+                if login && !bash_rcfile {
+                    cmd.arg("-l");
+                }
+                cmd.args(&self.args);
+                cmd
+            }
+        };
+
+        cmd.env("TERM", "xterm-256color");
+        cmd.env("COLORTERM", "truecolor");
+        // Ensure locale is set for proper Unicode support in vim/TUI apps
+        if std::env::var("LANG").is_err() {
+            cmd.env("LANG", "en_US.UTF-8");
+        }
+        for (key, value) in &self.env {
+            cmd.env(key, value);
+        }
+        for key in &self.env_remove {
+            cmd.env_remove(key);
+        }
+
+        if let Some((shell, dir)) = &integration {
+            shell_integration::inject(&mut cmd, *shell, login, dir);
+        }
+
+        if let Some(dir) = self.working_dir() {
+            cmd.cwd(dir);
+        }
+        cmd
+    }
+
+    /// Directory the session starts in
+    pub fn working_dir(&self) -> Option<String> {
+        match self.cwd.as_deref() {
+            Some(dir) if !dir.is_empty() => Some(dir.to_string()),
+            // Default to user's home directory if no cwd specified
+            _ => std::env::var("HOME").ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(cmd: &CommandBuilder) -> Vec<String> {
+        cmd.get_argv()
+            .iter()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_profile_uses_default_prog() {
+        let profile = LaunchProfile {
+            shell_integration: Some(false),
+            ..Default::default()
+        };
+        let cmd = profile.build_command();
+        assert!(cmd.is_default_prog());
+    }
+
+    #[test]
+    fn test_custom_program_with_args() {
+        let profile = LaunchProfile {
+            program: Some("htop".to_string()),
+            args: vec!["-d".to_string(), "10".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(argv(&profile.build_command()), vec!["htop", "-d", "10"]);
+    }
+
+    #[test]
+    fn test_login_flag_for_custom_program() {
+        let profile = LaunchProfile {
+            program: Some("bash".to_string()),
+            login: Some(true),
+            shell_integration: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(argv(&profile.build_command()), vec!["bash", "-l"]);
+    }
+
+    #[test]
+    fn test_non_login_default_shell() {
+        let profile = LaunchProfile {
+            login: Some(false),
+            shell_integration: Some(false),
+            ..Default::default()
+        };
+        let cmd = profile.build_command();
+        assert!(!cmd.is_default_prog());
+        assert_eq!(argv(&cmd), vec![default_shell()]);
+    }
+
+    #[test]
+    fn test_env_overrides_and_removals() {
+        let mut env = BTreeMap::new();
+        env.insert("TERM".to_string(), "xterm-kitty".to_string());
+        env.insert("FOO".to_string(), "bar".to_string());
+        let profile = LaunchProfile {
+            env,
+            env_remove: vec!["COLORTERM".to_string()],
+            ..Default::default()
+        };
+        let cmd = profile.build_command();
+        assert_eq!(cmd.get_env("TERM").unwrap(), "xterm-kitty");
+        assert_eq!(cmd.get_env("FOO").unwrap(), "bar");
+        assert!(cmd.get_env("COLORTERM").is_none());
+    }
+
+    #[test]
+    fn test_bash_integration_uses_rcfile_instead_of_login_flag() {
+        let profile = LaunchProfile {
+            program: Some("/bin/bash".to_string()),
+            login: Some(true),
+            ..Default::default()
+        };
+        let cmd = profile.build_command();
+        let argv = argv(&cmd);
+        assert_eq!(argv[..2], ["/bin/bash", "--rcfile"]);
+        assert!(argv[2].ends_with("bash/madsterm.bash"));
+        assert_eq!(cmd.get_env("MADSTERM_BASH_LOGIN").unwrap(), "1");
+    }
+
+    #[test]
+    fn test_no_integration_for_commands_or_when_disabled() {
+        let command = LaunchProfile {
+            program: Some("zsh".to_string()),
+            args: vec!["-c".to_string(), "true".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(argv(&command.build_command()), vec!["zsh", "-c", "true"]);
+        let disabled = LaunchProfile {
+            program: Some("zsh".to_string()),
+            shell_integration: Some(false),
+            ..Default::default()
+        };
+        let cmd = disabled.build_command();
+        assert!(cmd
+            .get_env("ZDOTDIR")
+            .is_none_or(|dir| !dir.to_string_lossy().contains("shell-integration")));
+    }
+
+    #[test]
+    fn test_cwd_is_applied() {
+        let profile = LaunchProfile {
+            cwd: Some("/tmp".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(profile.build_command().get_cwd().unwrap(), "/tmp");
+    }
+}
@@ -1,90 +1,821 @@
+use crate::blocks::{BlockTracker, CommandBlock};
 use crate::error::AppError;
-use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use crate::exit_status::{self, ChildStatus, ExitInfo};
+use crate::groups::{Groups, SessionGroup, WriteFailure};
+use crate::monitor::{Monitor, MonitorAlert, MonitorSettings, MonitorStatus};
+use crate::osc::{osc7_cwd, osc_notification, osc_title, Notification, OscScanner, Sequence};
+use crate::process_info::{local_hostname, ForegroundProcess, ForegroundWatcher, FOREGROUND_POLL};
+use crate::profile::LaunchProfile;
+use crate::recording::Recorder;
+use crate::scrollback::{Scrollback, ScrollbackLimits};
+use crate::session_log::{LogRotation, SessionLog};
+use crate::signal::PtySignal;
+use crate::triggers::{TriggerAction, TriggerMatch, TriggerSet, Triggers};
+use parking_lot::{Condvar, Mutex, MutexGuard};
+use portable_pty::{native_pty_system, ChildKiller, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::ipc::{Channel, InvokeResponseBody};
 use tauri::{AppHandle, Emitter};
 use uuid::Uuid;
 
-fn run_pty_reader(mut reader: Box<dyn Read + Send>, handle: AppHandle, session_id: String) {
-    let mut buf = [0u8; 16384];
-    // Buffer for incomplete UTF-8 sequences at chunk boundaries
-    let mut utf8_remainder: Vec<u8> = Vec::new();
-    let output_event = format!("pty-output-{}", session_id);
-    let exit_event = format!("pty-exit-{}", session_id);
+/// Longest a read is held back to be coalesced with the reads after it
+const FLUSH_INTERVAL: Duration = Duration::from_millis(4);
+/// Batch size at which output is flushed without waiting out the interval
+const FLUSH_BYTES: usize = 64 * 1024;
+/// Reads queued between the reader and the batcher before the reader blocks
+const READ_QUEUE_DEPTH: usize = 64;
+/// Input held for a session that isn't reading it, beyond which more is refused
+const MAX_QUEUED_INPUT: usize = 8 * 1024 * 1024;
+/// Unacknowledged output at which the reader stops reading the PTY...
+const HIGH_WATERMARK: usize = 1024 * 1024;
+/// ...and the level acks must bring it back down to before reading resumes
+const LOW_WATERMARK: usize = 256 * 1024;
+/// How often a paused reader checks whether its consumer went away
+const DETACH_POLL: Duration = Duration::from_millis(100);
+/// How long the exit event waits for the child's status after the PTY hangs up
+const EXIT_STATUS_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long closing a session waits for the child to be reaped after SIGKILL
+const REAP_TIMEOUT: Duration = Duration::from_secs(1);
+/// Bells closer together than this are reported once, so `yes $'\a'` can't flood the app
+const BELL_INTERVAL: Duration = Duration::from_millis(100);
 
-    loop {
-        match reader.read(&mut buf) {
-            Ok(0) => {
-                // Flush any remaining bytes as lossy before exit
-                if !utf8_remainder.is_empty() {
-                    let data = String::from_utf8_lossy(&utf8_remainder).to_string();
-                    let _ = handle.emit(&output_event, &data);
-                }
-                let _ = handle.emit(&exit_event, ());
-                break;
+/// Size of a character cell in pixels. Passed on to the PTY so programs that draw
+/// images, such as chafa or the kitty and sixel protocols, can size them
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CellSize {
+    pub width: u16,
+    pub height: u16,
+}
+
+/// Terminal size in cells
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TerminalSize {
+    pub cols: u16,
+    pub rows: u16,
+}
+
+/// Window size to report for `cols` x `rows` cells; pixels are 0 when unknown
+fn pty_size(cols: u16, rows: u16, cell: Option<CellSize>) -> PtySize {
+    let cell = cell.unwrap_or(CellSize {
+        width: 0,
+        height: 0,
+    });
+    PtySize {
+        rows,
+        cols,
+        pixel_width: cols.saturating_mul(cell.width),
+        pixel_height: rows.saturating_mul(cell.height),
+    }
+}
+
+/// Destination for everything a session's reader thread produces
+pub trait SessionSink: Send + Sync {
+    /// Raw PTY output; chunks may split UTF-8 sequences and escape codes
+    fn output(&self, session_id: &str, data: &[u8]);
+    fn exit(&self, session_id: &str, info: &ExitInfo);
+
+    /// A different job took over the terminal's foreground
+    fn foreground(&self, _session_id: &str, _process: &ForegroundProcess) {}
+
+    /// The session's working directory changed
+    fn cwd(&self, _session_id: &str, _cwd: &str) {}
+
+    /// A program set the window title with OSC 0 or OSC 2
+    fn title(&self, _session_id: &str, _title: &str) {}
+
+    /// A program rang the bell
+    fn bell(&self, _session_id: &str) {}
+
+    /// A program asked for a desktop notification with OSC 9 or OSC 777
+    fn notify(&self, _session_id: &str, _notification: &Notification) {}
+
+    /// Output matched one of the session's trigger rules
+    fn trigger(&self, _session_id: &str, _fired: &TriggerMatch) {}
+
+    /// The session showed the activity or silence it is being monitored for
+    fn monitor(&self, _session_id: &str, _alert: &MonitorAlert) {}
+
+    /// The recording being played back changed the terminal size
+    fn resize(&self, _session_id: &str, _size: TerminalSize) {}
+
+    /// Whether anyone is still listening on the other end
+    fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+/// Delivers session output to the webview as raw bytes over an IPC channel,
+/// and session events such as `pty-exit-{id}` as app events
+pub struct ChannelSink {
+    handle: AppHandle,
+    channel: Channel<InvokeResponseBody>,
+    /// A send failed, so the webview that owned the channel is gone
+    closed: AtomicBool,
+}
+
+impl ChannelSink {
+    pub fn new(handle: AppHandle, channel: Channel<InvokeResponseBody>) -> Self {
+        ChannelSink {
+            handle,
+            channel,
+            closed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl SessionSink for ChannelSink {
+    fn output(&self, _session_id: &str, data: &[u8]) {
+        if self
+            .channel
+            .send(InvokeResponseBody::Raw(data.to_vec()))
+            .is_err()
+        {
+            self.closed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        !self.closed.load(Ordering::Relaxed)
+    }
+
+    fn exit(&self, session_id: &str, info: &ExitInfo) {
+        let _ = self.handle.emit(&format!("pty-exit-{}", session_id), info);
+    }
+
+    fn foreground(&self, session_id: &str, process: &ForegroundProcess) {
+        let _ = self
+            .handle
+            .emit(&format!("pty-foreground-{}", session_id), process);
+    }
+
+    fn cwd(&self, session_id: &str, cwd: &str) {
+        let _ = self.handle.emit(&format!("pty-cwd-{}", session_id), cwd);
+    }
+
+    fn title(&self, session_id: &str, title: &str) {
+        let _ = self
+            .handle
+            .emit(&format!("pty-title-{}", session_id), title);
+    }
+
+    fn bell(&self, session_id: &str) {
+        let _ = self.handle.emit(&format!("pty-bell-{}", session_id), ());
+    }
+
+    fn notify(&self, session_id: &str, notification: &Notification) {
+        let _ = self
+            .handle
+            .emit(&format!("pty-notify-{}", session_id), notification);
+    }
+
+    fn trigger(&self, session_id: &str, fired: &TriggerMatch) {
+        let _ = self
+            .handle
+            .emit(&format!("pty-trigger-{}", session_id), fired);
+    }
+
+    fn monitor(&self, session_id: &str, alert: &MonitorAlert) {
+        let _ = self
+            .handle
+            .emit(&format!("pty-monitor-{}", session_id), alert);
+    }
+
+    fn resize(&self, session_id: &str, size: TerminalSize) {
+        let _ = self
+            .handle
+            .emit(&format!("pty-resize-{}", session_id), size);
+    }
+}
+
+/// Drops output on the floor; it is still recorded in the scrollback
+pub struct NullSink;
+
+impl SessionSink for NullSink {
+    fn output(&self, _session_id: &str, _data: &[u8]) {}
+
+    fn exit(&self, _session_id: &str, _info: &ExitInfo) {}
+
+    fn is_connected(&self) -> bool {
+        false
+    }
+}
+
+/// Scrollback and the current sink, locked together so that swapping the sink
+/// never drops or duplicates output
+struct SessionOutput {
+    scrollback: Scrollback,
+    sink: Arc<dyn SessionSink>,
+    /// Last known working directory, from OSC 7 or the foreground process
+    cwd: Option<String>,
+    blocks: BlockTracker,
+    /// Last title set with OSC 0 or OSC 2
+    title: Option<String>,
+    last_bell: Option<Instant>,
+    recording: Option<Recorder>,
+    log: Option<SessionLog>,
+    triggers: Triggers,
+    /// Queue to the session's input writer, for triggers that answer prompts
+    input: InputQueue,
+    monitor: Monitor,
+    bytes_out: u64,
+    /// The exit as reported to the sink, once all output has been delivered
+    exit: Option<ExitInfo>,
+}
+
+impl SessionOutput {
+    fn handle_sequence(
+        &mut self,
+        session_id: &str,
+        sequence: Sequence<'_>,
+        local_host: Option<&str>,
+    ) {
+        let (payload, range) = match sequence {
+            Sequence::Osc { payload, range } => (payload, range),
+            Sequence::Bell => return self.ring_bell(session_id),
+        };
+        if let Some(cwd) = osc7_cwd(payload, local_host) {
+            self.set_cwd(session_id, cwd);
+        } else if let Some(title) = osc_title(payload) {
+            if self.title.as_deref() != Some(title.as_str()) {
+                self.sink.title(session_id, &title);
+                self.title = Some(title);
             }
-            Ok(n) => {
-                // Prepend any leftover bytes from previous read
-                let chunk = if utf8_remainder.is_empty() {
-                    &buf[..n]
-                } else {
-                    utf8_remainder.extend_from_slice(&buf[..n]);
-                    utf8_remainder.as_slice()
+        } else if let Some(notification) = osc_notification(payload) {
+            self.sink.notify(session_id, &notification);
+        } else {
+            self.blocks
+                .handle(payload, range, &self.scrollback, self.cwd.as_deref());
+        }
+    }
+
+    fn ring_bell(&mut self, session_id: &str) {
+        let now = Instant::now();
+        if self
+            .last_bell
+            .is_some_and(|last| now.duration_since(last) < BELL_INTERVAL)
+        {
+            return;
+        }
+        self.last_bell = Some(now);
+        self.monitor.bell();
+        self.sink.bell(session_id);
+    }
+
+    fn fire_trigger(&mut self, session_id: &str, fired: TriggerMatch) {
+        match &fired.action {
+            TriggerAction::Notify { title } => {
+                let notification = Notification {
+                    title: title.clone().or_else(|| Some(fired.name.clone())),
+                    body: fired.line.clone(),
                 };
+                self.sink.notify(session_id, &notification);
+            }
+            TriggerAction::Bell => self.ring_bell(session_id),
+            TriggerAction::Respond { input } => {
+                let _ = self.input.send(session_id, input.as_bytes());
+            }
+            // Left to the terminal, which is told about every trigger below
+            TriggerAction::Highlight { .. } => {}
+        }
+        self.sink.trigger(session_id, &fired);
+    }
 
-                // Find the last valid UTF-8 boundary
-                match std::str::from_utf8(chunk) {
-                    Ok(s) => {
-                        let _ = handle.emit(&output_event, s);
-                        utf8_remainder.clear();
-                    }
-                    Err(e) => {
-                        let valid_up_to = e.valid_up_to();
-                        // Emit the valid portion
-                        if valid_up_to > 0 {
-                            // Safety: we know bytes up to valid_up_to are valid UTF-8
-                            let valid =
-                                unsafe { std::str::from_utf8_unchecked(&chunk[..valid_up_to]) };
-                            let _ = handle.emit(&output_event, valid);
-                        }
-                        // Keep the incomplete tail for next read
-                        let remainder = &chunk[valid_up_to..];
-                        if remainder.len() > 4 {
-                            // More than max UTF-8 sequence length — this isn't
-                            // just an incomplete sequence, emit lossy and clear
-                            let data = String::from_utf8_lossy(remainder).to_string();
-                            let _ = handle.emit(&output_event, &data);
-                            utf8_remainder.clear();
-                        } else {
-                            utf8_remainder = remainder.to_vec();
-                        }
+    /// OSC 7 reports the path the shell was given, symlinks included, while /proc
+    /// has it resolved; the same directory by either name is not a change
+    fn set_cwd(&mut self, session_id: &str, cwd: String) {
+        let unchanged = self
+            .cwd
+            .as_deref()
+            .is_some_and(|known| known == cwd || same_dir(known, &cwd));
+        if !unchanged {
+            self.sink.cwd(session_id, &cwd);
+            self.cwd = Some(cwd);
+        }
+    }
+}
+
+fn same_dir(a: &str, b: &str) -> bool {
+    match (Path::new(a).canonicalize(), Path::new(b).canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Output handed to a connected sink that its consumer has not acknowledged yet.
+/// Above the high watermark the reader stops reading the PTY, so the kernel buffer
+/// fills up and stalls the child until the consumer catches up
+#[derive(Default)]
+struct FlowControl {
+    unacked: Mutex<usize>,
+    drained: Condvar,
+}
+
+impl FlowControl {
+    fn sent(&self, bytes: usize) {
+        *self.unacked.lock() += bytes;
+    }
+
+    fn ack(&self, bytes: usize) {
+        let mut unacked = self.unacked.lock();
+        *unacked = unacked.saturating_sub(bytes);
+        if *unacked <= LOW_WATERMARK {
+            self.drained.notify_all();
+        }
+    }
+
+    /// Forget outstanding output, e.g. when a new consumer takes over the session
+    fn reset(&self) {
+        *self.unacked.lock() = 0;
+        self.drained.notify_all();
+    }
+
+    /// Block while over the high watermark, until acks drain it to the low watermark
+    /// or `detached` reports that nobody is left to send them
+    fn wait_for_capacity(&self, detached: impl Fn() -> bool) {
+        let mut unacked = self.unacked.lock();
+        if *unacked < HIGH_WATERMARK {
+            return;
+        }
+        while *unacked > LOW_WATERMARK {
+            self.drained.wait_for(&mut unacked, DETACH_POLL);
+            // Checked without holding the counter, which the batcher takes under the output lock
+            if *unacked > LOW_WATERMARK && MutexGuard::unlocked(&mut unacked, &detached) {
+                *unacked = 0;
+            }
+        }
+    }
+}
+
+/// Start the threads that move a session's output from the PTY to its sink,
+/// ending with the exit event once the output is drained
+fn spawn_output_pipeline(
+    reader: Box<dyn Read + Send>,
+    session_id: String,
+    output: Arc<Mutex<SessionOutput>>,
+    flow: Arc<FlowControl>,
+    status: Arc<ChildStatus>,
+    foreground: Option<ForegroundWatcher>,
+) {
+    let (tx, rx) = mpsc::sync_channel(READ_QUEUE_DEPTH);
+    let out = output.clone();
+    let f = flow.clone();
+    let reader = std::thread::spawn(move || {
+        run_pty_reader(reader, tx, &f, || !out.lock().sink.is_connected())
+    });
+    std::thread::spawn(move || {
+        run_output_batcher(rx, &session_id, &output, &flow, foreground);
+        // The batcher only returns once the reader has stopped
+        let reader_error = reader
+            .join()
+            .unwrap_or_else(|_| Some("PTY reader panicked".to_string()));
+        let mut info = status.wait_timeout(EXIT_STATUS_TIMEOUT).unwrap_or_default();
+        info.reader_error = reader_error;
+        let mut out = output.lock();
+        if let Some(Err(e)) = out.recording.take().map(Recorder::finish) {
+            log::warn!("Failed to finish recording session {}: {}", session_id, e);
+        }
+        if let Some(Err(e)) = out.log.take().map(SessionLog::finish) {
+            log::warn!("Failed to finish log of session {}: {}", session_id, e);
+        }
+        out.sink.exit(&session_id, &info);
+        out.exit = Some(info);
+    });
+}
+
+/// Sending side of a session's input writer
+#[derive(Clone)]
+struct InputQueue {
+    tx: mpsc::Sender<Vec<u8>>,
+    /// Bytes sent but not yet written to the PTY
+    queued: Arc<AtomicUsize>,
+}
+
+impl InputQueue {
+    /// Queue `data` without blocking, refusing it once a session that stopped reading
+    /// has `MAX_QUEUED_INPUT` bytes waiting
+    fn send(&self, session_id: &str, data: &[u8]) -> Result<(), AppError> {
+        let queued = self.queued.fetch_add(data.len(), Ordering::Relaxed);
+        if queued + data.len() > MAX_QUEUED_INPUT {
+            self.queued.fetch_sub(data.len(), Ordering::Relaxed);
+            return Err(AppError::Pty(format!(
+                "Session {} is not reading its input ({} bytes still queued)",
+                session_id, queued
+            )));
+        }
+        if self.tx.send(data.to_vec()).is_err() {
+            self.queued.fetch_sub(data.len(), Ordering::Relaxed);
+            return Err(AppError::Pty(format!(
+                "Session {} no longer accepts input",
+                session_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Whether the terminal is reading a line without echoing it, as password prompts do.
+/// Keeps a descriptor of its own so the writer thread can ask without the session
+struct EchoProbe {
+    #[cfg(unix)]
+    fd: Option<std::os::fd::OwnedFd>,
+}
+
+impl EchoProbe {
+    #[cfg(unix)]
+    fn new(master: &dyn MasterPty) -> Self {
+        use std::os::fd::BorrowedFd;
+
+        let fd = master.as_raw_fd().and_then(|fd| {
+            // Safety: the master fd is open for as long as `master` is borrowed
+            let borrowed = unsafe { BorrowedFd::borrow_raw(fd) };
+            borrowed.try_clone_to_owned().ok()
+        });
+        EchoProbe { fd }
+    }
+
+    #[cfg(not(unix))]
+    fn new(_master: &dyn MasterPty) -> Self {
+        EchoProbe {}
+    }
+
+    /// Line editors also turn echo off, but read raw input rather than whole lines
+    #[cfg(unix)]
+    fn reading_password(&self) -> bool {
+        use std::os::fd::AsRawFd;
+
+        let Some(fd) = &self.fd else {
+            return false;
+        };
+        let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+        if unsafe { libc::tcgetattr(fd.as_raw_fd(), termios.as_mut_ptr()) } != 0 {
+            return false;
+        }
+        let flags = unsafe { termios.assume_init() }.c_lflag;
+        flags & libc::ICANON != 0 && flags & libc::ECHO == 0
+    }
+
+    #[cfg(not(unix))]
+    fn reading_password(&self) -> bool {
+        false
+    }
+}
+
+/// Feed a session's input to the PTY from a thread of its own, so a child that stops
+/// reading stalls only its own input rather than every caller of the manager
+fn spawn_input_writer(
+    mut writer: Box<dyn Write + Send>,
+    session_id: String,
+    probe: EchoProbe,
+    output: Weak<Mutex<SessionOutput>>,
+) -> InputQueue {
+    let (tx, rx) = mpsc::channel::<Vec<u8>>();
+    let queued = Arc::new(AtomicUsize::new(0));
+    let written = queued.clone();
+    std::thread::spawn(move || {
+        // Ends once the session is dropped and its sender with it
+        for data in rx {
+            // Decided before the input goes out, so its echo can't reach the log first
+            if probe.reading_password() {
+                if let Some(output) = output.upgrade() {
+                    if let Some(log) = output.lock().log.as_mut() {
+                        log.hide_input();
                     }
                 }
             }
-            Err(_) => {
-                let _ = handle.emit(&exit_event, ());
+            let result = writer.write_all(&data).and_then(|_| writer.flush());
+            written.fetch_sub(data.len(), Ordering::Relaxed);
+            if let Err(e) = result {
+                log::warn!("Stopped writing input to session {}: {}", session_id, e);
                 break;
             }
         }
+    });
+    InputQueue { tx, queued }
+}
+
+/// Whether a read error just means the other side of the PTY hung up
+fn is_hangup(err: &std::io::Error) -> bool {
+    // Linux reports EIO on the master once every slave fd is closed
+    #[cfg(unix)]
+    if err.raw_os_error() == Some(libc::EIO) {
+        return true;
+    }
+    err.kind() == std::io::ErrorKind::BrokenPipe
+}
+
+/// Forward PTY reads to the batcher, returning why reading failed if it did
+fn run_pty_reader(
+    mut reader: Box<dyn Read + Send>,
+    tx: mpsc::SyncSender<Vec<u8>>,
+    flow: &FlowControl,
+    detached: impl Fn() -> bool,
+) -> Option<String> {
+    let mut buf = [0u8; 16384];
+    loop {
+        flow.wait_for_capacity(&detached);
+        match reader.read(&mut buf) {
+            Ok(0) => return None,
+            Ok(n) => {
+                if tx.send(buf[..n].to_vec()).is_err() {
+                    return None;
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) if is_hangup(&e) => return None,
+            Err(e) => return Some(e.to_string()),
+        }
+    }
+}
+
+/// Coalesce reads into batches bounded by `FLUSH_INTERVAL` and `FLUSH_BYTES`,
+/// so a flood of output becomes a handful of IPC messages instead of thousands.
+/// Between batches, and while the session is idle, reports foreground job changes
+fn run_output_batcher(
+    rx: mpsc::Receiver<Vec<u8>>,
+    session_id: &str,
+    output: &Mutex<SessionOutput>,
+    flow: &FlowControl,
+    mut foreground: Option<ForegroundWatcher>,
+) {
+    let local_host = local_hostname();
+    let mut osc = OscScanner::default();
+    let mut check_foreground = |output: &Mutex<SessionOutput>| {
+        if let Some(process) = foreground.as_mut().and_then(ForegroundWatcher::poll) {
+            let mut out = output.lock();
+            out.sink.foreground(session_id, &process);
+            // Covers shells without OSC 7 integration, and programs that chdir themselves
+            if let Some(cwd) = process.cwd {
+                out.set_cwd(session_id, cwd);
+            }
+        }
+    };
+    loop {
+        let mut batch = match rx.recv_timeout(FOREGROUND_POLL) {
+            Ok(chunk) => chunk,
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                let mut out = output.lock();
+                if let Some(alert) = out.monitor.check_silence(Instant::now()) {
+                    out.sink.monitor(session_id, &alert);
+                }
+                drop(out);
+                check_foreground(output);
+                continue;
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        let deadline = Instant::now() + FLUSH_INTERVAL;
+        while batch.len() < FLUSH_BYTES {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(remaining) {
+                Ok(chunk) => batch.extend_from_slice(&chunk),
+                Err(_) => break,
+            }
+        }
+        let mut out = output.lock();
+        out.bytes_out += batch.len() as u64;
+        out.scrollback.push(&batch);
+        if let Some(recorder) = out.recording.as_mut() {
+            if let Err(e) = recorder.output(&batch) {
+                log::warn!("Stopped recording session {}: {}", session_id, e);
+                out.recording = None;
+            }
+        }
+        if let Some(log) = out.log.as_mut() {
+            if let Err(e) = log.output(&batch) {
+                log::warn!("Stopped logging session {}: {}", session_id, e);
+                out.log = None;
+            }
+        }
+        osc.feed(&batch, |sequence| {
+            out.handle_sequence(session_id, sequence, local_host.as_deref())
+        });
+        for fired in out.triggers.feed(&batch) {
+            out.fire_trigger(session_id, fired);
+        }
+        // Nobody acks output that goes nowhere, so only count what a consumer receives
+        if out.sink.is_connected() {
+            flow.sent(batch.len());
+        }
+        out.sink.output(session_id, &batch);
+        if let Some(alert) = out.monitor.output(Instant::now()) {
+            out.sink.monitor(session_id, &alert);
+        }
+        drop(out);
+        check_foreground(output);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    pub id: String,
+    pub pid: Option<u32>,
+    pub cols: u16,
+    pub rows: u16,
+    /// When the session started, in milliseconds since the Unix epoch
+    pub started_ms: u64,
+    pub cwd: Option<String>,
+    pub title: Option<String>,
+    /// What the session was started with
+    pub launch: LaunchProfile,
+    /// The job in the foreground of the terminal, while the session is alive
+    pub foreground: Option<ForegroundProcess>,
+    /// Input written to the session and output read from it
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    /// Whether a client is currently receiving the session's output
+    pub attached: bool,
+    pub alive: bool,
+    /// How the session ended, once its output has all been delivered. Lets a client
+    /// that subscribed to `pty-exit-{id}` too late still learn of the exit
+    pub exit: Option<ExitInfo>,
+}
+
+/// A session's input, which can be written without holding the manager
+#[derive(Clone)]
+pub struct SessionInput {
+    session_id: String,
+    queue: InputQueue,
+    bytes_in: Arc<AtomicU64>,
+}
+
+impl SessionInput {
+    /// Queue input for the session. Never blocks, even if the child has stopped reading
+    pub fn write(&self, data: &str) -> Result<(), AppError> {
+        self.queue.send(&self.session_id, data.as_bytes())?;
+        self.bytes_in
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Where input typed into a session goes: the session alone, or its whole broadcast group
+pub enum InputTargets {
+    Session(SessionInput),
+    Group {
+        members: Vec<SessionInput>,
+        /// Members that are gone, reported with every write
+        missing: Vec<WriteFailure>,
+    },
+}
+
+impl InputTargets {
+    /// Members of a group that can't be written to are reported rather than ending the
+    /// broadcast
+    pub fn write(&self, data: &str) -> Result<Vec<WriteFailure>, AppError> {
+        match self {
+            InputTargets::Session(input) => {
+                input.write(data)?;
+                Ok(Vec::new())
+            }
+            InputTargets::Group { members, missing } => {
+                let mut failures = missing.clone();
+                failures.extend(members.iter().filter_map(|member| {
+                    let error = member.write(data).err()?;
+                    Some(WriteFailure {
+                        session_id: member.session_id.clone(),
+                        error: error.to_string(),
+                    })
+                }));
+                Ok(failures)
+            }
+        }
     }
 }
 
 pub struct PtySession {
     pub master: Box<dyn MasterPty + Send>,
-    pub writer: Box<dyn Write + Send>,
-    pub child: Box<dyn Child + Send + Sync>,
+    /// Queue to the session's input writer thread
+    input: InputQueue,
+    pub killer: Box<dyn ChildKiller + Send + Sync>,
+    pub pid: Option<u32>,
+    status: Arc<ChildStatus>,
+    output: Arc<Mutex<SessionOutput>>,
+    flow: Arc<FlowControl>,
     pub cols: u16,
     pub rows: u16,
+    pub cell: Option<CellSize>,
+    launch: LaunchProfile,
+    started: SystemTime,
+    bytes_in: Arc<AtomicU64>,
+}
+
+impl PtySession {
+    fn foreground(&self) -> Option<ForegroundProcess> {
+        #[cfg(unix)]
+        let process = self
+            .master
+            .process_group_leader()
+            .filter(|&pgid| pgid > 0)
+            .map(|pgid| ForegroundProcess::describe(pgid as u32, self.pid));
+        // Without job control, the best guess is the shell itself
+        #[cfg(not(unix))]
+        let process = self
+            .pid
+            .map(|pid| ForegroundProcess::describe(pid, Some(pid)));
+        process
+    }
+
+    /// Send `signal` to the foreground process group and to the shell's own group
+    #[cfg(unix)]
+    fn signal_groups(&self, signal: libc::c_int) {
+        let shell = self.pid.map(|pid| pid as libc::pid_t);
+        let foreground = self.master.process_group_leader();
+        if let Some(pgid) = foreground {
+            unsafe { libc::killpg(pgid, signal) };
+        }
+        // The shell leads its own session, so its group id is its pid
+        if let Some(pgid) = shell.filter(|&pgid| Some(pgid) != foreground) {
+            unsafe { libc::killpg(pgid, signal) };
+        }
+    }
+
+    /// Send `signal` to the foreground job, e.g. to stop a program that ignores ^C
+    #[cfg(unix)]
+    fn send_signal(&self, signal: PtySignal) -> Result<(), AppError> {
+        // With no job running, the shell itself is in the foreground
+        let pgid = self
+            .master
+            .process_group_leader()
+            .or(self.pid.map(|pid| pid as libc::pid_t))
+            .ok_or_else(|| AppError::Pty("Session has no process to signal".to_string()))?;
+        if unsafe { libc::killpg(pgid, signal.number()) } == -1 {
+            return Err(AppError::Pty(format!(
+                "Failed to send {}: {}",
+                signal.name(),
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn send_signal(&self, signal: PtySignal) -> Result<(), AppError> {
+        match signal {
+            PtySignal::Sigkill | PtySignal::Sigterm => {
+                self.killer.clone_killer().kill()?;
+                Ok(())
+            }
+            _ => Err(AppError::InvalidInput(format!(
+                "{} is not supported on this platform",
+                signal.name()
+            ))),
+        }
+    }
+
+    /// SIGHUP the session's process groups, so editors save their swap files and shells
+    /// write history, then SIGKILL whatever is still running after `grace`. Returns false
+    /// when the session can't be signalled, leaving the caller to kill the child
+    #[cfg(unix)]
+    fn hang_up(&self, grace: Duration) -> bool {
+        if self.pid.is_none() {
+            return false;
+        }
+        self.signal_groups(libc::SIGHUP);
+        if self.status.wait_timeout(grace).is_none() {
+            self.signal_groups(libc::SIGKILL);
+        }
+        true
+    }
+
+    #[cfg(not(unix))]
+    fn hang_up(&self, _grace: Duration) -> bool {
+        false
+    }
+
+    /// Stop the session's processes, returning once the child has been reaped
+    fn shut_down(self, grace: Duration) {
+        if self.status.get().is_some() {
+            return;
+        }
+        if !self.hang_up(grace) {
+            let _ = self.killer.clone_killer().kill();
+        }
+        if self.status.wait_timeout(REAP_TIMEOUT).is_none() {
+            log::warn!(
+                "Session process {:?} did not exit after being killed",
+                self.pid
+            );
+        }
+    }
 }
 
 pub struct PtyManager {
     sessions: HashMap<String, PtySession>,
+    groups: Groups,
 }
 
 impl PtyManager {
     pub fn new() -> Self {
         PtyManager {
             sessions: HashMap::new(),
+            groups: Groups::default(),
         }
     }
 
@@ -92,35 +823,17 @@ impl PtyManager {
         &mut self,
         cols: u16,
         rows: u16,
-        cwd: Option<String>,
-        app_handle: AppHandle,
+        cell: Option<CellSize>,
+        launch: &LaunchProfile,
+        scrollback_limits: &ScrollbackLimits,
+        sink: Arc<dyn SessionSink>,
     ) -> Result<String, AppError> {
         let pty_system = native_pty_system();
         let pair = pty_system
-            .openpty(PtySize {
-                rows,
-                cols,
-                pixel_width: 0,
-                pixel_height: 0,
-            })
+            .openpty(pty_size(cols, rows, cell))
             .map_err(|e| AppError::Pty(e.to_string()))?;
 
-        let mut cmd = CommandBuilder::new_default_prog();
-        cmd.env("TERM", "xterm-256color");
-        cmd.env("COLORTERM", "truecolor");
-        // Ensure locale is set for proper Unicode support in vim/TUI apps
-        if std::env::var("LANG").is_err() {
-            cmd.env("LANG", "en_US.UTF-8");
-        }
-        if let Some(dir) = &cwd {
-            cmd.cwd(dir);
-        }
-        if cwd.is_none() {
-            // Default to user's home directory if no cwd specified
-            if let Ok(home) = std::env::var("HOME") {
-                cmd.cwd(home);
-            }
-        }
+        let cmd = launch.build_command();
 
         let child = pair
             .slave
@@ -137,59 +850,1200 @@ impl PtyManager {
             .map_err(|e| AppError::Pty(e.to_string()))?;
 
         let session_id = Uuid::new_v4().to_string();
-
-        // Spawn background reader thread that emits PTY output to frontend
-        let sid = session_id.clone();
-        let handle = app_handle.clone();
-        std::thread::spawn(move || {
-            run_pty_reader(reader, handle, sid);
+        let probe = EchoProbe::new(pair.master.as_ref());
+        let output = Arc::new_cyclic(|output| {
+            let input = spawn_input_writer(writer, session_id.clone(), probe, output.clone());
+            Mutex::new(SessionOutput {
+                scrollback: Scrollback::new(scrollback_limits.clone()),
+                sink,
+                cwd: launch.working_dir(),
+                blocks: BlockTracker::default(),
+                title: None,
+                last_bell: None,
+                recording: None,
+                log: None,
+                triggers: Triggers::default(),
+                input,
+                monitor: Monitor::default(),
+                bytes_out: 0,
+                exit: None,
+            })
         });
+        let input = output.lock().input.clone();
+
+        let flow = Arc::new(FlowControl::default());
+        let status = Arc::new(ChildStatus::default());
+        let pid = child.process_id();
+        let foreground = ForegroundWatcher::new(pair.master.as_ref(), pid);
+        spawn_output_pipeline(
+            reader,
+            session_id.clone(),
+            output.clone(),
+            flow.clone(),
+            status.clone(),
+            Some(foreground),
+        );
+
+        // The waiter thread owns the child and reaps it; the session keeps a killer
+        let killer = child.clone_killer();
+        let st = status.clone();
+        std::thread::spawn(move || st.set(exit_status::wait_for_exit(child)));
 
         let session = PtySession {
             master: pair.master,
-            writer,
-            child,
+            input,
+            killer,
+            pid,
+            status,
+            output,
+            flow,
             cols,
             rows,
+            cell,
+            launch: launch.clone(),
+            started: SystemTime::now(),
+            bytes_in: Arc::new(AtomicU64::new(0)),
         };
 
         self.sessions.insert(session_id.clone(), session);
         Ok(session_id)
     }
 
-    pub fn write(&mut self, session_id: &str, data: &str) -> Result<(), AppError> {
-        let session = self
+    fn session(&self, session_id: &str) -> Result<&PtySession, AppError> {
+        self.sessions
+            .get(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session {} not found", session_id)))
+    }
+
+    /// Route a session's output to a new sink, replaying its scrollback there first
+    pub fn attach(&self, session_id: &str, sink: Arc<dyn SessionSink>) -> Result<(), AppError> {
+        let session = self.session(session_id)?;
+        let mut out = session.output.lock();
+        let contents = out.scrollback.contents();
+        if !contents.is_empty() {
+            sink.output(session_id, &contents);
+        }
+        out.sink = sink;
+        session.flow.reset();
+        Ok(())
+    }
+
+    /// The session's working directory, as last reported by the shell or seen in /proc
+    pub fn cwd(&self, session_id: &str) -> Result<Option<String>, AppError> {
+        Ok(self.session(session_id)?.output.lock().cwd.clone())
+    }
+
+    /// The job in the foreground of the session's terminal
+    pub fn foreground(&self, session_id: &str) -> Result<Option<ForegroundProcess>, AppError> {
+        Ok(self.session(session_id)?.foreground())
+    }
+
+    pub fn signal(&self, session_id: &str, signal: PtySignal) -> Result<(), AppError> {
+        self.session(session_id)?.send_signal(signal)
+    }
+
+    /// Record that the consumer has processed `bytes` of output, letting a paused reader resume
+    pub fn ack(&self, session_id: &str, bytes: usize) -> Result<(), AppError> {
+        self.session(session_id)?.flow.ack(bytes);
+        Ok(())
+    }
+
+    /// IDs of every session the manager holds, without reading their processes' state
+    pub fn session_ids(&self) -> Vec<String> {
+        self.sessions.keys().cloned().collect()
+    }
+
+    /// Every session the manager holds, oldest first, including ones whose process
+    /// has exited but that have not been closed yet
+    pub fn list(&self) -> Vec<SessionSummary> {
+        let mut sessions: Vec<SessionSummary> = self
             .sessions
-            .get_mut(session_id)
-            .ok_or_else(|| AppError::NotFound(format!("Session {} not found", session_id)))?;
-        session.writer.write_all(data.as_bytes())?;
-        session.writer.flush()?;
+            .iter()
+            .map(|(id, session)| {
+                let exit = session.status.get();
+                let out = session.output.lock();
+                SessionSummary {
+                    id: id.clone(),
+                    pid: session.pid,
+                    cols: session.cols,
+                    rows: session.rows,
+                    started_ms: session
+                        .started
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_millis() as u64,
+                    cwd: out.cwd.clone(),
+                    title: out.title.clone(),
+                    launch: session.launch.clone(),
+                    foreground: exit.is_none().then(|| session.foreground()).flatten(),
+                    bytes_in: session.bytes_in.load(Ordering::Relaxed),
+                    bytes_out: out.bytes_out,
+                    attached: out.sink.is_connected(),
+                    alive: exit.is_none(),
+                    exit: out.exit.clone(),
+                }
+            })
+            .collect();
+        sessions.sort_by_key(|session| session.started_ms);
+        sessions
+    }
+
+    /// Whether any session's process is still running
+    pub fn any_alive(&self) -> bool {
+        self.sessions
+            .values()
+            .any(|session| session.status.get().is_none())
+    }
+
+    /// The session's input, to write once the manager's lock is released
+    pub fn input(&self, session_id: &str) -> Result<SessionInput, AppError> {
+        let session = self.session(session_id)?;
+        Ok(SessionInput {
+            session_id: session_id.to_string(),
+            queue: session.input.clone(),
+            bytes_in: session.bytes_in.clone(),
+        })
+    }
+
+    /// Where input typed into a session goes, fanning out to the rest of its broadcast group
+    pub fn input_targets(&self, session_id: &str) -> Result<InputTargets, AppError> {
+        let Some(member_ids) = self.groups.members_with(session_id) else {
+            return Ok(InputTargets::Session(self.input(session_id)?));
+        };
+        let mut members = Vec::new();
+        let mut missing = Vec::new();
+        for member in member_ids {
+            match self.input(member) {
+                Ok(input) => members.push(input),
+                Err(error) => missing.push(WriteFailure {
+                    session_id: member.clone(),
+                    error: error.to_string(),
+                }),
+            }
+        }
+        Ok(InputTargets::Group { members, missing })
+    }
+
+    /// Group sessions so input to any of them goes to all, returning the group's ID
+    pub fn create_group(&mut self, members: &[String]) -> Result<String, AppError> {
+        for member in members {
+            self.session(member)?;
+        }
+        self.groups.create(members)
+    }
+
+    pub fn set_group_members(
+        &mut self,
+        group_id: &str,
+        members: &[String],
+    ) -> Result<(), AppError> {
+        for member in members {
+            self.session(member)?;
+        }
+        self.groups.set_members(group_id, members)
+    }
+
+    /// Ungroup a group's sessions, which keep running on their own
+    pub fn dissolve_group(&mut self, group_id: &str) -> Result<(), AppError> {
+        self.groups.dissolve(group_id)
+    }
+
+    pub fn groups(&self) -> Vec<SessionGroup> {
+        self.groups.list()
+    }
+
+    /// Replace the trigger rules matched against the session's output
+    pub fn set_triggers(
+        &self,
+        session_id: &str,
+        triggers: Arc<TriggerSet>,
+    ) -> Result<(), AppError> {
+        self.session(session_id)?.output.lock().triggers = Triggers::new(triggers);
+        Ok(())
+    }
+
+    /// Title the session as if its program had set it with OSC 2
+    pub fn set_title(&self, session_id: &str, title: &str) -> Result<(), AppError> {
+        let session = self.session(session_id)?;
+        let mut out = session.output.lock();
+        out.title = Some(title.to_string());
+        out.sink.title(session_id, title);
+        Ok(())
+    }
+
+    /// Watch the session for activity or silence, clearing earlier alerts and bells
+    pub fn set_monitor(&self, session_id: &str, settings: MonitorSettings) -> Result<(), AppError> {
+        self.session(session_id)?
+            .output
+            .lock()
+            .monitor
+            .configure(settings);
+        Ok(())
+    }
+
+    pub fn monitor_status(&self, session_id: &str) -> Result<MonitorStatus, AppError> {
+        Ok(self.session(session_id)?.output.lock().monitor.status())
+    }
+
+    /// Start a plain-text log of the session's output in `dir`, returning its path
+    pub fn start_log(
+        &self,
+        session_id: &str,
+        dir: &Path,
+        rotation: &LogRotation,
+    ) -> Result<PathBuf, AppError> {
+        let session = self.session(session_id)?;
+        let mut out = session.output.lock();
+        if out.log.is_some() {
+            return Err(AppError::InvalidInput(format!(
+                "Session {} is already being logged",
+                session_id
+            )));
+        }
+        let log = SessionLog::create(dir, session_id, rotation)?;
+        let path = log.path().to_path_buf();
+        out.log = Some(log);
+        Ok(path)
+    }
+
+    /// Stop logging the session, returning the path of the log
+    pub fn stop_log(&self, session_id: &str) -> Result<PathBuf, AppError> {
+        let log = self
+            .session(session_id)?
+            .output
+            .lock()
+            .log
+            .take()
+            .ok_or_else(|| {
+                AppError::NotFound(format!("Session {} is not being logged", session_id))
+            })?;
+        Ok(log.finish()?)
+    }
+
+    /// Start writing the session's output to an asciinema v2 recording at `path`
+    pub fn start_recording(&self, session_id: &str, path: &Path) -> Result<(), AppError> {
+        let session = self.session(session_id)?;
+        let mut out = session.output.lock();
+        if out.recording.is_some() {
+            return Err(AppError::InvalidInput(format!(
+                "Session {} is already being recorded",
+                session_id
+            )));
+        }
+        out.recording = Some(Recorder::create(path, session.cols, session.rows)?);
         Ok(())
     }
 
-    pub fn resize(&mut self, session_id: &str, cols: u16, rows: u16) -> Result<(), AppError> {
+    /// Stop recording the session, returning the path of the finished recording
+    pub fn stop_recording(&self, session_id: &str) -> Result<PathBuf, AppError> {
+        let recorder = self
+            .session(session_id)?
+            .output
+            .lock()
+            .recording
+            .take()
+            .ok_or_else(|| {
+                AppError::NotFound(format!("Session {} is not being recorded", session_id))
+            })?;
+        Ok(recorder.finish()?)
+    }
+
+    /// Commands the shell has marked with OSC 133, oldest first
+    pub fn blocks(&self, session_id: &str) -> Result<Vec<CommandBlock>, AppError> {
+        Ok(self.session(session_id)?.output.lock().blocks.list())
+    }
+
+    /// Output of one command block, as much of it as scrollback still holds
+    pub fn block_output(&self, session_id: &str, block_id: u64) -> Result<Vec<u8>, AppError> {
+        let session = self.session(session_id)?;
+        let out = session.output.lock();
+        let block = out
+            .blocks
+            .get(block_id)
+            .ok_or_else(|| AppError::NotFound(format!("Block {} not found", block_id)))?;
+        let end = block.end.unwrap_or(out.scrollback.end_offset());
+        Ok(out.scrollback.range(block.output_start, end))
+    }
+
+    pub fn scrollback(&self, session_id: &str) -> Result<Vec<u8>, AppError> {
+        let contents = self
+            .session(session_id)?
+            .output
+            .lock()
+            .scrollback
+            .contents();
+        Ok(contents)
+    }
+
+    /// Resize the terminal. Without a `cell` size, the last one given is kept
+    pub fn resize(
+        &mut self,
+        session_id: &str,
+        cols: u16,
+        rows: u16,
+        cell: Option<CellSize>,
+    ) -> Result<(), AppError> {
         let session = self
             .sessions
             .get_mut(session_id)
             .ok_or_else(|| AppError::NotFound(format!("Session {} not found", session_id)))?;
+        let cell = cell.or(session.cell);
         session
             .master
-            .resize(PtySize {
-                rows,
-                cols,
-                pixel_width: 0,
-                pixel_height: 0,
-            })
+            .resize(pty_size(cols, rows, cell))
             .map_err(|e| AppError::Pty(e.to_string()))?;
         session.cols = cols;
         session.rows = rows;
+        session.cell = cell;
+        let mut out = session.output.lock();
+        if let Some(Err(e)) = out.recording.as_mut().map(|r| r.resize(cols, rows)) {
+            log::warn!("Stopped recording session {}: {}", session_id, e);
+            out.recording = None;
+        }
         Ok(())
     }
 
-    pub fn close(&mut self, session_id: &str) -> Result<(), AppError> {
-        if let Some(mut session) = self.sessions.remove(session_id) {
-            let _ = session.child.kill();
+    /// Hang up a session and forget it. The shutdown runs in the background,
+    /// giving the processes `grace` to exit before they are killed
+    pub fn close(&mut self, session_id: &str, grace: Duration) -> Result<(), AppError> {
+        self.groups.remove_session(session_id);
+        if let Some(session) = self.sessions.remove(session_id) {
+            std::thread::spawn(move || session.shut_down(grace));
         }
         Ok(())
     }
+
+    /// Hang up every session in parallel, returning once all of them are gone
+    pub fn close_all(&mut self, grace: Duration) {
+        self.groups = Groups::default();
+        let shutdowns: Vec<_> = self
+            .sessions
+            .drain()
+            .map(|(_, session)| std::thread::spawn(move || session.shut_down(grace)))
+            .collect();
+        for shutdown in shutdowns {
+            let _ = shutdown.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::MonitorKind;
+    use crate::test_support::wait_until;
+    use crate::triggers::TriggerRule;
+
+    /// Hands out its data a few KiB per read, like a busy PTY
+    struct ChunkedReader {
+        data: Vec<u8>,
+        pos: usize,
+        chunk: usize,
+    }
+
+    impl Read for ChunkedReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.chunk.min(buf.len()).min(self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSink {
+        data: Mutex<Vec<u8>>,
+        calls: Mutex<usize>,
+        exited: Mutex<Option<ExitInfo>>,
+        foreground: Mutex<Option<ForegroundProcess>>,
+        cwd: Mutex<Option<String>>,
+        title: Mutex<Option<String>>,
+        bells: Mutex<usize>,
+        notifications: Mutex<Vec<Notification>>,
+        triggers: Mutex<Vec<TriggerMatch>>,
+        alerts: Mutex<Vec<MonitorAlert>>,
+        /// Acknowledge output as soon as it arrives, like a consumer that always keeps up
+        auto_ack: Option<Arc<FlowControl>>,
+    }
+
+    impl SessionSink for RecordingSink {
+        fn output(&self, _session_id: &str, data: &[u8]) {
+            self.data.lock().extend_from_slice(data);
+            *self.calls.lock() += 1;
+            if let Some(flow) = &self.auto_ack {
+                flow.ack(data.len());
+            }
+        }
+
+        fn exit(&self, _session_id: &str, info: &ExitInfo) {
+            *self.exited.lock() = Some(info.clone());
+        }
+
+        fn foreground(&self, _session_id: &str, process: &ForegroundProcess) {
+            *self.foreground.lock() = Some(process.clone());
+        }
+
+        fn cwd(&self, _session_id: &str, cwd: &str) {
+            *self.cwd.lock() = Some(cwd.to_string());
+        }
+
+        fn title(&self, _session_id: &str, title: &str) {
+            *self.title.lock() = Some(title.to_string());
+        }
+
+        fn bell(&self, _session_id: &str) {
+            *self.bells.lock() += 1;
+        }
+
+        fn notify(&self, _session_id: &str, notification: &Notification) {
+            self.notifications.lock().push(notification.clone());
+        }
+
+        fn trigger(&self, _session_id: &str, fired: &TriggerMatch) {
+            self.triggers.lock().push(fired.clone());
+        }
+
+        fn monitor(&self, _session_id: &str, alert: &MonitorAlert) {
+            self.alerts.lock().push(alert.clone());
+        }
+    }
+
+    fn wait_for_output(sink: &RecordingSink, needle: &str) {
+        wait_until(needle, || {
+            String::from_utf8_lossy(&sink.data.lock()).contains(needle)
+        });
+    }
+
+    fn wait_for_exit(sink: &RecordingSink) {
+        wait_until("exit", || sink.exited.lock().is_some());
+    }
+
+    fn output_for(sink: &Arc<RecordingSink>) -> Arc<Mutex<SessionOutput>> {
+        Arc::new(Mutex::new(SessionOutput {
+            scrollback: Scrollback::new(ScrollbackLimits::default()),
+            sink: sink.clone(),
+            cwd: None,
+            blocks: BlockTracker::default(),
+            title: None,
+            last_bell: None,
+            recording: None,
+            log: None,
+            triggers: Triggers::default(),
+            input: InputQueue {
+                tx: mpsc::channel().0,
+                queued: Arc::default(),
+            },
+            monitor: Monitor::default(),
+            bytes_out: 0,
+            exit: None,
+        }))
+    }
+
+    fn exited_cleanly() -> Arc<ChildStatus> {
+        let status = Arc::new(ChildStatus::default());
+        status.set(ExitInfo {
+            exit_code: Some(0),
+            ..Default::default()
+        });
+        status
+    }
+
+    #[test]
+    fn test_output_is_coalesced_without_loss() {
+        const CHUNK: usize = 4096;
+        const READS: usize = 1000;
+        // Every byte value, so invalid UTF-8 and NULs are included
+        let data: Vec<u8> = (0..CHUNK * READS).map(|i| (i % 251) as u8).collect();
+        let flow = Arc::new(FlowControl::default());
+        let sink = Arc::new(RecordingSink {
+            auto_ack: Some(flow.clone()),
+            ..Default::default()
+        });
+        let reader = ChunkedReader {
+            data: data.clone(),
+            pos: 0,
+            chunk: CHUNK,
+        };
+
+        spawn_output_pipeline(
+            Box::new(reader),
+            "s".to_string(),
+            output_for(&sink),
+            flow,
+            exited_cleanly(),
+            None,
+        );
+        wait_for_exit(&sink);
+
+        assert!(*sink.data.lock() == data, "Output bytes differ from input");
+        let calls = *sink.calls.lock();
+        assert!(
+            calls <= READS / 4,
+            "Expected coalesced output, got {} sink calls for {} reads",
+            calls,
+            READS
+        );
+    }
+
+    #[test]
+    fn test_reader_pauses_until_output_is_acked() {
+        let total = 8 * HIGH_WATERMARK;
+        let data: Vec<u8> = (0..total).map(|i| (i % 256) as u8).collect();
+        let flow = Arc::new(FlowControl::default());
+        let sink = Arc::new(RecordingSink::default());
+        let reader = ChunkedReader {
+            data: data.clone(),
+            pos: 0,
+            chunk: 4096,
+        };
+        spawn_output_pipeline(
+            Box::new(reader),
+            "s".to_string(),
+            output_for(&sink),
+            flow.clone(),
+            exited_cleanly(),
+            None,
+        );
+
+        // Without acks, delivery stalls a little past the high watermark
+        wait_until("the high watermark", || {
+            sink.data.lock().len() >= HIGH_WATERMARK
+        });
+        // A chunk already read when the watermark was crossed may still arrive
+        std::thread::sleep(Duration::from_millis(100));
+        let stalled = sink.data.lock().len();
+        assert!(stalled < total / 2);
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(sink.data.lock().len(), stalled);
+
+        // Acking what arrived lets the rest through
+        let mut acked = 0;
+        wait_until("exit", || {
+            let received = sink.data.lock().len();
+            flow.ack(received - acked);
+            acked = received;
+            sink.exited.lock().is_some()
+        });
+        assert!(*sink.data.lock() == data, "Output bytes differ from input");
+    }
+
+    #[test]
+    fn test_reader_resumes_when_consumer_detaches() {
+        let total = 4 * HIGH_WATERMARK;
+        let flow = Arc::new(FlowControl::default());
+        let sink = Arc::new(RecordingSink::default());
+        let output = output_for(&sink);
+        let reader = ChunkedReader {
+            data: vec![b'x'; total],
+            pos: 0,
+            chunk: 4096,
+        };
+        spawn_output_pipeline(
+            Box::new(reader),
+            "s".to_string(),
+            output.clone(),
+            flow,
+            exited_cleanly(),
+            None,
+        );
+
+        std::thread::sleep(Duration::from_millis(200));
+        assert!(sink.data.lock().len() < total);
+        // Detached output only lands in the scrollback, which keeps the PTY drained
+        let null: Arc<dyn SessionSink> = Arc::new(NullSink);
+        let detached = std::mem::replace(&mut output.lock().sink, null);
+        wait_until("the reader to resume", || Arc::strong_count(&output) == 1);
+        drop(detached);
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("device gone"))
+        }
+    }
+
+    #[test]
+    fn test_reader_failure_is_reported_on_exit() {
+        let sink = Arc::new(RecordingSink::default());
+        spawn_output_pipeline(
+            Box::new(FailingReader),
+            "s".to_string(),
+            output_for(&sink),
+            Arc::new(FlowControl::default()),
+            exited_cleanly(),
+            None,
+        );
+        wait_for_exit(&sink);
+
+        let info = sink.exited.lock().clone().unwrap();
+        assert_eq!(info.exit_code, Some(0));
+        assert_eq!(info.reader_error.as_deref(), Some("device gone"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_session_output_arrives_intact() {
+        let launch = LaunchProfile {
+            program: Some("sh".to_string()),
+            args: vec![
+                "-c".to_string(),
+                "head -c 300000 /dev/zero | tr '\\0' x".to_string(),
+            ],
+            ..Default::default()
+        };
+        let sink = Arc::new(RecordingSink::default());
+        let mut manager = PtyManager::new();
+        manager
+            .create_session(
+                80,
+                24,
+                None,
+                &launch,
+                &ScrollbackLimits::default(),
+                sink.clone(),
+            )
+            .unwrap();
+        wait_for_exit(&sink);
+
+        let data = sink.data.lock();
+        assert_eq!(data.iter().filter(|&&b| b == b'x').count(), 300_000);
+        let info = sink.exited.lock().clone().unwrap();
+        assert_eq!(info.exit_code, Some(0));
+        assert_eq!(info.reader_error, None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_exited_child_is_reaped() {
+        let launch = LaunchProfile {
+            program: Some("sh".to_string()),
+            args: vec!["-c".to_string(), "exit 7".to_string()],
+            ..Default::default()
+        };
+        let sink = Arc::new(RecordingSink::default());
+        let mut manager = PtyManager::new();
+        let id = manager
+            .create_session(
+                80,
+                24,
+                None,
+                &launch,
+                &ScrollbackLimits::default(),
+                sink.clone(),
+            )
+            .unwrap();
+        let pid = manager.session(&id).unwrap().pid.unwrap();
+        wait_for_exit(&sink);
+
+        assert_eq!(sink.exited.lock().clone().unwrap().exit_code, Some(7));
+        let summary = &manager.list()[0];
+        assert!(!summary.alive);
+        assert_eq!(summary.exit.as_ref().unwrap().exit_code, Some(7));
+        assert!(summary.foreground.is_none());
+        // A zombie would still have a /proc entry
+        assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists());
+    }
+
+    #[cfg(unix)]
+    fn start_script(manager: &mut PtyManager, script: &str) -> (String, Arc<RecordingSink>) {
+        let launch = LaunchProfile {
+            program: Some("sh".to_string()),
+            args: vec!["-c".to_string(), script.to_string()],
+            ..Default::default()
+        };
+        let sink = Arc::new(RecordingSink::default());
+        let id = manager
+            .create_session(
+                80,
+                24,
+                None,
+                &launch,
+                &ScrollbackLimits::default(),
+                sink.clone(),
+            )
+            .unwrap();
+        wait_for_output(&sink, "ready");
+        (id, sink)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_close_all_lets_processes_handle_sighup() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let marker = dir.path().join("hup");
+        let script = format!(
+            "trap 'echo bye > {}; exit 0' HUP; echo ready; while :; do sleep 0.05; done",
+            marker.display()
+        );
+        let mut manager = PtyManager::new();
+        let (_, sink) = start_script(&mut manager, &script);
+
+        manager.close_all(Duration::from_secs(5));
+
+        assert_eq!(std::fs::read_to_string(&marker).unwrap().trim(), "bye");
+        wait_for_exit(&sink);
+        assert_eq!(sink.exited.lock().clone().unwrap().exit_code, Some(0));
+        assert!(manager.list().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_close_escalates_to_sigkill_after_grace() {
+        let mut manager = PtyManager::new();
+        let (id, sink) = start_script(&mut manager, "trap '' HUP; echo ready; exec sleep 60");
+
+        let started = Instant::now();
+        manager.close(&id, Duration::from_millis(200)).unwrap();
+        assert!(manager.list().is_empty());
+        wait_for_exit(&sink);
+
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        let info = sink.exited.lock().clone().unwrap();
+        assert_eq!(info.signal, Some(libc::SIGKILL));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signal_reaches_foreground_job() {
+        let mut manager = PtyManager::new();
+        let (id, sink) = start_script(&mut manager, "echo ready; exec sleep 60");
+
+        manager.signal(&id, PtySignal::Sigterm).unwrap();
+        wait_for_exit(&sink);
+
+        let info = sink.exited.lock().clone().unwrap();
+        assert_eq!(info.signal, Some(libc::SIGTERM));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_foreground_change_is_reported() {
+        let mut manager = PtyManager::new();
+        let (id, sink) = start_script(&mut manager, "echo ready; exec sleep 60");
+
+        wait_until("a foreground event", || {
+            sink.foreground.lock().as_ref().map(|p| p.name.as_str()) == Some("sleep")
+        });
+
+        let process = manager.foreground(&id).unwrap().unwrap();
+        assert_eq!(process.name, "sleep");
+        assert_eq!(process.argv, vec!["sleep", "60"]);
+        assert_eq!(Some(process.pid), manager.session(&id).unwrap().pid);
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cwd_follows_osc7_reports() {
+        let mut manager = PtyManager::new();
+        let (id, sink) = start_script(
+            &mut manager,
+            r"cd /tmp; printf '\033]7;file://localhost/tmp\007'; echo ready; exec sleep 60",
+        );
+
+        wait_until("a cwd event", || sink.cwd.lock().as_deref() == Some("/tmp"));
+
+        assert_eq!(manager.cwd(&id).unwrap().as_deref(), Some("/tmp"));
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cwd_through_a_symlink_is_not_a_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("target");
+        let link = dir.path().join("link");
+        std::fs::create_dir(&target).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let link = link.to_string_lossy().into_owned();
+        let resolved = target
+            .canonicalize()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+
+        let sink = Arc::new(RecordingSink::default());
+        let output = output_for(&sink);
+        let mut out = output.lock();
+        out.set_cwd("s", link.clone());
+        *sink.cwd.lock() = None;
+        out.set_cwd("s", resolved);
+        assert_eq!(*sink.cwd.lock(), None);
+        assert_eq!(out.cwd.as_deref(), Some(link.as_str()));
+
+        out.set_cwd("s", "/".to_string());
+        assert_eq!(sink.cwd.lock().as_deref(), Some("/"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_osc133_marks_become_blocks() {
+        let mut manager = PtyManager::new();
+        let (id, sink) = start_script(
+            &mut manager,
+            r"printf '\033]133;A\007$ \033]133;B\007'; printf 'make\r\n\033]133;C\007'; \
+              printf 'built\n\033]133;D;1\007'; echo ready; exec sleep 60",
+        );
+        drop(sink);
+
+        let blocks = manager.blocks(&id).unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].command, "make");
+        assert_eq!(blocks[0].exit_code, Some(1));
+        assert_eq!(manager.block_output(&id, 0).unwrap(), b"built\r\n");
+        assert!(matches!(
+            manager.block_output(&id, 7),
+            Err(AppError::NotFound(_))
+        ));
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_bash_integration_reports_blocks_and_cwd() {
+        let home = tempfile::TempDir::new().unwrap();
+        let mut env = std::collections::BTreeMap::new();
+        // An empty home, so no user startup files get in the way
+        env.insert("HOME".to_string(), home.path().display().to_string());
+        let launch = LaunchProfile {
+            program: Some("bash".to_string()),
+            env,
+            login: Some(false),
+            ..Default::default()
+        };
+        let mut manager = PtyManager::new();
+        let sink = Arc::new(RecordingSink::default());
+        let id = manager
+            .create_session(
+                80,
+                24,
+                None,
+                &launch,
+                &ScrollbackLimits::default(),
+                sink.clone(),
+            )
+            .unwrap();
+
+        manager.input(&id).unwrap().write("cd /tmp\r").unwrap();
+        manager.input(&id).unwrap().write("false\r").unwrap();
+        wait_until("two blocks", || {
+            let blocks = manager.blocks(&id).unwrap();
+            blocks.len() == 2 && blocks[1].end.is_some()
+        });
+        let blocks = manager.blocks(&id).unwrap();
+        assert_eq!(blocks[0].command, "cd /tmp");
+        assert_eq!(blocks[0].exit_code, Some(0));
+        assert_eq!(blocks[1].command, "false");
+        assert_eq!(blocks[1].exit_code, Some(1));
+        assert_eq!(blocks[1].cwd.as_deref(), Some("/tmp"));
+        assert_eq!(sink.cwd.lock().as_deref(), Some("/tmp"));
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_title_bell_and_notifications_are_reported() {
+        let mut manager = PtyManager::new();
+        let (_id, sink) = start_script(
+            &mut manager,
+            r"printf '\033]2;build\007\007\007\033]9;done\007'; echo ready; exec sleep 60",
+        );
+
+        assert_eq!(sink.title.lock().as_deref(), Some("build"));
+        // Back-to-back bells are coalesced
+        assert_eq!(*sink.bells.lock(), 1);
+        assert_eq!(
+            *sink.notifications.lock(),
+            vec![Notification {
+                title: None,
+                body: "done".to_string()
+            }]
+        );
+        // The sequences still reach the terminal untouched
+        let title = b"\x1b]2;build\x07";
+        let output = sink.data.lock().clone();
+        assert!(output.windows(title.len()).any(|w| w == title));
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recording_captures_output_and_resizes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("session.cast");
+        let mut manager = PtyManager::new();
+        let (id, sink) = start_script(
+            &mut manager,
+            "echo ready; read line; echo got-$line; exec sleep 60",
+        );
+        manager.start_recording(&id, &path).unwrap();
+        assert!(matches!(
+            manager.start_recording(&id, &path),
+            Err(AppError::InvalidInput(_))
+        ));
+        manager.resize(&id, 100, 30, None).unwrap();
+        manager.input(&id).unwrap().write("x\r").unwrap();
+
+        wait_for_output(&sink, "got-x");
+        assert_eq!(manager.stop_recording(&id).unwrap(), path);
+        assert!(matches!(
+            manager.stop_recording(&id),
+            Err(AppError::NotFound(_))
+        ));
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let mut lines = contents.lines();
+        let header: crate::recording::CastHeader =
+            serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!((header.width, header.height), (80, 24));
+        assert!(contents.contains(r#""r","100x30""#));
+        assert!(contents.contains("got-x"));
+        assert!(!contents.contains("ready"));
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_log_leaves_out_password_echo() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut manager = PtyManager::new();
+        // Echoes the password back itself, as some prompts do with their own masking
+        let (id, sink) = start_script(
+            &mut manager,
+            r"stty -echo; echo ready; IFS= read -r pw; printf 'got %s\n' $pw; echo done; exec sleep 60",
+        );
+        let path = manager
+            .start_log(&id, dir.path(), &LogRotation::default())
+            .unwrap();
+        assert_eq!(path, dir.path().join(format!("{}.log", id)));
+        manager.input(&id).unwrap().write("hunter2\r").unwrap();
+
+        wait_for_output(&sink, "done");
+        assert_eq!(manager.stop_log(&id).unwrap(), path);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        assert!(log.contains(" done\n"));
+        assert!(!log.contains("hunter2"));
+        assert!(String::from_utf8_lossy(&sink.data.lock()).contains("got hunter2"));
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stalled_session_does_not_block_input_to_others() {
+        let mut manager = PtyManager::new();
+        // Never reads its input, so the PTY's input buffer fills up and stays full
+        let (stalled, _stalled_sink) =
+            start_script(&mut manager, "stty raw -echo; echo ready; exec sleep 60");
+        let (id, sink) = start_script(
+            &mut manager,
+            "echo ready; read line; echo got-$line; exec sleep 60",
+        );
+
+        let started = Instant::now();
+        let paste = "x".repeat(1024 * 1024);
+        manager.input(&stalled).unwrap().write(&paste).unwrap();
+        manager.input(&stalled).unwrap().write(&paste).unwrap();
+        manager.input(&id).unwrap().write("hello\r").unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        wait_for_output(&sink, "got-hello");
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_input_queued_for_a_stalled_session_is_capped() {
+        let mut manager = PtyManager::new();
+        let (id, _sink) = start_script(&mut manager, "stty raw -echo; echo ready; exec sleep 60");
+        let input = manager.input(&id).unwrap();
+
+        let paste = "x".repeat(1024 * 1024);
+        let refused =
+            (0..MAX_QUEUED_INPUT / paste.len() + 2).find_map(|_| input.write(&paste).err());
+        assert!(refused
+            .unwrap()
+            .to_string()
+            .contains("not reading its input"));
+        // What was refused isn't counted as sent
+        let sent = manager.list()[0].bytes_in as usize;
+        assert!(sent <= MAX_QUEUED_INPUT + 64 * 1024);
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cell_size_sets_pixel_dimensions() {
+        let launch = LaunchProfile {
+            program: Some("sh".to_string()),
+            args: vec!["-c".to_string(), "exec sleep 60".to_string()],
+            ..Default::default()
+        };
+        let mut manager = PtyManager::new();
+        let cell = CellSize {
+            width: 9,
+            height: 18,
+        };
+        let id = manager
+            .create_session(
+                80,
+                24,
+                Some(cell),
+                &launch,
+                &ScrollbackLimits::default(),
+                Arc::new(NullSink),
+            )
+            .unwrap();
+        let pixels = |manager: &PtyManager| {
+            let size = manager.session(&id).unwrap().master.get_size().unwrap();
+            (size.pixel_width, size.pixel_height)
+        };
+        assert_eq!(pixels(&manager), (720, 432));
+
+        // Resizing without metrics keeps the cell size
+        manager.resize(&id, 100, 30, None).unwrap();
+        assert_eq!(pixels(&manager), (900, 540));
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_group_input_reaches_every_member() {
+        let mut manager = PtyManager::new();
+        let script = "echo ready; read line; echo got-$line; exec sleep 60";
+        let (a, sink_a) = start_script(&mut manager, script);
+        let (b, sink_b) = start_script(&mut manager, script);
+        let (c, _sink_c) = start_script(&mut manager, script);
+        let group = manager
+            .create_group(&[a.clone(), b.clone(), c.clone()])
+            .unwrap();
+        assert_eq!(
+            manager.groups()[0].members,
+            vec![a.clone(), b.clone(), c.clone()]
+        );
+
+        // A member that can't be written to is reported and the rest still get the input
+        let gone = manager.sessions.remove(&c).unwrap();
+        let failures = manager.input_targets(&b).unwrap().write("hi\r").unwrap();
+        gone.shut_down(Duration::from_millis(100));
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].session_id, c);
+
+        for sink in [&sink_a, &sink_b] {
+            wait_for_output(sink, "got-hi");
+        }
+
+        manager.dissolve_group(&group).unwrap();
+        assert!(manager.groups().is_empty());
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_triggers_answer_prompts_and_notify() {
+        let mut manager = PtyManager::new();
+        let (id, sink) = start_script(
+            &mut manager,
+            "echo ready; read go; printf 'Continue? [y/N] '; read answer; \
+             echo answered-$answer; exec sleep 60",
+        );
+        let rules = [
+            TriggerRule {
+                name: "confirm".to_string(),
+                pattern: r"Continue\? \[y/N\]".to_string(),
+                ignore_case: false,
+                whole_word: false,
+                action: TriggerAction::Respond {
+                    input: "y\r".to_string(),
+                },
+            },
+            TriggerRule {
+                name: "answered".to_string(),
+                pattern: r"answered-\w+".to_string(),
+                ignore_case: false,
+                whole_word: false,
+                action: TriggerAction::Notify { title: None },
+            },
+        ];
+        let triggers = Arc::new(TriggerSet::compile(&rules).unwrap());
+        manager.set_triggers(&id, triggers).unwrap();
+        manager.input(&id).unwrap().write("go\r").unwrap();
+
+        wait_until("the answer", || !sink.notifications.lock().is_empty());
+        assert_eq!(
+            *sink.notifications.lock(),
+            vec![Notification {
+                title: Some("answered".to_string()),
+                body: "answered-y".to_string()
+            }]
+        );
+        let fired: Vec<String> = sink
+            .triggers
+            .lock()
+            .iter()
+            .map(|m| m.name.clone())
+            .collect();
+        assert_eq!(fired, vec!["confirm", "answered"]);
+
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_monitor_reports_activity_then_silence() {
+        let mut manager = PtyManager::new();
+        let (id, sink) = start_script(
+            &mut manager,
+            r"echo ready; read go; printf '\007'; echo working; exec sleep 60",
+        );
+        manager
+            .set_monitor(
+                &id,
+                MonitorSettings {
+                    activity: true,
+                    silence_secs: Some(1),
+                },
+            )
+            .unwrap();
+        manager.input(&id).unwrap().write("go\r").unwrap();
+
+        wait_until("alerts", || sink.alerts.lock().len() >= 2);
+        let kinds: Vec<MonitorKind> = sink.alerts.lock().iter().map(|a| a.kind).collect();
+        assert_eq!(kinds, vec![MonitorKind::Activity, MonitorKind::Silence]);
+        let status = manager.monitor_status(&id).unwrap();
+        assert_eq!(status.bells, 1);
+        assert!(status.activity && status.silent);
+        manager.close_all(Duration::from_millis(100));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_list_describes_each_session() {
+        let mut manager = PtyManager::new();
+        let before = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        let (first, _) = start_script(
+            &mut manager,
+            r"cd /tmp; printf '\033]2;first\007'; echo ready; read line; exec sleep 60",
+        );
+        let (second, _) = start_script(&mut manager, "echo ready; exec sleep 60");
+        manager.input(&first).unwrap().write("hello\r").unwrap();
+
+        let sessions = manager.list();
+        let ids: Vec<&str> = sessions.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec![first.as_str(), second.as_str()]);
+        let summary = &sessions[0];
+        assert!(summary.started_ms >= before);
+        assert_eq!(summary.title.as_deref(), Some("first"));
+        assert_eq!(summary.launch.program.as_deref(), Some("sh"));
+        assert_eq!(summary.bytes_in, 6);
+        assert!(summary.bytes_out >= "ready\r\n".len() as u64);
+        assert!(summary.alive && summary.exit.is_none());
+        assert!(summary.foreground.is_some());
+        manager.close_all(Duration::from_millis(100));
+    }
 }
@@ -0,0 +1,384 @@
+//! Session recording in the asciinema v2 format (https://docs.asciinema.org/manual/asciicast/v2/):
+//! a JSON header line followed by one `[time, code, data]` line per event.
+//! Playback reads a recording back and replays its output at an adjustable speed.
+
+use crate::error::AppError;
+use crate::exit_status::ExitInfo;
+use crate::pty_manager::{SessionSink, TerminalSize};
+use parking_lot::{Condvar, Mutex};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Recordings being played back, by playback ID
+pub type PlaybackState = Arc<Mutex<HashMap<String, Arc<Playback>>>>;
+
+/// Longest pause replayed when the recording doesn't set its own `idle_time_limit`
+const DEFAULT_IDLE_LIMIT: f64 = 2.0;
+/// Longest single sleep during playback; longer pauses are waited out in steps
+const MAX_WAIT: Duration = Duration::from_secs(3600);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CastHeader {
+    pub version: u32,
+    pub width: u16,
+    pub height: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_time_limit: Option<f64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Writes a session's output and resizes to a `.cast` file as they happen
+pub struct Recorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    started: Instant,
+    /// Bytes of a UTF-8 sequence split across reads, held until the rest arrives
+    partial: Vec<u8>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, cols: u16, rows: u16) -> std::io::Result<Self> {
+        let mut env = BTreeMap::new();
+        env.insert("TERM".to_string(), "xterm-256color".to_string());
+        if let Ok(shell) = std::env::var("SHELL") {
+            env.insert("SHELL".to_string(), shell);
+        }
+        let header = CastHeader {
+            version: 2,
+            width: cols,
+            height: rows,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|d| d.as_secs()),
+            idle_time_limit: None,
+            env,
+        };
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &header)?;
+        writer.write_all(b"\n")?;
+        Ok(Recorder {
+            path: path.to_path_buf(),
+            writer,
+            started: Instant::now(),
+            partial: Vec::new(),
+        })
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> std::io::Result<()> {
+        let time = self.started.elapsed().as_secs_f64();
+        serde_json::to_writer(&mut self.writer, &(time, code, data))?;
+        self.writer.write_all(b"\n")
+    }
+
+    pub fn output(&mut self, data: &[u8]) -> std::io::Result<()> {
+        self.partial.extend_from_slice(data);
+        let complete = complete_utf8_len(&self.partial);
+        if complete == 0 {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&self.partial[..complete]).into_owned();
+        self.partial.drain(..complete);
+        self.event("o", &text)
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) -> std::io::Result<()> {
+        self.event("r", &format!("{}x{}", cols, rows))
+    }
+
+    pub fn finish(mut self) -> std::io::Result<PathBuf> {
+        if !self.partial.is_empty() {
+            let text = String::from_utf8_lossy(&self.partial).into_owned();
+            self.event("o", &text)?;
+        }
+        self.writer.flush()?;
+        Ok(self.path)
+    }
+}
+
+/// Length of `data` without a UTF-8 sequence cut off at its end
+fn complete_utf8_len(data: &[u8]) -> usize {
+    // A sequence is at most 4 bytes, so only the last 3 can start an unfinished one
+    for back in 1..=data.len().min(3) {
+        let byte = data[data.len() - back];
+        if byte & 0xC0 == 0x80 {
+            continue;
+        }
+        let needed = match byte {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        return if needed > back {
+            data.len() - back
+        } else {
+            data.len()
+        };
+    }
+    data.len()
+}
+
+/// A recording being replayed into a read-only terminal
+pub struct Playback {
+    speed: Mutex<f64>,
+    changed: Condvar,
+    stopped: AtomicBool,
+}
+
+impl Playback {
+    pub fn set_speed(&self, speed: f64) {
+        *self.speed.lock() = speed;
+        self.changed.notify_all();
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // Taking the lock orders the store before the player's next check
+        let _speed = self.speed.lock();
+        self.changed.notify_all();
+    }
+
+    /// Wait out `delay` seconds of recording time at the current speed, which may
+    /// change meanwhile. Returns false once playback is stopped
+    fn wait(&self, mut delay: f64) -> bool {
+        let mut speed = self.speed.lock();
+        while delay > 0.0 {
+            if self.stopped.load(Ordering::Relaxed) {
+                return false;
+            }
+            let started = Instant::now();
+            let current = *speed;
+            // Huge idle limits or a zero speed would overflow a Duration
+            let timeout = Duration::try_from_secs_f64(delay / current)
+                .map_or(MAX_WAIT, |timeout| timeout.min(MAX_WAIT));
+            if self.changed.wait_for(&mut speed, timeout).timed_out() {
+                break;
+            }
+            delay -= started.elapsed().as_secs_f64() * current;
+        }
+        !self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+/// Open a recording and replay its output to `sink` on a background thread,
+/// ending with an exit event. The playback is listed in `playbacks` until it ends.
+/// Returns the recording's header, for sizing the terminal
+pub fn play(
+    path: &Path,
+    playback_id: String,
+    speed: f64,
+    sink: Arc<dyn SessionSink>,
+    playbacks: &PlaybackState,
+) -> Result<(CastHeader, Arc<Playback>), AppError> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+    let header_line = lines
+        .next()
+        .ok_or_else(|| AppError::InvalidInput("Recording is empty".to_string()))??;
+    let header: CastHeader = serde_json::from_str(&header_line)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid recording header: {}", e)))?;
+    if header.version != 2 {
+        return Err(AppError::InvalidInput(format!(
+            "Unsupported asciicast version {}",
+            header.version
+        )));
+    }
+
+    let playback = Arc::new(Playback {
+        speed: Mutex::new(speed),
+        changed: Condvar::new(),
+        stopped: AtomicBool::new(false),
+    });
+    let idle_limit = header.idle_time_limit.unwrap_or(DEFAULT_IDLE_LIMIT);
+    let player = playback.clone();
+    playbacks
+        .lock()
+        .insert(playback_id.clone(), playback.clone());
+    let playbacks = Arc::downgrade(playbacks);
+    std::thread::spawn(move || {
+        let mut info = ExitInfo {
+            exit_code: Some(0),
+            ..Default::default()
+        };
+        let mut previous = 0.0;
+        for line in lines {
+            if line.as_ref().is_ok_and(|line| line.trim().is_empty()) {
+                continue;
+            }
+            let event = line.map_err(|e| e.to_string()).and_then(|line| {
+                serde_json::from_str::<(f64, String, String)>(&line).map_err(|e| e.to_string())
+            });
+            let (time, code, data) = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    info.reader_error = Some(format!("Invalid recording: {}", e));
+                    break;
+                }
+            };
+            if !player.wait((time - previous).min(idle_limit)) {
+                break;
+            }
+            previous = time;
+            match code.as_str() {
+                "o" => sink.output(&playback_id, data.as_bytes()),
+                "r" => {
+                    if let Some(size) = parse_size(&data) {
+                        sink.resize(&playback_id, size);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(playbacks) = playbacks.upgrade() {
+            playbacks.lock().remove(&playback_id);
+        }
+        sink.exit(&playback_id, &info);
+    });
+    Ok((header, playback))
+}
+
+/// Size from a resize event's `COLSxROWS`
+fn parse_size(data: &str) -> Option<TerminalSize> {
+    let (cols, rows) = data.split_once('x')?;
+    Some(TerminalSize {
+        cols: cols.trim().parse().ok()?,
+        rows: rows.trim().parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::CollectingSink;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_records_header_output_and_resizes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("demo.cast");
+        let mut recorder = Recorder::create(&path, 80, 24).unwrap();
+        // "é" split across two reads must still be written as one character
+        recorder.output(b"caf\xc3").unwrap();
+        recorder.output(b"\xa9\r\n").unwrap();
+        recorder.resize(100, 30).unwrap();
+        recorder.finish().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        let header: CastHeader = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!((header.version, header.width, header.height), (2, 80, 24));
+        assert_eq!(header.env["TERM"], "xterm-256color");
+        let events: Vec<(f64, String, String)> = lines[1..]
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let codes: Vec<(&str, &str)> = events
+            .iter()
+            .map(|(_, code, data)| (code.as_str(), data.as_str()))
+            .collect();
+        assert_eq!(codes, vec![("o", "caf"), ("o", "é\r\n"), ("r", "100x30")]);
+        assert!(events.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+    }
+
+    #[test]
+    fn test_playback_replays_output_and_caps_idle_time() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("demo.cast");
+        std::fs::write(
+            &path,
+            concat!(
+                r#"{"version": 2, "width": 80, "height": 24, "idle_time_limit": 0.05}"#,
+                "\n",
+                r#"[0.1, "o", "hello "]"#,
+                "\n",
+                r#"[0.2, "r", "90x30"]"#,
+                "\n",
+                r#"[3600.0, "o", "world"]"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let sink = Arc::new(CollectingSink::default());
+        let started = Instant::now();
+        let playbacks = PlaybackState::default();
+        let (header, _playback) =
+            play(&path, "p".to_string(), 2.0, sink.clone(), &playbacks).unwrap();
+        assert_eq!((header.width, header.height), (80, 24));
+        let info = sink.wait_for_exit();
+        // A finished playback no longer needs stopping
+        assert!(playbacks.lock().is_empty());
+        assert_eq!(info.exit_code, Some(0));
+        assert_eq!(*sink.data.lock(), b"hello world");
+        assert_eq!(
+            *sink.resizes.lock(),
+            vec![TerminalSize { cols: 90, rows: 30 }]
+        );
+        // The hour-long pause was cut to the recording's idle limit
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_stopped_playback_ends_early() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("slow.cast");
+        std::fs::write(
+            &path,
+            "{\"version\": 2, \"width\": 80, \"height\": 24, \"idle_time_limit\": 60}\n\
+             [30.0, \"o\", \"late\"]\n",
+        )
+        .unwrap();
+        let sink = Arc::new(CollectingSink::default());
+        let playbacks = PlaybackState::default();
+        let (_, playback) = play(&path, "p".to_string(), 1.0, sink.clone(), &playbacks).unwrap();
+        assert!(playbacks.lock().contains_key("p"));
+        playback.stop();
+        sink.wait_for_exit();
+        assert!(sink.data.lock().is_empty());
+    }
+
+    #[test]
+    fn test_playback_survives_huge_pauses() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("huge.cast");
+        std::fs::write(
+            &path,
+            "{\"version\": 2, \"width\": 80, \"height\": 24, \"idle_time_limit\": 1e20}\n\
+             [1e20, \"o\", \"late\"]\n",
+        )
+        .unwrap();
+        let sink = Arc::new(CollectingSink::default());
+        let playbacks = PlaybackState::default();
+        let (_, playback) = play(&path, "p".to_string(), 1.0, sink.clone(), &playbacks).unwrap();
+        // Still waiting rather than having panicked on the pause's length
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(playbacks.lock().contains_key("p"));
+        playback.stop();
+        assert_eq!(sink.wait_for_exit().exit_code, Some(0));
+        assert!(sink.data.lock().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_other_formats() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("v1.cast");
+        std::fs::write(&path, r#"{"version": 1, "width": 80, "height": 24}"#).unwrap();
+        let result = play(
+            &path,
+            "p".to_string(),
+            1.0,
+            Arc::new(CollectingSink::default()),
+            &PlaybackState::default(),
+        );
+        assert!(matches!(result, Err(AppError::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,332 @@
+//! One-off commands run without a terminal: stdout and stderr are streamed as they
+//! arrive, and the run can time out or be cancelled.
+
+use crate::error::AppError;
+use crate::exit_status::ExitInfo;
+use parking_lot::{Condvar, Mutex};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Commands currently running, by run ID
+pub type RunState = Arc<Mutex<HashMap<String, Arc<RunControl>>>>;
+
+/// How often a run checks whether its process has exited
+const EXIT_POLL: Duration = Duration::from_millis(20);
+/// How long a stopped command gets to exit after SIGTERM before it is killed
+const STOP_GRACE: Duration = Duration::from_secs(2);
+/// How long output may still arrive after the command exits, from programs it left
+/// running in the background, before those are killed
+const DRAIN_GRACE: Duration = Duration::from_millis(250);
+
+/// Receives a command's output. Chunks may split UTF-8 sequences
+pub trait RunSink: Send + Sync {
+    fn stdout(&self, data: &[u8]);
+    fn stderr(&self, data: &[u8]);
+}
+
+pub struct RunRequest {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    /// Added to the app's own environment
+    pub env: HashMap<String, String>,
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunResult {
+    #[serde(flatten)]
+    pub exit: ExitInfo,
+    /// The command was stopped because it ran past its timeout
+    pub timed_out: bool,
+    /// The command was stopped by `cancel`
+    pub cancelled: bool,
+    pub duration_ms: u64,
+}
+
+/// Lets another thread cancel a run
+#[derive(Default)]
+pub struct RunControl {
+    cancelled: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl RunControl {
+    pub fn cancel(&self) {
+        *self.cancelled.lock() = true;
+        self.changed.notify_all();
+    }
+
+    /// Wait up to `timeout` for a cancel, returning whether the run was cancelled
+    fn wait_cancelled(&self, timeout: Duration) -> bool {
+        let mut cancelled = self.cancelled.lock();
+        if !*cancelled {
+            self.changed.wait_for(&mut cancelled, timeout);
+        }
+        *cancelled
+    }
+}
+
+/// Run a command to completion, streaming its output to `sink`. Fails only if the
+/// command could not be started; a timeout or cancel still returns a result
+pub fn run(
+    request: &RunRequest,
+    control: &RunControl,
+    sink: Arc<dyn RunSink>,
+) -> Result<RunResult, AppError> {
+    let mut command = Command::new(&request.program);
+    command
+        .args(&request.args)
+        .envs(&request.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(cwd) = &request.cwd {
+        command.current_dir(cwd);
+    }
+    // A group of its own, so stopping the command also stops what it started
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let started = Instant::now();
+    let mut child = command
+        .spawn()
+        .map_err(|e| AppError::Io(format!("Failed to start {}: {}", request.program, e)))?;
+    let stdout = child.stdout.take().map(|pipe| {
+        let sink = sink.clone();
+        std::thread::spawn(move || forward(pipe, |data| sink.stdout(data)))
+    });
+    let stderr = child.stderr.take().map(|pipe| {
+        let sink = sink.clone();
+        std::thread::spawn(move || forward(pipe, |data| sink.stderr(data)))
+    });
+
+    let deadline = request.timeout.map(|timeout| started + timeout);
+    let mut result = RunResult::default();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            result.timed_out = true;
+        } else if control.wait_cancelled(EXIT_POLL) {
+            result.cancelled = true;
+        }
+        if result.timed_out || result.cancelled {
+            break stop(&mut child)?;
+        }
+    };
+
+    // Programs the command left running in the background may hold the pipes open
+    let readers: Vec<_> = [stdout, stderr].into_iter().flatten().collect();
+    let drained = Instant::now() + DRAIN_GRACE;
+    while readers.iter().any(|reader| !reader.is_finished()) {
+        if Instant::now() >= drained {
+            kill_group(&child);
+            break;
+        }
+        std::thread::sleep(EXIT_POLL);
+    }
+    let mut read_errors = Vec::new();
+    for reader in readers {
+        match reader.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => read_errors.push(e.to_string()),
+            Err(_) => read_errors.push("Output reader panicked".to_string()),
+        }
+    }
+    result.exit = exit_info(status);
+    if !read_errors.is_empty() {
+        result.exit.reader_error = Some(read_errors.join("; "));
+    }
+    result.duration_ms = started.elapsed().as_millis() as u64;
+    Ok(result)
+}
+
+fn forward(mut pipe: impl Read, mut send: impl FnMut(&[u8])) -> std::io::Result<()> {
+    let mut buf = [0u8; 16384];
+    loop {
+        match pipe.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => send(&buf[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// SIGTERM the command's process group, then SIGKILL it if it outlives the grace period
+#[cfg(unix)]
+fn stop(child: &mut Child) -> std::io::Result<ExitStatus> {
+    unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGTERM) };
+    let deadline = Instant::now() + STOP_GRACE;
+    while Instant::now() < deadline {
+        if let Some(status) = child.try_wait()? {
+            // Children that ignored SIGTERM would hold the output pipes open
+            kill_group(child);
+            return Ok(status);
+        }
+        std::thread::sleep(EXIT_POLL);
+    }
+    kill_group(child);
+    child.wait()
+}
+
+#[cfg(not(unix))]
+fn stop(child: &mut Child) -> std::io::Result<ExitStatus> {
+    child.kill()?;
+    child.wait()
+}
+
+/// SIGKILL whatever is left of the command's process group
+#[cfg(unix)]
+fn kill_group(child: &Child) {
+    unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) };
+}
+
+#[cfg(not(unix))]
+fn kill_group(_child: &Child) {}
+
+#[cfg(unix)]
+fn exit_info(status: ExitStatus) -> ExitInfo {
+    use std::os::unix::process::ExitStatusExt;
+
+    ExitInfo {
+        exit_code: status.code(),
+        signal: status.signal(),
+        signal_name: status
+            .signal()
+            .and_then(crate::signal::signal_name)
+            .map(str::to_string),
+        reader_error: None,
+    }
+}
+
+#[cfg(not(unix))]
+fn exit_info(status: ExitStatus) -> ExitInfo {
+    ExitInfo {
+        exit_code: status.code(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct CollectingSink {
+        stdout: Mutex<Vec<u8>>,
+        stderr: Mutex<Vec<u8>>,
+    }
+
+    impl RunSink for CollectingSink {
+        fn stdout(&self, data: &[u8]) {
+            self.stdout.lock().extend_from_slice(data);
+        }
+
+        fn stderr(&self, data: &[u8]) {
+            self.stderr.lock().extend_from_slice(data);
+        }
+    }
+
+    fn shell(script: &str) -> RunRequest {
+        RunRequest {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            cwd: None,
+            env: HashMap::new(),
+            timeout: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_streams_output_separately_and_reports_exit_code() {
+        let mut request = shell("echo out; echo err >&2; echo $GREETING; pwd; exit 3");
+        request.env.insert("GREETING".to_string(), "hi".to_string());
+        request.cwd = Some("/tmp".to_string());
+        let sink = Arc::new(CollectingSink::default());
+        let result = run(&request, &RunControl::default(), sink.clone()).unwrap();
+
+        assert_eq!(result.exit.exit_code, Some(3));
+        assert!(!result.timed_out && !result.cancelled);
+        let cwd = std::fs::canonicalize("/tmp").unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&sink.stdout.lock()),
+            format!("out\nhi\n{}\n", cwd.display())
+        );
+        assert_eq!(*sink.stderr.lock(), b"err\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_stops_the_command_and_its_children() {
+        let mut request = shell("sleep 30 & sleep 30; echo never");
+        request.timeout = Some(Duration::from_millis(200));
+        let sink = Arc::new(CollectingSink::default());
+        let started = Instant::now();
+        let result = run(&request, &RunControl::default(), sink.clone()).unwrap();
+
+        assert!(result.timed_out);
+        assert_eq!(result.exit.signal_name.as_deref(), Some("SIGTERM"));
+        // Returning at all means the background sleep no longer holds the pipes open
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(sink.stdout.lock().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_background_children_cannot_outlast_the_command() {
+        let sink = Arc::new(CollectingSink::default());
+        let started = Instant::now();
+        let result = run(
+            &shell("sleep 30 & echo started"),
+            &RunControl::default(),
+            sink.clone(),
+        )
+        .unwrap();
+        assert_eq!(result.exit.exit_code, Some(0));
+        // The command itself finished; only what it left behind was stopped
+        assert!(!result.timed_out && !result.cancelled);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(*sink.stdout.lock(), b"started\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancel_stops_the_command() {
+        let control = Arc::new(RunControl::default());
+        let canceller = control.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            canceller.cancel();
+        });
+        let result = run(
+            &shell("sleep 30"),
+            &control,
+            Arc::new(CollectingSink::default()),
+        )
+        .unwrap();
+        assert!(result.cancelled);
+        assert!(result.exit.signal.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_missing_program_fails_to_start() {
+        let mut request = shell("");
+        request.program = "/nonexistent/program".to_string();
+        let result = run(
+            &request,
+            &RunControl::default(),
+            Arc::new(CollectingSink::default()),
+        );
+        assert!(matches!(result, Err(AppError::Io(_))));
+    }
+}
@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Upper bounds for a session's retained output; whichever is hit first wins
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScrollbackLimits {
+    pub max_bytes: usize,
+    pub max_lines: usize,
+}
+
+impl Default for ScrollbackLimits {
+    fn default() -> Self {
+        ScrollbackLimits {
+            max_bytes: 2 * 1024 * 1024,
+            max_lines: 10_000,
+        }
+    }
+}
+
+/// Ring buffer of the most recent PTY output, trimmed from the front
+pub struct Scrollback {
+    buf: VecDeque<u8>,
+    limits: ScrollbackLimits,
+    /// Number of '\n' bytes currently held
+    newlines: usize,
+    /// Bytes pushed over the session's lifetime, i.e. the stream offset of the end
+    total: u64,
+}
+
+impl Scrollback {
+    pub fn new(limits: ScrollbackLimits) -> Self {
+        Scrollback {
+            buf: VecDeque::new(),
+            limits,
+            newlines: 0,
+            total: 0,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend(data);
+        self.total += data.len() as u64;
+        self.newlines += data.iter().filter(|&&b| b == b'\n').count();
+
+        if self.buf.len() > self.limits.max_bytes {
+            self.discard_front(self.buf.len() - self.limits.max_bytes);
+            // Never start in the middle of a UTF-8 sequence
+            let partial = self.buf.iter().take_while(|&&b| b & 0xC0 == 0x80).count();
+            self.discard_front(partial);
+        }
+
+        // A trailing partial line counts too, so N lines hold at most N - 1 newlines
+        let max_lines = self.limits.max_lines.max(1);
+        if self.newlines >= max_lines {
+            let mut excess = self.newlines - max_lines + 1;
+            let mut cut = 0;
+            for (i, &b) in self.buf.iter().enumerate() {
+                if b == b'\n' {
+                    excess -= 1;
+                    if excess == 0 {
+                        cut = i + 1;
+                        break;
+                    }
+                }
+            }
+            self.discard_front(cut);
+        }
+    }
+
+    fn discard_front(&mut self, n: usize) {
+        self.newlines -= self.buf.drain(..n).filter(|&b| b == b'\n').count();
+    }
+
+    pub fn contents(&self) -> Vec<u8> {
+        self.buf.iter().copied().collect()
+    }
+
+    /// Stream offset one past the last byte pushed
+    pub fn end_offset(&self) -> u64 {
+        self.total
+    }
+
+    /// Stream offset of the oldest byte still held
+    pub fn start_offset(&self) -> u64 {
+        self.total - self.buf.len() as u64
+    }
+
+    /// The retained part of the stream between two offsets
+    pub fn range(&self, start: u64, end: u64) -> Vec<u8> {
+        let first = self.start_offset();
+        let start = (start.max(first) - first) as usize;
+        let end = (end.clamp(first, self.total) - first) as usize;
+        if start >= end {
+            return Vec::new();
+        }
+        self.buf.range(start..end).copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrollback(max_bytes: usize, max_lines: usize) -> Scrollback {
+        Scrollback::new(ScrollbackLimits {
+            max_bytes,
+            max_lines,
+        })
+    }
+
+    #[test]
+    fn test_keeps_everything_under_limits() {
+        let mut sb = scrollback(1024, 100);
+        sb.push(b"hello ");
+        sb.push(b"world\r\n");
+        assert_eq!(sb.contents(), b"hello world\r\n");
+    }
+
+    #[test]
+    fn test_trims_to_byte_limit() {
+        let mut sb = scrollback(8, 100);
+        sb.push(b"0123456789abcdef");
+        assert_eq!(sb.contents(), b"89abcdef");
+    }
+
+    #[test]
+    fn test_trims_to_line_limit() {
+        let mut sb = scrollback(1024, 2);
+        sb.push(b"one\ntwo\nthree\nfour");
+        assert_eq!(sb.contents(), b"three\nfour");
+        sb.push(b"\nfive\n");
+        assert_eq!(sb.contents(), b"five\n");
+    }
+
+    #[test]
+    fn test_range_uses_stream_offsets() {
+        let mut sb = scrollback(8, 100);
+        sb.push(b"0123456789");
+        assert_eq!((sb.start_offset(), sb.end_offset()), (2, 10));
+        assert_eq!(sb.range(4, 7), b"456");
+        // Trimmed bytes are gone, so the range is cut to what is still held
+        assert_eq!(sb.range(0, 4), b"23");
+        assert_eq!(sb.range(8, 100), b"89");
+        assert!(sb.range(7, 4).is_empty());
+    }
+
+    #[test]
+    fn test_byte_trim_respects_utf8_boundaries() {
+        let mut sb = scrollback(5, 100);
+        // "é" is two bytes; a 5-byte cut would land inside the first one
+        sb.push("éééé".as_bytes());
+        let contents = sb.contents();
+        assert!(std::str::from_utf8(&contents).is_ok());
+        assert_eq!(contents, "éé".as_bytes());
+    }
+}
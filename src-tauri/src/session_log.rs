@@ -0,0 +1,274 @@
+//! Plain-text session logs: output is stripped of escape sequences and written one
+//! timestamped line at a time, rotating to numbered files once a log grows too big.
+
+use crate::ansi::AnsiStripper;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// When a log is rotated and how many rotated files are kept
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogRotation {
+    /// Size a log may reach before it is moved aside to `<name>.1`
+    pub max_bytes: u64,
+    /// Rotated files kept next to the live log; older ones are deleted
+    pub max_files: u32,
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        LogRotation {
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+        }
+    }
+}
+
+/// The `sessionLog` section of the app config
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogSettings {
+    /// Log every new session from the start
+    pub enabled: bool,
+    /// Where logs are written, by default the `sessions` folder of the app's log directory
+    pub directory: Option<String>,
+    pub rotation: LogRotation,
+}
+
+pub struct SessionLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    size: u64,
+    rotation: LogRotation,
+    stripper: AnsiStripper,
+    /// Text of the line being received, with the time it started
+    line: Vec<u8>,
+    line_started: Option<SystemTime>,
+    /// Set while a password is being typed, until the program ends the line
+    hiding_input: bool,
+}
+
+impl SessionLog {
+    /// Start a log for `session_id` in `dir`, appending if one already exists
+    pub fn create(dir: &Path, session_id: &str, rotation: &LogRotation) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.log", session_id));
+        let file = open_log(&path, false)?;
+        let size = file.metadata()?.len();
+        Ok(SessionLog {
+            path,
+            writer: BufWriter::new(file),
+            size,
+            rotation: rotation.clone(),
+            stripper: AnsiStripper::default(),
+            line: Vec::new(),
+            line_started: None,
+            hiding_input: false,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn output(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let mut text = Vec::with_capacity(data.len());
+        self.stripper.feed(data, &mut text);
+        for chunk in text.split_inclusive(|&b| b == b'\n') {
+            let (content, ends_line) = match chunk.strip_suffix(b"\n") {
+                Some(content) => (content, true),
+                None => (chunk, false),
+            };
+            if !self.hiding_input && !content.is_empty() {
+                self.line_started.get_or_insert_with(SystemTime::now);
+                self.line.extend_from_slice(content);
+            }
+            if ends_line {
+                self.hiding_input = false;
+                self.write_line()?;
+            }
+        }
+        // Flushed per batch, so the log is current if the app dies
+        self.writer.flush()
+    }
+
+    /// Keep whatever the program echoes from here to the end of the line out of the
+    /// log, because the terminal is reading a password
+    pub fn hide_input(&mut self) {
+        self.hiding_input = true;
+    }
+
+    fn write_line(&mut self) -> std::io::Result<()> {
+        let started = self.line_started.take().unwrap_or_else(SystemTime::now);
+        let mut entry = format!("{} ", format_timestamp(started)).into_bytes();
+        entry.append(&mut self.line);
+        entry.push(b'\n');
+        if self.size > 0 && self.size + entry.len() as u64 > self.rotation.max_bytes {
+            self.rotate()?;
+        }
+        self.writer.write_all(&entry)?;
+        self.size += entry.len() as u64;
+        Ok(())
+    }
+
+    /// Shift `name.1`.. up by one, dropping the oldest, and start a fresh `name`
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.writer.flush()?;
+        let numbered = |n: u32| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        if self.rotation.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.rotation.max_files).rev() {
+                match std::fs::rename(numbered(n), numbered(n + 1)) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            std::fs::rename(&self.path, numbered(1))?;
+        }
+        self.writer = BufWriter::new(open_log(&self.path, true)?);
+        self.size = 0;
+        Ok(())
+    }
+
+    /// Write out the unfinished last line and close the log
+    pub fn finish(mut self) -> std::io::Result<PathBuf> {
+        if !self.line.is_empty() {
+            self.write_line()?;
+        }
+        self.writer.flush()?;
+        Ok(self.path)
+    }
+}
+
+/// RFC 3339 UTC time with milliseconds, e.g. `2024-05-01T09:30:00.250Z`
+/// Open a log file, creating it readable by the user only since it holds whatever
+/// the terminal showed
+fn open_log(path: &Path, truncate: bool) -> std::io::Result<File> {
+    let mut options = File::options();
+    options.create(true);
+    if truncate {
+        options.write(true).truncate(true);
+    } else {
+        options.append(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time_of_day = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Gregorian date of a day counted from 1970-01-01, after Howard Hinnant's algorithm
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// Log lines without their timestamps
+    fn lines(path: &Path) -> Vec<String> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_210_096_250);
+        assert_eq!(format_timestamp(time), "2024-02-29T12:34:56.250Z");
+    }
+
+    #[test]
+    fn test_logs_plain_text_lines() {
+        let dir = TempDir::new().unwrap();
+        let mut log = SessionLog::create(dir.path(), "s1", &LogRotation::default()).unwrap();
+        log.output(b"\x1b[32m$\x1b[0m ls\r\nfile1  fi").unwrap();
+        log.output(b"le2\r\n\x1b]0;title\x07$ ").unwrap();
+        let path = log.finish().unwrap();
+
+        assert_eq!(path, dir.path().join("s1.log"));
+        assert_eq!(lines(&path), vec!["$ ls", "file1  file2", "$ "]);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_hidden_input_is_left_out() {
+        let dir = TempDir::new().unwrap();
+        let mut log = SessionLog::create(dir.path(), "s1", &LogRotation::default()).unwrap();
+        log.output(b"Password: ").unwrap();
+        log.hide_input();
+        log.output(b"hunter2").unwrap();
+        log.output(b"\r\nWelcome\r\n").unwrap();
+        let path = log.finish().unwrap();
+        assert_eq!(lines(&path), vec!["Password: ", "Welcome"]);
+    }
+
+    #[test]
+    fn test_rotates_and_keeps_max_files() {
+        let dir = TempDir::new().unwrap();
+        let rotation = LogRotation {
+            max_bytes: 70,
+            max_files: 2,
+        };
+        let mut log = SessionLog::create(dir.path(), "s1", &rotation).unwrap();
+        for n in 0..12 {
+            log.output(format!("line {}\n", n).as_bytes()).unwrap();
+        }
+        let path = log.finish().unwrap();
+
+        // Entries are 32 or 33 bytes, so every file holds two lines
+        assert_eq!(lines(&path), vec!["line 10", "line 11"]);
+        assert_eq!(
+            lines(&dir.path().join("s1.log.1")),
+            vec!["line 8", "line 9"]
+        );
+        assert_eq!(
+            lines(&dir.path().join("s1.log.2")),
+            vec!["line 6", "line 7"]
+        );
+        assert!(!dir.path().join("s1.log.3").exists());
+    }
+}
@@ -0,0 +1,190 @@
+//! Bundled shell integration scripts, which make bash, zsh and fish report prompts,
+//! commands (OSC 133) and their working directory (OSC 7) without dotfile changes.
+//! The scripts are written to the runtime directory, which must be private to the
+//! user since every shell runs them, and wired in through the shell's own startup
+//! mechanism, then hand over to the user's startup files.
+
+use portable_pty::CommandBuilder;
+use std::path::{Path, PathBuf};
+
+const BASH_RC: &str = "bash/madsterm.bash";
+const FISH_DATA_DIR: &str = "fish";
+const ZSH_ZDOTDIR: &str = "zsh";
+
+const SCRIPTS: &[(&str, &str)] = &[
+    (
+        BASH_RC,
+        include_str!("../shell-integration/bash/madsterm.bash"),
+    ),
+    (
+        "zsh/.zshenv",
+        include_str!("../shell-integration/zsh/.zshenv"),
+    ),
+    (
+        "zsh/.zprofile",
+        include_str!("../shell-integration/zsh/.zprofile"),
+    ),
+    (
+        "zsh/.zshrc",
+        include_str!("../shell-integration/zsh/.zshrc"),
+    ),
+    (
+        "zsh/madsterm.zsh",
+        include_str!("../shell-integration/zsh/madsterm.zsh"),
+    ),
+    (
+        "fish/fish/vendor_conf.d/madsterm.fish",
+        include_str!("../shell-integration/fish/vendor_conf.d/madsterm.fish"),
+    ),
+];
+
+/// Where fish looks when XDG_DATA_DIRS is unset
+const FISH_DEFAULT_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Recognise a shell from a program path such as `/bin/zsh` or a login `-bash`
+    pub fn detect(program: &str) -> Option<Self> {
+        let name = program.rsplit('/').next().unwrap_or(program);
+        match name.trim_start_matches('-') {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            _ => None,
+        }
+    }
+}
+
+/// Directory the scripts are installed in. Fails unless the runtime directory holding
+/// it is private to the current user, as anyone able to change the scripts could run
+/// code in every new shell
+fn install_dir() -> std::io::Result<PathBuf> {
+    Ok(crate::daemon::ensure_runtime_dir()?.join("shell-integration"))
+}
+
+/// Write the bundled scripts under `dir`, leaving files that are already current alone
+fn install_to(dir: &Path) -> std::io::Result<()> {
+    for (name, contents) in SCRIPTS {
+        let path = dir.join(name);
+        if std::fs::read(&path).is_ok_and(|existing| existing == contents.as_bytes()) {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Written aside and renamed, so a shell starting meanwhile never reads half a script
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        std::fs::write(&tmp, contents)?;
+        std::fs::rename(&tmp, &path)?;
+    }
+    Ok(())
+}
+
+/// Install the scripts, returning their directory, or `None` (after logging) if
+/// that failed and the session should start without integration
+pub fn install() -> Option<PathBuf> {
+    let dir = install_dir().and_then(|dir| install_to(&dir).map(|_| dir));
+    match dir {
+        Ok(dir) => Some(dir),
+        Err(e) => {
+            log::warn!("Starting shells without integration: {}", e);
+            None
+        }
+    }
+}
+
+/// Start an interactive shell with its integration loaded from `dir`. Bash needs its
+/// own command line, so `cmd` must not have arguments yet; `login` makes the bash
+/// script read the login and logout files that `--rcfile` would otherwise skip
+pub fn inject(cmd: &mut CommandBuilder, shell: Shell, login: bool, dir: &Path) {
+    match shell {
+        Shell::Bash => {
+            cmd.arg("--rcfile");
+            cmd.arg(dir.join(BASH_RC));
+            if login {
+                cmd.env("MADSTERM_BASH_LOGIN", "1");
+            }
+        }
+        Shell::Zsh => {
+            if let Some(user_zdotdir) = cmd.get_env("ZDOTDIR").map(|dir| dir.to_owned()) {
+                cmd.env("MADSTERM_USER_ZDOTDIR", user_zdotdir);
+            }
+            cmd.env("ZDOTDIR", dir.join(ZSH_ZDOTDIR));
+        }
+        Shell::Fish => {
+            let data_dir = dir.join(FISH_DATA_DIR);
+            let existing = cmd
+                .get_env("XDG_DATA_DIRS")
+                .map(|dirs| dirs.to_string_lossy().into_owned())
+                .filter(|dirs| !dirs.is_empty())
+                .unwrap_or_else(|| FISH_DEFAULT_DATA_DIRS.to_string());
+            cmd.env(
+                "XDG_DATA_DIRS",
+                format!("{}:{}", data_dir.display(), existing),
+            );
+            cmd.env("MADSTERM_FISH_DATA_DIR", data_dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_detect_shell_from_program() {
+        assert_eq!(Shell::detect("/usr/bin/zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::detect("-bash"), Some(Shell::Bash));
+        assert_eq!(Shell::detect("fish"), Some(Shell::Fish));
+        assert_eq!(Shell::detect("/bin/sh"), None);
+        assert_eq!(Shell::detect("/usr/bin/bashful"), None);
+    }
+
+    #[test]
+    fn test_install_writes_every_script() {
+        let dir = TempDir::new().unwrap();
+        install_to(dir.path()).unwrap();
+        // Installing again over current files is a no-op
+        install_to(dir.path()).unwrap();
+        for (name, contents) in SCRIPTS {
+            assert_eq!(
+                std::fs::read_to_string(dir.path().join(name)).unwrap(),
+                *contents
+            );
+        }
+    }
+
+    #[test]
+    fn test_inject_zsh_keeps_user_zdotdir() {
+        let mut cmd = CommandBuilder::new("zsh");
+        cmd.env("ZDOTDIR", "/home/u/.config/zsh");
+        inject(&mut cmd, Shell::Zsh, true, Path::new("/run/m"));
+        assert_eq!(cmd.get_env("ZDOTDIR").unwrap(), "/run/m/zsh");
+        assert_eq!(
+            cmd.get_env("MADSTERM_USER_ZDOTDIR").unwrap(),
+            "/home/u/.config/zsh"
+        );
+    }
+
+    #[test]
+    fn test_inject_fish_prepends_data_dir() {
+        let mut cmd = CommandBuilder::new("fish");
+        cmd.env_remove("XDG_DATA_DIRS");
+        inject(&mut cmd, Shell::Fish, false, Path::new("/run/m"));
+        assert_eq!(
+            cmd.get_env("XDG_DATA_DIRS").unwrap(),
+            "/run/m/fish:/usr/local/share:/usr/share"
+        );
+        assert_eq!(
+            cmd.get_env("MADSTERM_FISH_DATA_DIR").unwrap(),
+            "/run/m/fish"
+        );
+    }
+}
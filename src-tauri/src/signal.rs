@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+/// Signals the frontend may send to a session's foreground process group
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PtySignal {
+    Sighup,
+    Sigint,
+    Sigquit,
+    Sigkill,
+    Sigusr1,
+    Sigusr2,
+    Sigterm,
+    Sigcont,
+    Sigtstp,
+}
+
+impl PtySignal {
+    const ALL: [PtySignal; 9] = [
+        PtySignal::Sighup,
+        PtySignal::Sigint,
+        PtySignal::Sigquit,
+        PtySignal::Sigkill,
+        PtySignal::Sigusr1,
+        PtySignal::Sigusr2,
+        PtySignal::Sigterm,
+        PtySignal::Sigcont,
+        PtySignal::Sigtstp,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PtySignal::Sighup => "SIGHUP",
+            PtySignal::Sigint => "SIGINT",
+            PtySignal::Sigquit => "SIGQUIT",
+            PtySignal::Sigkill => "SIGKILL",
+            PtySignal::Sigusr1 => "SIGUSR1",
+            PtySignal::Sigusr2 => "SIGUSR2",
+            PtySignal::Sigterm => "SIGTERM",
+            PtySignal::Sigcont => "SIGCONT",
+            PtySignal::Sigtstp => "SIGTSTP",
+        }
+    }
+
+    /// Parse a signal name such as `SIGINT` or `int`, case-insensitively
+    pub fn from_name(name: &str) -> Option<Self> {
+        let upper = name.trim().to_ascii_uppercase();
+        let full = if upper.starts_with("SIG") {
+            upper
+        } else {
+            format!("SIG{}", upper)
+        };
+        Self::ALL.into_iter().find(|signal| signal.name() == full)
+    }
+
+    #[cfg(unix)]
+    pub fn number(self) -> libc::c_int {
+        match self {
+            PtySignal::Sighup => libc::SIGHUP,
+            PtySignal::Sigint => libc::SIGINT,
+            PtySignal::Sigquit => libc::SIGQUIT,
+            PtySignal::Sigkill => libc::SIGKILL,
+            PtySignal::Sigusr1 => libc::SIGUSR1,
+            PtySignal::Sigusr2 => libc::SIGUSR2,
+            PtySignal::Sigterm => libc::SIGTERM,
+            PtySignal::Sigcont => libc::SIGCONT,
+            PtySignal::Sigtstp => libc::SIGTSTP,
+        }
+    }
+}
+
+/// Signals a process can be terminated by besides the ones in `PtySignal`
+#[cfg(unix)]
+const OTHER_SIGNALS: [(libc::c_int, &str); 13] = [
+    (libc::SIGILL, "SIGILL"),
+    (libc::SIGTRAP, "SIGTRAP"),
+    (libc::SIGABRT, "SIGABRT"),
+    (libc::SIGBUS, "SIGBUS"),
+    (libc::SIGFPE, "SIGFPE"),
+    (libc::SIGSEGV, "SIGSEGV"),
+    (libc::SIGPIPE, "SIGPIPE"),
+    (libc::SIGALRM, "SIGALRM"),
+    (libc::SIGCHLD, "SIGCHLD"),
+    (libc::SIGSTOP, "SIGSTOP"),
+    (libc::SIGTTIN, "SIGTTIN"),
+    (libc::SIGTTOU, "SIGTTOU"),
+    (libc::SIGWINCH, "SIGWINCH"),
+];
+
+/// Name of a signal by number, such as `SIGKILL` for 9
+#[cfg(unix)]
+pub fn signal_name(number: libc::c_int) -> Option<&'static str> {
+    PtySignal::ALL
+        .into_iter()
+        .find(|signal| signal.number() == number)
+        .map(PtySignal::name)
+        .or_else(|| {
+            OTHER_SIGNALS
+                .iter()
+                .find(|(n, _)| *n == number)
+                .map(|(_, name)| *name)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name_accepts_short_and_lowercase_names() {
+        assert_eq!(PtySignal::from_name("SIGINT"), Some(PtySignal::Sigint));
+        assert_eq!(PtySignal::from_name("term"), Some(PtySignal::Sigterm));
+        assert_eq!(PtySignal::from_name(" sigtstp "), Some(PtySignal::Sigtstp));
+    }
+
+    #[test]
+    fn test_from_name_rejects_unsupported_signals() {
+        assert_eq!(PtySignal::from_name("SIGSEGV"), None);
+        assert_eq!(PtySignal::from_name(""), None);
+        assert_eq!(PtySignal::from_name("9"), None);
+    }
+
+    #[test]
+    fn test_serde_uses_signal_names() {
+        let json = serde_json::to_string(&PtySignal::Sigusr1).unwrap();
+        assert_eq!(json, r#""SIGUSR1""#);
+        let parsed: PtySignal = serde_json::from_str(r#""SIGCONT""#).unwrap();
+        assert_eq!(parsed, PtySignal::Sigcont);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_signal_name_covers_sendable_and_fatal_signals() {
+        assert_eq!(signal_name(libc::SIGKILL), Some("SIGKILL"));
+        assert_eq!(signal_name(libc::SIGSEGV), Some("SIGSEGV"));
+        assert_eq!(signal_name(0), None);
+    }
+}
@@ -0,0 +1,52 @@
+//! Helpers shared by the unit tests of several modules.
+
+use crate::exit_status::ExitInfo;
+use crate::pty_manager::{SessionSink, TerminalSize};
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+
+/// Poll until `done` holds, failing the test after ten seconds
+pub fn wait_until(what: &str, mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !done() {
+        assert!(Instant::now() < deadline, "Timed out waiting for {}", what);
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Keeps a session's output, resizes and exit
+#[derive(Default)]
+pub struct CollectingSink {
+    pub data: Mutex<Vec<u8>>,
+    pub resizes: Mutex<Vec<TerminalSize>>,
+    pub exited: Mutex<Option<ExitInfo>>,
+}
+
+impl CollectingSink {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.data.lock()).into_owned()
+    }
+
+    pub fn wait_for_output(&self, needle: &str) {
+        wait_until(needle, || self.text().contains(needle));
+    }
+
+    pub fn wait_for_exit(&self) -> ExitInfo {
+        wait_until("exit", || self.exited.lock().is_some());
+        self.exited.lock().clone().unwrap()
+    }
+}
+
+impl SessionSink for CollectingSink {
+    fn output(&self, _session_id: &str, data: &[u8]) {
+        self.data.lock().extend_from_slice(data);
+    }
+
+    fn exit(&self, _session_id: &str, info: &ExitInfo) {
+        *self.exited.lock() = Some(info.clone());
+    }
+
+    fn resize(&self, _session_id: &str, size: TerminalSize) {
+        self.resizes.lock().push(size);
+    }
+}
@@ -0,0 +1,263 @@
+//! Output triggers: user-defined rules that watch a session's output for a pattern
+//! and act on it, such as notifying when a build fails or answering a known prompt.
+
+use crate::ansi::AnsiStripper;
+use crate::commands::fs::build_search_pattern;
+use crate::error::AppError;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Longest line kept for matching; a longer one is matched as it stands and dropped
+const MAX_LINE: usize = 4096;
+/// A rule answers at most this often, so a prompt that reappears with its own
+/// answer can't send it in a loop
+const RESPOND_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum TriggerAction {
+    /// Show a desktop notification, titled with the rule's name unless `title` is set
+    Notify {
+        #[serde(default)]
+        title: Option<String>,
+    },
+    /// Have the terminal highlight the match
+    Highlight {
+        #[serde(default)]
+        color: Option<String>,
+    },
+    Bell,
+    /// Type `input` into the session, e.g. `"y\r"` to answer a prompt
+    Respond {
+        input: String,
+    },
+}
+
+/// A `triggers` entry of the app config
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerRule {
+    #[serde(default)]
+    pub name: String,
+    /// Regular expression matched against each line of output, without escape sequences
+    pub pattern: String,
+    #[serde(default)]
+    pub ignore_case: bool,
+    #[serde(default)]
+    pub whole_word: bool,
+    pub action: TriggerAction,
+}
+
+/// A rule that fired, as reported in `pty-trigger-{id}`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerMatch {
+    pub name: String,
+    pub action: TriggerAction,
+    /// The matched text
+    pub text: String,
+    /// The line it was found in, as far as it had been received
+    pub line: String,
+}
+
+/// Trigger rules with their patterns compiled, shared by every session they apply to
+#[derive(Default)]
+pub struct TriggerSet {
+    rules: Vec<TriggerRule>,
+    regexes: Vec<Regex>,
+}
+
+impl TriggerSet {
+    /// Compile `rules`, failing on the first invalid pattern
+    pub fn compile(rules: &[TriggerRule]) -> Result<Self, AppError> {
+        let regexes = rules
+            .iter()
+            .map(|rule| {
+                let regex =
+                    build_search_pattern(&rule.pattern, !rule.ignore_case, rule.whole_word, true)?;
+                if regex.is_match("") {
+                    return Err(AppError::InvalidInput(format!(
+                        "Trigger pattern matches empty text: {}",
+                        rule.pattern
+                    )));
+                }
+                Ok(regex)
+            })
+            .collect::<Result<_, AppError>>()?;
+        Ok(TriggerSet {
+            rules: rules.to_vec(),
+            regexes,
+        })
+    }
+
+    pub fn rules(&self) -> &[TriggerRule] {
+        &self.rules
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+/// Where a rule has got to on the current line
+#[derive(Clone, Copy, Default)]
+struct RuleState {
+    /// End of the last match fired on the current line
+    matched_to: usize,
+    last_response: Option<Instant>,
+}
+
+/// A session's trigger rules and the output line they are matching
+#[derive(Default)]
+pub struct Triggers {
+    set: Arc<TriggerSet>,
+    state: Vec<RuleState>,
+    stripper: AnsiStripper,
+    line: Vec<u8>,
+}
+
+impl Triggers {
+    pub fn new(set: Arc<TriggerSet>) -> Self {
+        Triggers {
+            state: vec![RuleState::default(); set.rules.len()],
+            set,
+            ..Default::default()
+        }
+    }
+
+    /// Match a batch of output, returning the rules that fired. Output is matched a
+    /// line at a time, and an unfinished line is matched again as it grows, so a
+    /// match split across batches still fires, and prompts fire without waiting for
+    /// a newline. Each match fires once, when it is first seen
+    pub fn feed(&mut self, data: &[u8]) -> Vec<TriggerMatch> {
+        let mut fired = Vec::new();
+        if self.set.is_empty() {
+            return fired;
+        }
+        let mut text = Vec::with_capacity(data.len());
+        self.stripper.feed(data, &mut text);
+        for chunk in text.split_inclusive(|&b| b == b'\n') {
+            let (content, ends_line) = match chunk.strip_suffix(b"\n") {
+                Some(content) => (content.strip_suffix(b"\r").unwrap_or(content), true),
+                None => (chunk, false),
+            };
+            self.line.extend_from_slice(content);
+            if ends_line || self.line.len() >= MAX_LINE {
+                self.scan(&mut fired);
+                self.line.clear();
+                for state in &mut self.state {
+                    state.matched_to = 0;
+                }
+            }
+        }
+        if !self.line.is_empty() {
+            self.scan(&mut fired);
+        }
+        fired
+    }
+
+    fn scan(&mut self, fired: &mut Vec<TriggerMatch>) {
+        let line = String::from_utf8_lossy(&self.line);
+        let now = Instant::now();
+        let rules = self.set.rules.iter().zip(&self.set.regexes);
+        for ((rule, regex), state) in rules.zip(&mut self.state) {
+            for found in regex.find_iter(&line) {
+                // Matches that began before the last one ended were already fired,
+                // perhaps shorter, when the line was matched before
+                if found.is_empty() || found.start() < state.matched_to {
+                    continue;
+                }
+                state.matched_to = found.end();
+                if let TriggerAction::Respond { .. } = rule.action {
+                    if state
+                        .last_response
+                        .is_some_and(|last| now.duration_since(last) < RESPOND_INTERVAL)
+                    {
+                        continue;
+                    }
+                    state.last_response = Some(now);
+                }
+                fired.push(TriggerMatch {
+                    name: rule.name.clone(),
+                    action: rule.action.clone(),
+                    text: found.as_str().to_string(),
+                    line: line.to_string(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, action: TriggerAction) -> TriggerRule {
+        TriggerRule {
+            name: "test".to_string(),
+            pattern: pattern.to_string(),
+            ignore_case: false,
+            whole_word: false,
+            action,
+        }
+    }
+
+    fn compile(rules: &[TriggerRule]) -> Result<Triggers, AppError> {
+        TriggerSet::compile(rules).map(|set| Triggers::new(Arc::new(set)))
+    }
+
+    fn texts(fired: &[TriggerMatch]) -> Vec<&str> {
+        fired.iter().map(|m| m.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_match_split_across_batches_fires_once() {
+        let mut triggers = compile(&[TriggerRule {
+            ignore_case: true,
+            ..rule(r"error\w*", TriggerAction::Highlight { color: None })
+        }])
+        .unwrap();
+        assert!(triggers.feed(b"make: \x1b[31mErr").is_empty());
+        let fired = triggers.feed(b"or");
+        assert_eq!(texts(&fired), vec!["Error"]);
+        // Growing into a longer match does not fire again
+        assert!(triggers.feed(b"s found\r\n").is_empty());
+
+        let fired = triggers.feed(b"error\r\nno ERROR here? ERROR\r\n");
+        assert_eq!(texts(&fired), vec!["error", "ERROR", "ERROR"]);
+        assert_eq!(fired[1].line, "no ERROR here? ERROR");
+    }
+
+    #[test]
+    fn test_prompt_is_answered_without_a_newline() {
+        let respond = TriggerAction::Respond {
+            input: "y\r".to_string(),
+        };
+        let mut triggers = compile(&[rule(r"Continue\? \[y/N\]", respond)]).unwrap();
+        let fired = triggers.feed(b"\x1b[1mContinue? [y/N]\x1b[0m ");
+        assert_eq!(texts(&fired), vec!["Continue? [y/N]"]);
+        assert!(triggers.feed(b"y").is_empty());
+        // The same prompt straight away is not answered again
+        assert!(triggers.feed(b"\r\nContinue? [y/N] ").is_empty());
+    }
+
+    #[test]
+    fn test_whole_word_and_case() {
+        let mut triggers = compile(&[TriggerRule {
+            whole_word: true,
+            ..rule("fail", TriggerAction::Bell)
+        }])
+        .unwrap();
+        assert!(triggers.feed(b"failed FAIL\n").is_empty());
+        assert_eq!(texts(&triggers.feed(b"tests fail\n")), vec!["fail"]);
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        assert!(compile(&[rule("(", TriggerAction::Bell)]).is_err());
+        assert!(compile(&[rule("x*", TriggerAction::Bell)]).is_err());
+        assert!(compile(&[]).unwrap().feed(b"anything\n").is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+//! The workspace manifest: the sessions that were open when the app last saved, so
+//! the next start can open them again in the same order and directories.
+
+use crate::error::AppError;
+use crate::profile::LaunchProfile;
+use crate::pty_manager::SessionSummary;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const FILE_NAME: &str = "workspace.json";
+const VERSION: u32 = 1;
+
+/// How often the manifest is saved while the app runs, so a crash loses little
+pub const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Largest amount of output saved per tab, whatever the config asks for
+const MAX_SCROLLBACK_BYTES: usize = 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkspaceTab {
+    pub title: Option<String>,
+    /// The working directory the backend last saw the session in
+    pub cwd: Option<String>,
+    pub launch: LaunchProfile,
+    /// The end of the session's output, escape sequences included
+    pub scrollback: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Workspace {
+    pub version: u32,
+    /// When the manifest was written, in milliseconds since the Unix epoch
+    pub saved_ms: u64,
+    pub tabs: Vec<WorkspaceTab>,
+}
+
+/// Where the manifest lives, and the tab order the frontend last reported
+pub struct WorkspaceState {
+    pub path: PathBuf,
+    pub order: Mutex<Vec<String>>,
+    /// Set once the frontend asked to restore the manifest. Saving before then would
+    /// replace it with whatever little is running yet
+    restore_attempted: AtomicBool,
+}
+
+impl WorkspaceState {
+    pub fn new(path: PathBuf) -> Self {
+        WorkspaceState {
+            path,
+            order: Mutex::new(Vec::new()),
+            restore_attempted: AtomicBool::new(false),
+        }
+    }
+
+    pub fn restore_attempted(&self) -> bool {
+        self.restore_attempted.load(Ordering::Relaxed)
+    }
+
+    pub fn set_restore_attempted(&self) {
+        self.restore_attempted.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Workspace {
+    /// Describe the live sessions as tabs, in `order` with any others after it.
+    /// Sessions started for a one-off command are left out.
+    /// `scrollback` fetches a session's output, of which up to `scrollback_bytes` are kept
+    pub fn capture(
+        mut sessions: Vec<SessionSummary>,
+        order: &[String],
+        scrollback_bytes: usize,
+        scrollback: impl Fn(&str) -> Option<Vec<u8>>,
+    ) -> Self {
+        sessions.retain(|session| session.alive && !session.launch.one_off);
+        // Stable, so sessions missing from `order` stay oldest first
+        sessions.sort_by_key(|session| {
+            order
+                .iter()
+                .position(|id| *id == session.id)
+                .unwrap_or(usize::MAX)
+        });
+        let scrollback_bytes = scrollback_bytes.min(MAX_SCROLLBACK_BYTES);
+        let tabs = sessions
+            .into_iter()
+            .map(|session| WorkspaceTab {
+                scrollback: match scrollback_bytes {
+                    0 => None,
+                    max => scrollback(&session.id).and_then(|output| tail(&output, max)),
+                },
+                title: session.title,
+                cwd: session.cwd,
+                launch: session.launch,
+            })
+            .collect();
+        Workspace {
+            version: VERSION,
+            saved_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            tabs,
+        }
+    }
+
+    /// Read the manifest, or `None` if there is none or it can't be read
+    pub fn load(path: &Path) -> Option<Self> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        match serde_json::from_str::<Workspace>(&contents) {
+            Ok(workspace) if workspace.version == VERSION => Some(workspace),
+            Ok(workspace) => {
+                log::warn!(
+                    "Ignoring workspace {} with unknown version {}",
+                    path.display(),
+                    workspace.version
+                );
+                None
+            }
+            Err(e) => {
+                log::warn!("Ignoring invalid workspace {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let contents =
+            serde_json::to_string_pretty(self).map_err(|e| AppError::Io(e.to_string()))?;
+        // Write to a sibling file first so a crash never leaves a truncated manifest
+        let tmp = path.with_extension("json.tmp");
+        // Removed first, so one left by a crash doesn't keep laxer permissions
+        let _ = std::fs::remove_file(&tmp);
+        let mut options = std::fs::File::options();
+        options.write(true).create_new(true);
+        // Readable by the user only, as saved output can hold anything the terminal showed
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&tmp)?.write_all(contents.as_bytes())?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// The last `max` bytes of output, starting on a fresh line where there is one so
+/// the text doesn't begin partway through an escape sequence
+fn tail(output: &[u8], max: usize) -> Option<String> {
+    let start = output.len().saturating_sub(max);
+    let mut tail = &output[start..];
+    if start > 0 {
+        if let Some(newline) = tail.iter().position(|&b| b == b'\n') {
+            tail = &tail[newline + 1..];
+        }
+    }
+    if tail.is_empty() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(tail).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn summary(id: &str, alive: bool) -> SessionSummary {
+        SessionSummary {
+            id: id.to_string(),
+            pid: None,
+            cols: 80,
+            rows: 24,
+            started_ms: 0,
+            cwd: Some(format!("/srv/{}", id)),
+            title: Some(id.to_string()),
+            launch: LaunchProfile {
+                program: Some("fish".to_string()),
+                ..Default::default()
+            },
+            foreground: None,
+            bytes_in: 0,
+            bytes_out: 0,
+            attached: true,
+            alive,
+            exit: None,
+        }
+    }
+
+    #[test]
+    fn test_capture_orders_tabs_and_skips_exited_and_one_off_sessions() {
+        let mut one_off = summary("one-off", true);
+        one_off.launch.one_off = true;
+        let sessions = vec![
+            summary("a", true),
+            summary("b", true),
+            summary("gone", false),
+            one_off,
+            summary("c", true),
+        ];
+        let order = ["c".to_string(), "a".to_string()];
+        let workspace = Workspace::capture(sessions, &order, 4, |id| {
+            Some(format!("{} old\r\n{}$ ", id, id).into_bytes())
+        });
+        let titles: Vec<_> = workspace
+            .tabs
+            .iter()
+            .map(|tab| tab.title.as_deref().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["c", "a", "b"]);
+        let tab = &workspace.tabs[0];
+        assert_eq!(tab.cwd.as_deref(), Some("/srv/c"));
+        assert_eq!(tab.launch.program.as_deref(), Some("fish"));
+        assert_eq!(tab.scrollback.as_deref(), Some("c$ "));
+
+        let workspace = Workspace::capture(vec![summary("a", true)], &[], 0, |_| {
+            panic!("scrollback fetched when none is saved")
+        });
+        assert_eq!(workspace.tabs[0].scrollback, None);
+    }
+
+    #[test]
+    fn test_tail_starts_on_a_fresh_line() {
+        assert_eq!(tail(b"one\ntwo\nthree", 9).as_deref(), Some("three"));
+        assert_eq!(tail(b"one\ntwo", 100).as_deref(), Some("one\ntwo"));
+        assert_eq!(tail(b"abcdef", 3).as_deref(), Some("def"));
+        assert_eq!(tail(b"", 10), None);
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("data").join(FILE_NAME);
+        assert!(Workspace::load(&path).is_none());
+
+        let workspace = Workspace::capture(vec![summary("a", true)], &[], 0, |_| None);
+        workspace.save(&path).unwrap();
+        assert_eq!(Workspace::load(&path), Some(workspace));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::write(&path, "{ not json").unwrap();
+        assert!(Workspace::load(&path).is_none());
+        std::fs::write(&path, r#"{"version": 99, "tabs": []}"#).unwrap();
+        assert!(Workspace::load(&path).is_none());
+    }
+}
@@ -5,26 +5,29 @@ import {
   PanelGroup,
   PanelResizeHandle,
 } from "react-resizable-panels";
+import { getConfigLoadError } from "./commands/pty";
 import { DiffPanel } from "./components/diff/DiffPanel";
 import { FileExplorer } from "./components/explorer/FileExplorer";
 import { AboutDialog } from "./components/layout/AboutDialog";
 import { SettingsPanel } from "./components/layout/SettingsPanel";
 import { StatusBar } from "./components/layout/StatusBar";
 import { TabBar } from "./components/layout/TabBar";
+import {
+  listenForRemoteTabs,
+  openInitialTabs,
+  reportTabOrder,
+} from "./components/terminal/initialTabs";
 import { TerminalPanel } from "./components/terminal/TerminalPanel";
 import { ToastContainer } from "./components/ui/Toast";
 import { useKeyboardShortcuts } from "./hooks/useKeyboardShortcuts";
+import { useNotificationStore } from "./stores/notificationStore";
 import { usePanelStore } from "./stores/panelStore";
-import { useTabStore } from "./stores/tabStore";
+import { createTabId, useTabStore } from "./stores/tabStore";
 import { useThemeStore } from "./stores/themeStore";
 
-function generateId() {
-  return Math.random().toString(36).substring(2, 10);
-}
-
 export default function App() {
   const { leftPanelVisible, rightPanelVisible } = usePanelStore();
-  const { tabs, activeTabId, addTab, removeTab } = useTabStore();
+  const { activeTabId, addTab, removeTab } = useTabStore();
   const theme = useThemeStore((s) => s.theme);
   const { settingsOpen, setSettingsOpen, aboutOpen, setAboutOpen } = usePanelStore();
 
@@ -36,7 +39,7 @@ export default function App() {
   }, [setSettingsOpen]);
 
   const handleNewTab = useCallback(() => {
-    const id = generateId();
+    const id = createTabId();
     addTab({
       id,
       title: "Terminal",
@@ -52,14 +55,37 @@ export default function App() {
     }
   }, [activeTabId, removeTab]);
 
-  // Create initial tab on mount
+  // Reopen surviving and saved sessions on mount, or start with a fresh tab
   const initializedRef = useRef(false);
   useEffect(() => {
-    if (tabs.length === 0 && !initializedRef.current) {
-      initializedRef.current = true;
-      handleNewTab();
-    }
-  }, [handleNewTab, tabs.length]);
+    if (initializedRef.current) return;
+    initializedRef.current = true;
+    openInitialTabs().then(() => {
+      if (useTabStore.getState().tabs.length === 0) {
+        handleNewTab();
+      }
+      reportTabOrder();
+    });
+  }, [handleNewTab]);
+
+  // An unreadable config is left alone rather than overwritten, which the user should know
+  useEffect(() => {
+    getConfigLoadError()
+      .then((message) => {
+        if (message) {
+          useNotificationStore.getState().addNotification({ type: "error", message, duration: 0 });
+        }
+      })
+      .catch(() => {});
+  }, []);
+
+  // Tabs opened from outside, e.g. by `madsterm-ctl open-tab` or a second `madsterm`
+  useEffect(() => {
+    const unlisten = listenForRemoteTabs();
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
 
   useKeyboardShortcuts(handleNewTab, handleCloseTab, handleOpenSettings);
 
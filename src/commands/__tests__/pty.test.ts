@@ -1,6 +1,49 @@
+import { Channel } from "@tauri-apps/api/core";
 import { beforeEach, describe, expect, it, vi } from "vitest";
 import { mockInvoke, mockListen } from "../../test/setup";
-import { closePty, createPtySession, onPtyExit, onPtyOutput, resizePty, writePty } from "../pty";
+import {
+  ackPty,
+  attachPty,
+  closePty,
+  createPtyGroup,
+  createPtySession,
+  dissolvePtyGroup,
+  getForegroundProcess,
+  getPtyBlockOutput,
+  getPtyCwd,
+  getPtyMonitorStatus,
+  getPtyScrollback,
+  listDaemonSessions,
+  listPtyBlocks,
+  listPtyGroups,
+  listPtySessions,
+  listTriggers,
+  onControlOpenTab,
+  onPtyCwd,
+  onPtyExit,
+  onPtyBell,
+  onPtyForeground,
+  onPtyMonitor,
+  onPtyNotify,
+  onPtyTitle,
+  onPtyTrigger,
+  playRecording,
+  resizePty,
+  restoreWorkspace,
+  saveWorkspace,
+  setPlaybackSpeed,
+  setPtyGroupMembers,
+  setPtyMonitor,
+  setTriggers,
+  signalPty,
+  startPtyLog,
+  startPtyRecording,
+  stopPlayback,
+  stopPtyLog,
+  stopPtyRecording,
+  takeLaunchArgs,
+  writePty,
+} from "../pty";
 
 describe("pty commands", () => {
   beforeEach(() => {
@@ -11,25 +54,64 @@ describe("pty commands", () => {
 
   it("should call invoke with correct command and args for createPtySession", async () => {
     mockInvoke.mockResolvedValue("session-123");
-    const result = await createPtySession(80, 24, "/home/user");
+    const result = await createPtySession(80, 24, "/home/user", vi.fn());
     expect(mockInvoke).toHaveBeenCalledWith("pty_create", {
       cols: 80,
       rows: 24,
+      cell: null,
       cwd: "/home/user",
+      onOutput: expect.any(Channel),
     });
     expect(result).toBe("session-123");
   });
 
+  it("should deliver channel output to the createPtySession callback as bytes", async () => {
+    mockInvoke.mockResolvedValue("session-123");
+    const onOutput = vi.fn();
+    await createPtySession(80, 24, undefined, onOutput);
+    const channel = mockInvoke.mock.calls[0][1].onOutput as Channel<ArrayBuffer>;
+    channel.onmessage(new Uint8Array([104, 105, 0xff]).buffer);
+    expect(onOutput).toHaveBeenCalledWith(new Uint8Array([104, 105, 0xff]));
+  });
+
   it("should pass null cwd when not provided to createPtySession", async () => {
     mockInvoke.mockResolvedValue("session-456");
-    await createPtySession(80, 24);
+    await createPtySession(80, 24, undefined, vi.fn());
     expect(mockInvoke).toHaveBeenCalledWith("pty_create", {
       cols: 80,
       rows: 24,
+      cell: null,
       cwd: null,
+      onOutput: expect.any(Channel),
+    });
+  });
+
+  it("should pass launch arguments to createPtySession", async () => {
+    mockInvoke.mockResolvedValue("session-789");
+    await createPtySession(80, 24, "/home/user", vi.fn(), undefined, {
+      cwd: "/srv/api",
+      profile: "fish",
+      command: ["cargo", "run"],
+    });
+    expect(mockInvoke).toHaveBeenCalledWith("pty_create", {
+      cols: 80,
+      rows: 24,
+      cell: null,
+      cwd: "/srv/api",
+      profile: "fish",
+      command: ["cargo", "run"],
+      onOutput: expect.any(Channel),
     });
   });
 
+  it("should call invoke with correct command for takeLaunchArgs", async () => {
+    const args = { cwd: "/srv/api", profile: null, command: [] };
+    mockInvoke.mockResolvedValue(args);
+    const result = await takeLaunchArgs();
+    expect(mockInvoke).toHaveBeenCalledWith("take_launch_args");
+    expect(result).toEqual(args);
+  });
+
   it("should call invoke with correct command and args for writePty", async () => {
     mockInvoke.mockResolvedValue(undefined);
     await writePty("sess1", "hello");
@@ -39,6 +121,35 @@ describe("pty commands", () => {
     });
   });
 
+  it("should return broadcast failures from writePty", async () => {
+    const failures = [{ sessionId: "sess2", error: "Session sess2 not found" }];
+    mockInvoke.mockResolvedValue(failures);
+    const result = await writePty("sess1", "ls\r");
+    expect(result).toEqual(failures);
+  });
+
+  it("should call invoke with correct commands and args for broadcast groups", async () => {
+    mockInvoke.mockResolvedValue("group1");
+    expect(await createPtyGroup(["sess1", "sess2"])).toBe("group1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_group_create", {
+      sessionIds: ["sess1", "sess2"],
+    });
+
+    mockInvoke.mockResolvedValue(undefined);
+    await setPtyGroupMembers("group1", ["sess1"]);
+    expect(mockInvoke).toHaveBeenCalledWith("pty_group_set_members", {
+      groupId: "group1",
+      sessionIds: ["sess1"],
+    });
+    await dissolvePtyGroup("group1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_group_dissolve", { groupId: "group1" });
+
+    const groups = [{ id: "group1", members: ["sess1"] }];
+    mockInvoke.mockResolvedValue(groups);
+    expect(await listPtyGroups()).toEqual(groups);
+    expect(mockInvoke).toHaveBeenCalledWith("pty_group_list");
+  });
+
   it("should call invoke with correct command and args for resizePty", async () => {
     mockInvoke.mockResolvedValue(undefined);
     await resizePty("sess1", 120, 40);
@@ -46,26 +157,339 @@ describe("pty commands", () => {
       sessionId: "sess1",
       cols: 120,
       rows: 40,
+      cell: null,
     });
   });
 
-  it("should call invoke with correct command and args for closePty", async () => {
-    mockInvoke.mockResolvedValue(undefined);
-    await closePty("sess1");
-    expect(mockInvoke).toHaveBeenCalledWith("pty_close", {
+  it("should pass cell pixel metrics to pty_create and pty_resize", async () => {
+    mockInvoke.mockResolvedValue("session-789");
+    await createPtySession(80, 24, undefined, vi.fn(), { width: 9, height: 18 });
+    expect(mockInvoke).toHaveBeenCalledWith(
+      "pty_create",
+      expect.objectContaining({ cell: { width: 9, height: 18 } }),
+    );
+    await resizePty("session-789", 100, 30, { width: 10, height: 20 });
+    expect(mockInvoke).toHaveBeenCalledWith("pty_resize", {
+      sessionId: "session-789",
+      cols: 100,
+      rows: 30,
+      cell: { width: 10, height: 20 },
+    });
+  });
+
+  it("should call invoke with correct command and args for getForegroundProcess", async () => {
+    const process = {
+      pid: 42,
+      name: "vim",
+      argv: ["vim", "main.rs"],
+      isShell: false,
+      cwd: "/home/user",
+    };
+    mockInvoke.mockResolvedValue(process);
+    const result = await getForegroundProcess("sess1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_foreground_process", {
       sessionId: "sess1",
     });
+    expect(result).toEqual(process);
+  });
+
+  it("should call listen with correct event name for onPtyForeground", async () => {
+    const unlistenFn = vi.fn();
+    mockListen.mockResolvedValue(unlistenFn);
+    const result = await onPtyForeground("sess1", vi.fn());
+    expect(mockListen).toHaveBeenCalledWith("pty-foreground-sess1", expect.any(Function));
+    expect(result).toBe(unlistenFn);
   });
 
-  it("should call listen with correct event name for onPtyOutput", async () => {
+  it("should call invoke with correct command and args for getPtyCwd", async () => {
+    mockInvoke.mockResolvedValue("/home/user/project");
+    const result = await getPtyCwd("sess1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_get_cwd", { sessionId: "sess1" });
+    expect(result).toBe("/home/user/project");
+  });
+
+  it("should call listen with correct event name for onPtyCwd", async () => {
     const unlistenFn = vi.fn();
     mockListen.mockResolvedValue(unlistenFn);
+    const result = await onPtyCwd("sess1", vi.fn());
+    expect(mockListen).toHaveBeenCalledWith("pty-cwd-sess1", expect.any(Function));
+    expect(result).toBe(unlistenFn);
+  });
+
+  it("should forward title events from onPtyTitle", async () => {
+    let handler: ((event: { payload: unknown }) => void) | undefined;
+    mockListen.mockImplementation((_name: string, cb: typeof handler) => {
+      handler = cb;
+      return Promise.resolve(() => {});
+    });
     const callback = vi.fn();
-    const result = await onPtyOutput("sess1", callback);
-    expect(mockListen).toHaveBeenCalledWith("pty-output-sess1", expect.any(Function));
+    await onPtyTitle("sess1", callback);
+    expect(mockListen).toHaveBeenCalledWith("pty-title-sess1", expect.any(Function));
+    handler?.({ payload: "vim main.rs" });
+    expect(callback).toHaveBeenCalledWith("vim main.rs");
+  });
+
+  it("should call listen with correct event name for onPtyBell", async () => {
+    const unlistenFn = vi.fn();
+    mockListen.mockResolvedValue(unlistenFn);
+    const result = await onPtyBell("sess1", vi.fn());
+    expect(mockListen).toHaveBeenCalledWith("pty-bell-sess1", expect.any(Function));
     expect(result).toBe(unlistenFn);
   });
 
+  it("should forward notifications from onPtyNotify", async () => {
+    let handler: ((event: { payload: unknown }) => void) | undefined;
+    mockListen.mockImplementation((_name: string, cb: typeof handler) => {
+      handler = cb;
+      return Promise.resolve(() => {});
+    });
+    const callback = vi.fn();
+    await onPtyNotify("sess1", callback);
+    expect(mockListen).toHaveBeenCalledWith("pty-notify-sess1", expect.any(Function));
+    const notification = { title: "make", body: "done" };
+    handler?.({ payload: notification });
+    expect(callback).toHaveBeenCalledWith(notification);
+  });
+
+  it("should call invoke with correct command and args for trigger rules", async () => {
+    const rules = [
+      {
+        name: "confirm",
+        pattern: "Continue\\? \\[y/N\\]",
+        action: { type: "respond" as const, input: "y\r" },
+      },
+    ];
+    mockInvoke.mockResolvedValue(rules);
+    expect(await listTriggers()).toEqual(rules);
+    expect(mockInvoke).toHaveBeenCalledWith("trigger_list");
+
+    mockInvoke.mockResolvedValue(undefined);
+    await setTriggers(rules);
+    expect(mockInvoke).toHaveBeenCalledWith("trigger_set", { rules });
+  });
+
+  it("should forward fired triggers from onPtyTrigger", async () => {
+    let handler: ((event: { payload: unknown }) => void) | undefined;
+    mockListen.mockImplementation((_name: string, cb: typeof handler) => {
+      handler = cb;
+      return Promise.resolve(() => {});
+    });
+    const callback = vi.fn();
+    await onPtyTrigger("sess1", callback);
+    expect(mockListen).toHaveBeenCalledWith("pty-trigger-sess1", expect.any(Function));
+    const fired = { name: "errors", action: { type: "highlight" }, text: "error", line: "1 error" };
+    handler?.({ payload: fired });
+    expect(callback).toHaveBeenCalledWith(fired);
+  });
+
+  it("should call invoke with correct command and args for setPtyMonitor", async () => {
+    mockInvoke.mockResolvedValue(undefined);
+    await setPtyMonitor("sess1", { activity: true, silenceSecs: 30 });
+    expect(mockInvoke).toHaveBeenCalledWith("pty_set_monitor", {
+      sessionId: "sess1",
+      settings: { activity: true, silenceSecs: 30 },
+    });
+  });
+
+  it("should call invoke with correct command and args for getPtyMonitorStatus", async () => {
+    const status = { bells: 2, lastOutputMs: 1700000000000, activity: true, silent: false };
+    mockInvoke.mockResolvedValue(status);
+    expect(await getPtyMonitorStatus("sess1")).toEqual(status);
+    expect(mockInvoke).toHaveBeenCalledWith("pty_monitor_status", { sessionId: "sess1" });
+  });
+
+  it("should forward alerts from onPtyMonitor", async () => {
+    let handler: ((event: { payload: unknown }) => void) | undefined;
+    mockListen.mockImplementation((_name: string, cb: typeof handler) => {
+      handler = cb;
+      return Promise.resolve(() => {});
+    });
+    const callback = vi.fn();
+    await onPtyMonitor("sess1", callback);
+    expect(mockListen).toHaveBeenCalledWith("pty-monitor-sess1", expect.any(Function));
+    const alert = {
+      kind: "silence",
+      status: { bells: 0, lastOutputMs: 1, activity: false, silent: true },
+    };
+    handler?.({ payload: alert });
+    expect(callback).toHaveBeenCalledWith(alert);
+  });
+
+  it("should call invoke with correct command and args for signalPty", async () => {
+    mockInvoke.mockResolvedValue(undefined);
+    await signalPty("sess1", "SIGKILL");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_signal", {
+      sessionId: "sess1",
+      signal: "SIGKILL",
+    });
+  });
+
+  it("should call invoke with correct command and args for ackPty", async () => {
+    mockInvoke.mockResolvedValue(undefined);
+    await ackPty("sess1", 65536);
+    expect(mockInvoke).toHaveBeenCalledWith("pty_ack", {
+      sessionId: "sess1",
+      bytes: 65536,
+    });
+  });
+
+  it("should call invoke with correct command and args for listPtyBlocks", async () => {
+    const block = {
+      id: 0,
+      command: "make",
+      start: 0,
+      outputStart: 24,
+      end: 40,
+      exitCode: 1,
+      startedAt: 1700000000000,
+      durationMs: 1200,
+      cwd: "/home/user",
+    };
+    mockInvoke.mockResolvedValue([block]);
+    const result = await listPtyBlocks("sess1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_list_blocks", { sessionId: "sess1" });
+    expect(result).toEqual([block]);
+  });
+
+  it("should call invoke with correct command and args for getPtyBlockOutput", async () => {
+    mockInvoke.mockResolvedValue("built\r\n");
+    const result = await getPtyBlockOutput("sess1", 3, true);
+    expect(mockInvoke).toHaveBeenCalledWith("pty_get_block_output", {
+      sessionId: "sess1",
+      blockId: 3,
+      plain: true,
+    });
+    expect(result).toBe("built\r\n");
+  });
+
+  it("should call invoke with correct command and args for startPtyLog", async () => {
+    mockInvoke.mockResolvedValue("/logs/sess1.log");
+    const result = await startPtyLog("sess1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_start_log", {
+      sessionId: "sess1",
+      directory: null,
+    });
+    expect(result).toBe("/logs/sess1.log");
+  });
+
+  it("should call invoke with correct command and args for stopPtyLog", async () => {
+    mockInvoke.mockResolvedValue("/logs/sess1.log");
+    const result = await stopPtyLog("sess1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_stop_log", { sessionId: "sess1" });
+    expect(result).toBe("/logs/sess1.log");
+  });
+
+  it("should call invoke with correct command and args for startPtyRecording", async () => {
+    mockInvoke.mockResolvedValue(undefined);
+    await startPtyRecording("sess1", "/tmp/demo.cast");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_start_recording", {
+      sessionId: "sess1",
+      path: "/tmp/demo.cast",
+    });
+  });
+
+  it("should call invoke with correct command and args for stopPtyRecording", async () => {
+    mockInvoke.mockResolvedValue("/tmp/demo.cast");
+    const result = await stopPtyRecording("sess1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_stop_recording", { sessionId: "sess1" });
+    expect(result).toBe("/tmp/demo.cast");
+  });
+
+  it("should call invoke with correct command and args for playRecording", async () => {
+    const info = { id: "play1", cols: 80, rows: 24 };
+    mockInvoke.mockResolvedValue(info);
+    const result = await playRecording("/tmp/demo.cast", undefined, vi.fn());
+    expect(mockInvoke).toHaveBeenCalledWith("pty_play_recording", {
+      path: "/tmp/demo.cast",
+      speed: null,
+      onOutput: expect.any(Channel),
+    });
+    expect(result).toEqual(info);
+  });
+
+  it("should call invoke with correct command and args for playback controls", async () => {
+    mockInvoke.mockResolvedValue(undefined);
+    await setPlaybackSpeed("play1", 2);
+    expect(mockInvoke).toHaveBeenCalledWith("pty_set_playback_speed", {
+      playbackId: "play1",
+      speed: 2,
+    });
+    await stopPlayback("play1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_stop_playback", { playbackId: "play1" });
+  });
+
+  it("should call invoke with correct command and args for getPtyScrollback", async () => {
+    mockInvoke.mockResolvedValue(new TextEncoder().encode("$ ls\r\n").buffer);
+    const result = await getPtyScrollback("sess1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_get_scrollback", {
+      sessionId: "sess1",
+    });
+    expect(new TextDecoder().decode(result)).toBe("$ ls\r\n");
+  });
+
+  it("should call invoke with correct command for listDaemonSessions", async () => {
+    const sessions = [{ id: "sess1", pid: 42, cols: 80, rows: 24, attached: false, alive: true }];
+    mockInvoke.mockResolvedValue(sessions);
+    const result = await listDaemonSessions();
+    expect(mockInvoke).toHaveBeenCalledWith("pty_daemon_sessions");
+    expect(result).toEqual(sessions);
+  });
+
+  it("should call invoke with correct command for listPtySessions", async () => {
+    const sessions = [
+      {
+        id: "sess1",
+        pid: 42,
+        cols: 80,
+        rows: 24,
+        startedMs: 1700000000000,
+        cwd: "/tmp",
+        title: "build",
+        foreground: null,
+        bytesIn: 6,
+        bytesOut: 1024,
+        attached: true,
+        alive: false,
+        exit: { exitCode: 0, signal: null, signalName: null, readerError: null },
+      },
+    ];
+    mockInvoke.mockResolvedValue(sessions);
+    const result = await listPtySessions();
+    expect(mockInvoke).toHaveBeenCalledWith("pty_list");
+    expect(result).toEqual(sessions);
+  });
+
+  it("should forward remotely opened tabs from onControlOpenTab", async () => {
+    let handler: ((event: { payload: unknown }) => void) | undefined;
+    mockListen.mockImplementation((_name: string, cb: typeof handler) => {
+      handler = cb;
+      return Promise.resolve(() => {});
+    });
+    const callback = vi.fn();
+    await onControlOpenTab(callback);
+    expect(mockListen).toHaveBeenCalledWith("control-open-tab", expect.any(Function));
+    const tab = { sessionId: "sess1", title: "api" };
+    handler?.({ payload: tab });
+    expect(callback).toHaveBeenCalledWith(tab);
+  });
+
+  it("should call invoke with correct command and args for attachPty", async () => {
+    mockInvoke.mockResolvedValue(undefined);
+    await attachPty("sess1", vi.fn());
+    expect(mockInvoke).toHaveBeenCalledWith("pty_attach", {
+      sessionId: "sess1",
+      onOutput: expect.any(Channel),
+    });
+  });
+
+  it("should call invoke with correct command and args for closePty", async () => {
+    mockInvoke.mockResolvedValue(undefined);
+    await closePty("sess1");
+    expect(mockInvoke).toHaveBeenCalledWith("pty_close", {
+      sessionId: "sess1",
+    });
+  });
+
   it("should call listen with correct event name for onPtyExit", async () => {
     const unlistenFn = vi.fn();
     mockListen.mockResolvedValue(unlistenFn);
@@ -74,4 +498,31 @@ describe("pty commands", () => {
     expect(mockListen).toHaveBeenCalledWith("pty-exit-sess1", expect.any(Function));
     expect(result).toBe(unlistenFn);
   });
+
+  it("should pass the exit info to the onPtyExit callback", async () => {
+    let handler: ((event: { payload: unknown }) => void) | undefined;
+    mockListen.mockImplementation((_name: string, cb: typeof handler) => {
+      handler = cb;
+      return Promise.resolve(() => {});
+    });
+    const callback = vi.fn();
+    await onPtyExit("sess1", callback);
+    const info = { exitCode: null, signal: 9, signalName: "SIGKILL", readerError: null };
+    handler?.({ payload: info });
+    expect(callback).toHaveBeenCalledWith(info);
+  });
+
+  it("should call invoke with correct command for restoreWorkspace", async () => {
+    const tabs = [{ sessionId: "sess1", title: "api", scrollback: "$ " }];
+    mockInvoke.mockResolvedValue(tabs);
+    const result = await restoreWorkspace();
+    expect(mockInvoke).toHaveBeenCalledWith("restore_workspace");
+    expect(result).toEqual(tabs);
+  });
+
+  it("should call invoke with correct command and args for saveWorkspace", async () => {
+    mockInvoke.mockResolvedValue(undefined);
+    await saveWorkspace(["sess2", "sess1"]);
+    expect(mockInvoke).toHaveBeenCalledWith("save_workspace", { tabOrder: ["sess2", "sess1"] });
+  });
 });
@@ -0,0 +1,59 @@
+import { Channel } from "@tauri-apps/api/core";
+import { beforeEach, describe, expect, it, vi } from "vitest";
+import { mockInvoke } from "../../test/setup";
+import { runCommand } from "../run";
+
+describe("run commands", () => {
+  beforeEach(() => {
+    mockInvoke.mockReset();
+  });
+
+  it("should invoke run_command with its options and resolve with the result", async () => {
+    const exit = {
+      exitCode: 0,
+      signal: null,
+      signalName: null,
+      readerError: null,
+      timedOut: false,
+      cancelled: false,
+      durationMs: 12,
+    };
+    mockInvoke.mockResolvedValue(exit);
+    const running = runCommand({
+      program: "cargo",
+      args: ["test", "-p", "foo"],
+      cwd: "/home/user/repo",
+      timeoutMs: 60000,
+    });
+    expect(mockInvoke).toHaveBeenCalledWith("run_command", {
+      runId: running.id,
+      program: "cargo",
+      args: ["test", "-p", "foo"],
+      cwd: "/home/user/repo",
+      env: null,
+      timeoutMs: 60000,
+      onStdout: expect.any(Channel),
+      onStderr: expect.any(Channel),
+    });
+    expect(await running.result).toEqual(exit);
+  });
+
+  it("should deliver stdout and stderr to their own callbacks", () => {
+    mockInvoke.mockResolvedValue(undefined);
+    const onStdout = vi.fn();
+    const onStderr = vi.fn();
+    runCommand({ program: "git", onStdout, onStderr });
+    const args = mockInvoke.mock.calls[0][1];
+    (args.onStdout as Channel<ArrayBuffer>).onmessage(new Uint8Array([111]).buffer);
+    (args.onStderr as Channel<ArrayBuffer>).onmessage(new Uint8Array([101]).buffer);
+    expect(onStdout).toHaveBeenCalledWith(new Uint8Array([111]));
+    expect(onStderr).toHaveBeenCalledWith(new Uint8Array([101]));
+  });
+
+  it("should cancel by run ID", async () => {
+    mockInvoke.mockResolvedValue(undefined);
+    const running = runCommand({ program: "sleep", args: ["30"] });
+    await running.cancel();
+    expect(mockInvoke).toHaveBeenLastCalledWith("run_cancel", { runId: running.id });
+  });
+});
@@ -1,31 +1,428 @@
-import { invoke } from "@tauri-apps/api/core";
+import { Channel, invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
-export async function createPtySession(cols: number, rows: number, cwd?: string): Promise<string> {
-  return invoke<string>("pty_create", { cols, rows, cwd: cwd || null });
+/** Channel receiving a session's raw output bytes, coalesced by the backend */
+function outputChannel(onOutput: (data: Uint8Array) => void): Channel<ArrayBuffer> {
+  const channel = new Channel<ArrayBuffer>();
+  channel.onmessage = (data) => onOutput(new Uint8Array(data));
+  return channel;
 }
 
-export async function writePty(sessionId: string, data: string): Promise<void> {
-  return invoke("pty_write", { sessionId, data });
+/** Size of one character cell in pixels, which lets programs size inline images */
+export interface CellSize {
+  width: number;
+  height: number;
 }
 
-export async function resizePty(sessionId: string, cols: number, rows: number): Promise<void> {
-  return invoke("pty_resize", { sessionId, cols, rows });
+export async function createPtySession(
+  cols: number,
+  rows: number,
+  cwd: string | undefined,
+  onOutput: (data: Uint8Array) => void,
+  cell?: CellSize,
+  launch?: LaunchArgs,
+): Promise<string> {
+  return invoke<string>("pty_create", {
+    cols,
+    rows,
+    cell: cell ?? null,
+    cwd: launch?.cwd || cwd || null,
+    ...(launch && { profile: launch.profile, command: launch.command }),
+    onOutput: outputChannel(onOutput),
+  });
+}
+
+/** What `madsterm [--cwd DIR] [--profile NAME] [PATH] [-e CMD ARGS...]` asked to open */
+export interface LaunchArgs {
+  cwd: string | null;
+  profile: string | null;
+  command: string[];
+}
+
+/** The app's command-line arguments for its first tab; null once taken or when there were none */
+export async function takeLaunchArgs(): Promise<LaunchArgs | null> {
+  return invoke<LaunchArgs | null>("take_launch_args");
+}
+
+/** A broadcast group member that input could not be written to */
+export interface WriteFailure {
+  sessionId: string;
+  error: string;
+}
+
+/** Input to a session in a broadcast group goes to every member; failures are returned */
+export async function writePty(sessionId: string, data: string): Promise<WriteFailure[]> {
+  return invoke<WriteFailure[]>("pty_write", { sessionId, data });
+}
+
+export interface SessionGroup {
+  id: string;
+  members: string[];
+}
+
+export async function createPtyGroup(sessionIds: string[]): Promise<string> {
+  return invoke<string>("pty_group_create", { sessionIds });
+}
+
+export async function setPtyGroupMembers(groupId: string, sessionIds: string[]): Promise<void> {
+  return invoke("pty_group_set_members", { groupId, sessionIds });
+}
+
+export async function dissolvePtyGroup(groupId: string): Promise<void> {
+  return invoke("pty_group_dissolve", { groupId });
+}
+
+export async function listPtyGroups(): Promise<SessionGroup[]> {
+  return invoke<SessionGroup[]>("pty_group_list");
+}
+
+/** Without `cell`, the backend keeps the cell size it was last given */
+export async function resizePty(
+  sessionId: string,
+  cols: number,
+  rows: number,
+  cell?: CellSize,
+): Promise<void> {
+  return invoke("pty_resize", { sessionId, cols, rows, cell: cell ?? null });
+}
+
+export interface ForegroundProcess {
+  pid: number;
+  name: string;
+  argv: string[];
+  /** True when the shell itself is in the foreground, i.e. nothing is running */
+  isShell: boolean;
+  /** Working directory of the job, where the platform exposes it */
+  cwd: string | null;
+}
+
+export async function getForegroundProcess(sessionId: string): Promise<ForegroundProcess | null> {
+  return invoke<ForegroundProcess | null>("pty_foreground_process", { sessionId });
+}
+
+export function onPtyForeground(
+  sessionId: string,
+  callback: (process: ForegroundProcess) => void,
+): Promise<UnlistenFn> {
+  return listen<ForegroundProcess>(`pty-foreground-${sessionId}`, (event) =>
+    callback(event.payload),
+  );
+}
+
+export async function getPtyCwd(sessionId: string): Promise<string | null> {
+  return invoke<string | null>("pty_get_cwd", { sessionId });
+}
+
+export function onPtyCwd(sessionId: string, callback: (cwd: string) => void): Promise<UnlistenFn> {
+  return listen<string>(`pty-cwd-${sessionId}`, (event) => callback(event.payload));
+}
+
+export function onPtyTitle(
+  sessionId: string,
+  callback: (title: string) => void,
+): Promise<UnlistenFn> {
+  return listen<string>(`pty-title-${sessionId}`, (event) => callback(event.payload));
+}
+
+export function onPtyBell(sessionId: string, callback: () => void): Promise<UnlistenFn> {
+  return listen<null>(`pty-bell-${sessionId}`, () => callback());
+}
+
+/** Requested by a program with OSC 9 or OSC 777 */
+export interface PtyNotification {
+  title: string | null;
+  body: string;
+}
+
+export function onPtyNotify(
+  sessionId: string,
+  callback: (notification: PtyNotification) => void,
+): Promise<UnlistenFn> {
+  return listen<PtyNotification>(`pty-notify-${sessionId}`, (event) => callback(event.payload));
+}
+
+export type TriggerAction =
+  | { type: "notify"; title?: string | null }
+  | { type: "highlight"; color?: string | null }
+  | { type: "bell" }
+  | { type: "respond"; input: string };
+
+/** A regex matched against each line of every session's output */
+export interface TriggerRule {
+  name: string;
+  pattern: string;
+  ignoreCase?: boolean;
+  wholeWord?: boolean;
+  action: TriggerAction;
+}
+
+export interface TriggerMatch {
+  name: string;
+  action: TriggerAction;
+  text: string;
+  line: string;
+}
+
+export async function listTriggers(): Promise<TriggerRule[]> {
+  return invoke<TriggerRule[]>("trigger_list");
+}
+
+/** Save the trigger rules and apply them to every running session */
+export async function setTriggers(rules: TriggerRule[]): Promise<void> {
+  return invoke("trigger_set", { rules });
+}
+
+export function onPtyTrigger(
+  sessionId: string,
+  callback: (fired: TriggerMatch) => void,
+): Promise<UnlistenFn> {
+  return listen<TriggerMatch>(`pty-trigger-${sessionId}`, (event) => callback(event.payload));
+}
+
+export interface MonitorSettings {
+  /** Alert on the first output after monitoring starts */
+  activity?: boolean;
+  /** Alert once output stops for this many seconds */
+  silenceSecs?: number | null;
+}
+
+export interface MonitorStatus {
+  bells: number;
+  /** Milliseconds since the Unix epoch */
+  lastOutputMs: number | null;
+  activity: boolean;
+  silent: boolean;
+}
+
+export interface MonitorAlert {
+  kind: "activity" | "silence";
+  status: MonitorStatus;
+}
+
+/** Watch a session for activity or silence, clearing earlier alerts and the bell count */
+export async function setPtyMonitor(sessionId: string, settings: MonitorSettings): Promise<void> {
+  return invoke("pty_set_monitor", { sessionId, settings });
+}
+
+export async function getPtyMonitorStatus(sessionId: string): Promise<MonitorStatus> {
+  return invoke<MonitorStatus>("pty_monitor_status", { sessionId });
+}
+
+export function onPtyMonitor(
+  sessionId: string,
+  callback: (alert: MonitorAlert) => void,
+): Promise<UnlistenFn> {
+  return listen<MonitorAlert>(`pty-monitor-${sessionId}`, (event) => callback(event.payload));
+}
+
+export type PtySignal =
+  | "SIGHUP"
+  | "SIGINT"
+  | "SIGQUIT"
+  | "SIGKILL"
+  | "SIGUSR1"
+  | "SIGUSR2"
+  | "SIGTERM"
+  | "SIGCONT"
+  | "SIGTSTP";
+
+/** Send a signal to the session's foreground process group */
+export async function signalPty(sessionId: string, signal: PtySignal): Promise<void> {
+  return invoke("pty_signal", { sessionId, signal });
+}
+
+/** Report output the terminal has finished processing, so the backend keeps reading */
+export async function ackPty(sessionId: string, bytes: number): Promise<void> {
+  return invoke("pty_ack", { sessionId, bytes });
+}
+
+/** Raw recent output, which `Terminal.write` accepts as it is */
+export async function getPtyScrollback(sessionId: string): Promise<Uint8Array> {
+  return new Uint8Array(await invoke<ArrayBuffer>("pty_get_scrollback", { sessionId }));
+}
+
+/** A command marked by OSC 133 shell integration; offsets count bytes of session output */
+export interface CommandBlock {
+  id: number;
+  command: string;
+  start: number;
+  outputStart: number;
+  /** Null while the command is still running */
+  end: number | null;
+  exitCode: number | null;
+  /** Milliseconds since the Unix epoch */
+  startedAt: number;
+  durationMs: number | null;
+  cwd: string | null;
+}
+
+export async function listPtyBlocks(sessionId: string): Promise<CommandBlock[]> {
+  return invoke<CommandBlock[]>("pty_list_blocks", { sessionId });
+}
+
+export async function getPtyBlockOutput(
+  sessionId: string,
+  blockId: number,
+  plain = false,
+): Promise<string> {
+  return invoke<string>("pty_get_block_output", { sessionId, blockId, plain });
+}
+
+/**
+ * Start a plain-text log of the session's output, in `directory` or else the configured
+ * log directory. Resolves to the log file's path
+ */
+export async function startPtyLog(sessionId: string, directory?: string): Promise<string> {
+  return invoke<string>("pty_start_log", { sessionId, directory: directory ?? null });
+}
+
+export async function stopPtyLog(sessionId: string): Promise<string> {
+  return invoke<string>("pty_stop_log", { sessionId });
+}
+
+/** Start writing the session to an asciinema v2 `.cast` file at an absolute path */
+export async function startPtyRecording(sessionId: string, path: string): Promise<void> {
+  return invoke("pty_start_recording", { sessionId, path });
+}
+
+/** Finish the session's recording, resolving to the file it was written to */
+export async function stopPtyRecording(sessionId: string): Promise<string> {
+  return invoke<string>("pty_stop_recording", { sessionId });
+}
+
+export interface PlaybackInfo {
+  /** Used with the playback commands and `onPtyExit`, which fires when playback ends */
+  id: string;
+  cols: number;
+  rows: number;
+}
+
+export async function playRecording(
+  path: string,
+  speed: number | undefined,
+  onOutput: (data: Uint8Array) => void,
+): Promise<PlaybackInfo> {
+  return invoke<PlaybackInfo>("pty_play_recording", {
+    path,
+    speed: speed ?? null,
+    onOutput: outputChannel(onOutput),
+  });
+}
+
+/** A resize event in the recording being played back */
+export function onPlaybackResize(
+  playbackId: string,
+  callback: (size: { cols: number; rows: number }) => void,
+): Promise<UnlistenFn> {
+  return listen<{ cols: number; rows: number }>(`pty-resize-${playbackId}`, (event) =>
+    callback(event.payload),
+  );
+}
+
+export async function setPlaybackSpeed(playbackId: string, speed: number): Promise<void> {
+  return invoke("pty_set_playback_speed", { playbackId, speed });
+}
+
+export async function stopPlayback(playbackId: string): Promise<void> {
+  return invoke("pty_stop_playback", { playbackId });
+}
+
+/** Why the config file couldn't be read at startup; settings aren't saved until it is fixed */
+export async function getConfigLoadError(): Promise<string | null> {
+  return invoke<string | null>("config_load_error");
+}
+
+/** A recipe for spawning a session, as kept in the config's profiles */
+export interface LaunchProfile {
+  name: string;
+  program: string | null;
+  args: string[];
+  env: Record<string, string>;
+  envRemove: string[];
+  cwd: string | null;
+  login: boolean | null;
+  shellIntegration: boolean | null;
+  /** Started for a one-off command, so the workspace doesn't restore it */
+  oneOff?: boolean;
+}
+
+export interface PtySessionSummary {
+  id: string;
+  pid: number | null;
+  cols: number;
+  rows: number;
+  /** Milliseconds since the Unix epoch */
+  startedMs: number;
+  cwd: string | null;
+  title: string | null;
+  /** What the session was started with */
+  launch: LaunchProfile;
+  foreground: ForegroundProcess | null;
+  bytesIn: number;
+  bytesOut: number;
+  attached: boolean;
+  alive: boolean;
+  exit: PtyExitInfo | null;
+}
+
+/** A session opened with `madsterm-ctl open-tab`, to be shown with `attachPty` */
+export interface ControlOpenTab {
+  sessionId: string;
+  title: string | null;
+}
+
+export function onControlOpenTab(callback: (tab: ControlOpenTab) => void): Promise<UnlistenFn> {
+  return listen<ControlOpenTab>("control-open-tab", (event) => callback(event.payload));
+}
+
+/** Every session the backend holds, oldest first, including exited ones not yet closed */
+export async function listPtySessions(): Promise<PtySessionSummary[]> {
+  return invoke<PtySessionSummary[]>("pty_list");
+}
+
+export async function listDaemonSessions(): Promise<PtySessionSummary[]> {
+  return invoke<PtySessionSummary[]>("pty_daemon_sessions");
+}
+
+export async function attachPty(
+  sessionId: string,
+  onOutput: (data: Uint8Array) => void,
+): Promise<void> {
+  return invoke("pty_attach", { sessionId, onOutput: outputChannel(onOutput) });
 }
 
 export async function closePty(sessionId: string): Promise<void> {
   return invoke("pty_close", { sessionId });
 }
 
-export function onPtyOutput(
+export interface PtyExitInfo {
+  exitCode: number | null;
+  signal: number | null;
+  signalName: string | null;
+  readerError: string | null;
+}
+
+export function onPtyExit(
   sessionId: string,
-  callback: (data: string) => void,
+  callback: (info: PtyExitInfo) => void,
 ): Promise<UnlistenFn> {
-  return listen<string>(`pty-output-${sessionId}`, (event) => {
-    callback(event.payload);
-  });
+  return listen<PtyExitInfo>(`pty-exit-${sessionId}`, (event) => callback(event.payload));
+}
+
+/** A session started by `restoreWorkspace`, to be shown with `attachPty` */
+export interface RestoredTab {
+  sessionId: string;
+  title: string | null;
+  /** Output saved from the previous run, to write before attaching */
+  scrollback: string | null;
+}
+
+/** Reopen the sessions saved when the app last ran; does nothing if sessions are already running */
+export async function restoreWorkspace(): Promise<RestoredTab[]> {
+  return invoke<RestoredTab[]>("restore_workspace");
 }
 
-export function onPtyExit(sessionId: string, callback: () => void): Promise<UnlistenFn> {
-  return listen(`pty-exit-${sessionId}`, () => callback());
+/** Save the workspace now, with the tabs in the order they are shown */
+export async function saveWorkspace(tabOrder: string[]): Promise<void> {
+  return invoke("save_workspace", { tabOrder });
 }
@@ -0,0 +1,52 @@
+import { Channel, invoke } from "@tauri-apps/api/core";
+
+export interface RunOptions {
+  program: string;
+  args?: string[];
+  cwd?: string;
+  /** Added to the app's environment */
+  env?: Record<string, string>;
+  /** The command is stopped and `timedOut` set once it runs this long */
+  timeoutMs?: number;
+  onStdout?: (data: Uint8Array) => void;
+  onStderr?: (data: Uint8Array) => void;
+}
+
+export interface RunResult {
+  exitCode: number | null;
+  signal: number | null;
+  signalName: string | null;
+  readerError: string | null;
+  timedOut: boolean;
+  cancelled: boolean;
+  durationMs: number;
+}
+
+export interface RunningCommand {
+  id: string;
+  /** Resolves once the command exits, including after a timeout or cancel */
+  result: Promise<RunResult>;
+  cancel: () => Promise<void>;
+}
+
+function byteChannel(onData?: (data: Uint8Array) => void): Channel<ArrayBuffer> {
+  const channel = new Channel<ArrayBuffer>();
+  channel.onmessage = (data) => onData?.(new Uint8Array(data));
+  return channel;
+}
+
+/** Run a command without a terminal, streaming stdout and stderr separately */
+export function runCommand(options: RunOptions): RunningCommand {
+  const id = crypto.randomUUID();
+  const result = invoke<RunResult>("run_command", {
+    runId: id,
+    program: options.program,
+    args: options.args ?? [],
+    cwd: options.cwd ?? null,
+    env: options.env ?? null,
+    timeoutMs: options.timeoutMs ?? null,
+    onStdout: byteChannel(options.onStdout),
+    onStderr: byteChannel(options.onStderr),
+  });
+  return { id, result, cancel: () => invoke("run_cancel", { runId: id }) };
+}
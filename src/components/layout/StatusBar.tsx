@@ -3,6 +3,7 @@ import { useEffect, useLayoutEffect, useRef, useState } from "react";
 import { getCwd, getShellName } from "../../commands/fs";
 import { fetchGitBranch } from "../../commands/git";
 import { usePanelStore } from "../../stores/panelStore";
+import { useTabStore } from "../../stores/tabStore";
 import { useThemeStore } from "../../stores/themeStore";
 import { ThemePicker } from "./ThemePicker";
 
@@ -94,6 +95,7 @@ export const StatusBar: React.FC = () => {
   const theme = useThemeStore((s) => s.theme);
   const [branch, setBranch] = useState<string | null>(null);
   const [shellName, setShellName] = useState<string>("sh");
+  const activeCwd = useTabStore((s) => s.tabs.find((t) => t.id === s.activeTabId)?.cwd);
 
   useEffect(() => {
     getShellName()
@@ -104,7 +106,7 @@ export const StatusBar: React.FC = () => {
   useEffect(() => {
     const loadBranch = async () => {
       try {
-        const cwd = await getCwd();
+        const cwd = activeCwd || (await getCwd());
         const b = await fetchGitBranch(cwd);
         setBranch(b);
       } catch {
@@ -114,7 +116,7 @@ export const StatusBar: React.FC = () => {
     loadBranch();
     const interval = setInterval(loadBranch, 5000);
     return () => clearInterval(interval);
-  }, []);
+  }, [activeCwd]);
 
   const infoItemStyle: React.CSSProperties = {
     display: "flex",
@@ -10,13 +10,13 @@ import { useSettingsStore } from "../../stores/settingsStore";
 import { useTabStore } from "../../stores/tabStore";
 import { useThemeStore } from "../../stores/themeStore";
 import { createKeyEventHandler } from "./keyHandler";
-import { cleanupPty, initPty } from "./ptyLifecycle";
+import { cleanupPty, initPty, type PtySource } from "./ptyLifecycle";
 import type { VimMode } from "./vimMode";
 import "@xterm/xterm/css/xterm.css";
 
 interface TerminalInstanceProps {
   tabId: string;
-  cwd?: string;
+  source: PtySource;
   isActive: boolean;
   onTitleChange?: (title: string) => void;
   onExit?: () => void;
@@ -25,7 +25,7 @@ interface TerminalInstanceProps {
 
 export const TerminalInstance: React.FC<TerminalInstanceProps> = ({
   tabId,
-  cwd,
+  source,
   isActive,
   onTitleChange,
   onExit,
@@ -35,6 +35,7 @@ export const TerminalInstance: React.FC<TerminalInstanceProps> = ({
     useSettingsStore();
   const theme = useThemeStore((s) => s.theme);
   const updateTabCwd = useTabStore((s) => s.updateTabCwd);
+  const updateTabSessionId = useTabStore((s) => s.updateTabSessionId);
   const addNotification = useNotificationStore((s) => s.addNotification);
   const containerRef = useRef<HTMLDivElement>(null);
   const terminalRef = useRef<Terminal | null>(null);
@@ -110,13 +111,18 @@ export const TerminalInstance: React.FC<TerminalInstanceProps> = ({
     }
 
     terminalRef.current = terminal;
-    fitAddonRef.current = fitAddon;
+    // A playback keeps the size it was recorded at rather than following the container
+    const followsContainer = !source.recordingPath;
+    fitAddonRef.current = followsContainer ? fitAddon : null;
     searchAddonRef.current = searchAddon;
     onSearchAddonReady?.(searchAddon);
 
     const cleanupRefs = {
       unlistenOutput: null as (() => void) | null,
       unlistenExit: null as (() => void) | null,
+      unlistenCwd: null as (() => void) | null,
+      unlistenTitle: null as (() => void) | null,
+      unlistenResize: null as (() => void) | null,
     };
     let ptyInitialized = false;
 
@@ -128,10 +134,13 @@ export const TerminalInstance: React.FC<TerminalInstanceProps> = ({
           terminal,
           cols,
           rows,
-          cwd,
+          source,
           {
             onConnected: (sessionId) => {
               sessionIdRef.current = sessionId;
+              if (sessionId) {
+                updateTabSessionId(tabId, sessionId);
+              }
               setConnecting(false);
             },
             onSessionEnded: () => {
@@ -168,7 +177,7 @@ export const TerminalInstance: React.FC<TerminalInstanceProps> = ({
 
     const resizeObserver = new ResizeObserver(() => {
       requestAnimationFrame(() => {
-        fitAddon.fit();
+        if (followsContainer) fitAddon.fit();
       });
     });
     resizeObserver.observe(containerRef.current);
@@ -172,7 +172,13 @@ export const TerminalPanel: React.FC = () => {
           <TerminalInstance
             key={tab.id}
             tabId={tab.id}
-            cwd={tab.cwd}
+            source={{
+              cwd: tab.cwd,
+              attachSessionId: tab.attachSessionId,
+              scrollback: tab.restoredScrollback,
+              recordingPath: tab.recordingPath,
+              launch: tab.launch,
+            }}
             isActive={tab.id === activeTabId}
             onTitleChange={(title) => updateTabTitle(tab.id, title)}
             onExit={() => removeTab(tab.id)}
@@ -0,0 +1,131 @@
+import { beforeEach, describe, expect, it } from "vitest";
+import { mockInvoke, mockListen } from "../../../test/setup";
+import { useTabStore } from "../../../stores/tabStore";
+import { listenForRemoteTabs, openInitialTabs, reportTabOrder } from "../initialTabs";
+
+const session = (id: string, cwd: string | null, title: string | null) => ({
+  id,
+  cwd,
+  title,
+  alive: true,
+});
+
+/** Answer each command from `responses`, as the backend would */
+const respondWith = (responses: Record<string, unknown>) =>
+  mockInvoke.mockImplementation((command: string) => Promise.resolve(responses[command] ?? null));
+
+describe("openInitialTabs", () => {
+  beforeEach(() => {
+    mockInvoke.mockReset();
+    useTabStore.setState({ tabs: [], activeTabId: null, recentlyClosedTabs: [] });
+  });
+
+  it("reattaches to the sessions the daemon kept running", async () => {
+    respondWith({
+      pty_daemon_sessions: [session("sess1", "/srv/api", null), session("sess2", null, "htop")],
+    });
+    expect(await openInitialTabs()).toBe(2);
+    expect(mockInvoke).toHaveBeenCalledWith("pty_daemon_sessions");
+    const { tabs, activeTabId } = useTabStore.getState();
+    expect(tabs.map((t) => [t.attachSessionId, t.title, t.cwd])).toEqual([
+      ["sess1", "api", "/srv/api"],
+      ["sess2", "htop", ""],
+    ]);
+    expect(activeTabId).toBe(tabs[0].id);
+  });
+
+  it("reopens the saved workspace with its scrollback", async () => {
+    respondWith({
+      restore_workspace: [
+        { sessionId: "sess1", title: "api", scrollback: "$ make\r\n" },
+        { sessionId: "sess2", title: null, scrollback: null },
+      ],
+    });
+    expect(await openInitialTabs()).toBe(2);
+    expect(mockInvoke).toHaveBeenCalledWith("restore_workspace");
+    const { tabs } = useTabStore.getState();
+    expect(tabs.map((t) => [t.attachSessionId, t.title, t.restoredScrollback])).toEqual([
+      ["sess1", "api", "$ make\r\n"],
+      ["sess2", "Terminal", undefined],
+    ]);
+  });
+
+  it("opens the tab the command line asked for", async () => {
+    const launch = { cwd: "/srv/api", profile: null, command: ["cargo", "run"] };
+    respondWith({
+      take_launch_args: launch,
+      pty_daemon_sessions: [session("sess1", "/srv/db", null)],
+    });
+    expect(await openInitialTabs()).toBe(2);
+    expect(mockInvoke).toHaveBeenCalledWith("take_launch_args");
+    const { tabs, activeTabId } = useTabStore.getState();
+    expect(tabs[1]).toMatchObject({ title: "cargo", cwd: "/srv/api", launch });
+    expect(tabs[1].attachSessionId).toBeUndefined();
+    expect(activeTabId).toBe(tabs[1].id);
+  });
+
+  it("opens nothing when the daemon is unavailable", async () => {
+    mockInvoke.mockRejectedValue("no daemon");
+    expect(await openInitialTabs()).toBe(0);
+    expect(useTabStore.getState().tabs).toHaveLength(0);
+  });
+});
+
+describe("listenForRemoteTabs", () => {
+  beforeEach(() => {
+    mockListen.mockReset();
+    useTabStore.setState({ tabs: [], activeTabId: null, recentlyClosedTabs: [] });
+  });
+
+  it("opens a tab attached to each remotely opened session", async () => {
+    let handler: ((event: { payload: unknown }) => void) | undefined;
+    mockListen.mockImplementation((_name: string, cb: typeof handler) => {
+      handler = cb;
+      return Promise.resolve(() => {});
+    });
+    await listenForRemoteTabs();
+    expect(mockListen).toHaveBeenCalledWith("control-open-tab", expect.any(Function));
+    handler?.({ payload: { sessionId: "sess1", title: "build" } });
+    handler?.({ payload: { sessionId: "sess2", title: null } });
+
+    const { tabs, activeTabId } = useTabStore.getState();
+    expect(tabs.map((t) => [t.attachSessionId, t.title])).toEqual([
+      ["sess1", "build"],
+      ["sess2", "Terminal"],
+    ]);
+    expect(activeTabId).toBe(tabs[1].id);
+  });
+});
+
+describe("reportTabOrder", () => {
+  beforeEach(() => {
+    mockInvoke.mockReset();
+    mockInvoke.mockResolvedValue(undefined);
+    useTabStore.setState({ tabs: [], activeTabId: null, recentlyClosedTabs: [] });
+  });
+
+  it("saves the workspace when the order of sessions changes", () => {
+    const tab = (id: string, sessionId: string) => ({
+      id,
+      title: id,
+      sessionId,
+      cwd: "",
+      isActive: true,
+    });
+    const unsubscribe = reportTabOrder();
+    const { addTab, moveTab, updateTabTitle } = useTabStore.getState();
+    addTab(tab("a", "sess1"));
+    addTab(tab("b", "sess2"));
+    // Tabs without a session yet, e.g. still connecting or playing a recording, are left out
+    addTab(tab("c", ""));
+    moveTab(1, 0);
+    updateTabTitle("a", "renamed");
+    unsubscribe();
+
+    expect(mockInvoke.mock.calls).toEqual([
+      ["save_workspace", { tabOrder: ["sess1"] }],
+      ["save_workspace", { tabOrder: ["sess1", "sess2"] }],
+      ["save_workspace", { tabOrder: ["sess2", "sess1"] }],
+    ]);
+  });
+});
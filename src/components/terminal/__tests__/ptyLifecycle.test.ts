@@ -0,0 +1,78 @@
+import type { Terminal } from "@xterm/xterm";
+import { describe, expect, it, vi } from "vitest";
+import { mockInvoke, mockListen } from "../../../test/setup";
+import { cellSize, describeExit, initPlayback } from "../ptyLifecycle";
+
+const clean = { exitCode: 0, signal: null, signalName: null, readerError: null };
+
+describe("describeExit", () => {
+  it("reports a clean exit as completed", () => {
+    expect(describeExit(clean)).toBe("Process completed");
+  });
+
+  it("includes a non-zero exit code", () => {
+    expect(describeExit({ ...clean, exitCode: 2 })).toBe("Process exited with code 2");
+  });
+
+  it("names the terminating signal", () => {
+    expect(describeExit({ ...clean, exitCode: null, signal: 9, signalName: "SIGKILL" })).toBe(
+      "Process terminated by SIGKILL",
+    );
+    expect(describeExit({ ...clean, exitCode: null, signal: 64, signalName: null })).toBe(
+      "Process terminated by signal 64",
+    );
+  });
+
+  it("prefers a reader failure over the exit status", () => {
+    expect(describeExit({ ...clean, readerError: "Input/output error" })).toBe(
+      "Lost terminal output: Input/output error",
+    );
+  });
+});
+
+describe("cellSize", () => {
+  const terminalWithCell = (cell?: { width: number; height: number }) =>
+    ({ _core: { _renderService: { dimensions: { css: { cell } } } } }) as unknown as Terminal;
+
+  it("rounds the renderer's cell size to whole pixels", () => {
+    expect(cellSize(terminalWithCell({ width: 8.4, height: 17.6 }))).toEqual({
+      width: 8,
+      height: 18,
+    });
+  });
+
+  it("is undefined before the renderer has measured", () => {
+    expect(cellSize(terminalWithCell())).toBeUndefined();
+    expect(cellSize(terminalWithCell({ width: 0, height: 0 }))).toBeUndefined();
+  });
+});
+
+describe("initPlayback", () => {
+  it("sizes the terminal from the recording and follows its resizes", async () => {
+    mockInvoke.mockReset();
+    mockInvoke.mockResolvedValue({ id: "p1", cols: 100, rows: 30 });
+    const handlers: Record<string, (event: { payload: unknown }) => void> = {};
+    mockListen.mockImplementation((name: string, cb: (event: { payload: unknown }) => void) => {
+      handlers[name] = cb;
+      return Promise.resolve(() => {});
+    });
+    const terminal = { options: {}, write: vi.fn(), resize: vi.fn() } as unknown as Terminal;
+    const callbacks = {
+      onConnected: vi.fn(),
+      onSessionEnded: vi.fn(),
+      onError: vi.fn(),
+      updateTabCwd: vi.fn(),
+      tabId: "t1",
+      copyOnSelect: false,
+    };
+
+    await initPlayback(terminal, "/tmp/demo.cast", callbacks, {
+      unlistenOutput: null,
+      unlistenExit: null,
+    });
+    expect(terminal.resize).toHaveBeenCalledWith(100, 30);
+    handlers["pty-resize-p1"]({ payload: { cols: 90, rows: 20 } });
+    expect(terminal.resize).toHaveBeenLastCalledWith(90, 20);
+    expect(callbacks.onConnected).toHaveBeenCalledWith(null);
+  });
+});
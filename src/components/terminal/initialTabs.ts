@@ -0,0 +1,98 @@
+import type { UnlistenFn } from "@tauri-apps/api/event";
+import {
+  type LaunchArgs,
+  listDaemonSessions,
+  onControlOpenTab,
+  restoreWorkspace,
+  saveWorkspace,
+  takeLaunchArgs,
+} from "../../commands/pty";
+import { createTabId, folderTitle, useTabStore } from "../../stores/tabStore";
+import type { Tab } from "../../types";
+
+/** Tabs for the sessions the daemon kept running while the app was closed */
+async function daemonTabs(): Promise<Tab[]> {
+  const sessions = await listDaemonSessions();
+  return sessions.map((session) => ({
+    id: createTabId(),
+    title: session.title ?? folderTitle(session.cwd ?? ""),
+    sessionId: "",
+    cwd: session.cwd ?? "",
+    isActive: true,
+    attachSessionId: session.id,
+  }));
+}
+
+/** Tabs for the sessions saved when the app last quit, started again by the backend */
+async function restoredTabs(): Promise<Tab[]> {
+  const restored = await restoreWorkspace();
+  return restored.map((tab) => ({
+    id: createTabId(),
+    title: tab.title ?? "Terminal",
+    sessionId: "",
+    cwd: "",
+    isActive: true,
+    attachSessionId: tab.sessionId,
+    restoredScrollback: tab.scrollback ?? undefined,
+  }));
+}
+
+/** The tab `madsterm [PATH] [-e CMD ...]` asked for */
+function launchTab(launch: LaunchArgs): Tab {
+  return {
+    id: createTabId(),
+    title: launch.command[0] ?? folderTitle(launch.cwd ?? ""),
+    sessionId: "",
+    cwd: launch.cwd ?? "",
+    isActive: true,
+    launch,
+  };
+}
+
+/** Open the tabs the app starts with, returning how many were opened */
+export async function openInitialTabs(): Promise<number> {
+  const { addTab, setActiveTab } = useTabStore.getState();
+  // Taken first, so no other tab can be started with them
+  const launch = await takeLaunchArgs().catch(() => null);
+  const tabs = await daemonTabs().catch(() => []);
+  // Always asked, since the backend only saves the workspace once it has been restored.
+  // It starts nothing while sessions are running, so before any tab starts one
+  tabs.push(...(await restoredTabs().catch(() => [])));
+  if (launch) {
+    tabs.push(launchTab(launch));
+  }
+  for (const tab of tabs) {
+    addTab(tab);
+  }
+  if (tabs.length > 0) {
+    // The tab the command line asked for, else the first one reopened
+    setActiveTab(launch ? tabs[tabs.length - 1].id : tabs[0].id);
+  }
+  return tabs.length;
+}
+
+/** Show sessions opened over remote control, e.g. by `madsterm-ctl open-tab`, in new tabs */
+export function listenForRemoteTabs(): Promise<UnlistenFn> {
+  return onControlOpenTab((opened) => {
+    useTabStore.getState().addTab({
+      id: createTabId(),
+      title: opened.title ?? "Terminal",
+      sessionId: "",
+      cwd: "",
+      isActive: true,
+      attachSessionId: opened.sessionId,
+    });
+  });
+}
+
+/** Keep the backend's saved workspace in the order the tabs are shown, returning the unsubscribe */
+export function reportTabOrder(): () => void {
+  let reported = "";
+  return useTabStore.subscribe(({ tabs }) => {
+    const order = tabs.map((tab) => tab.sessionId).filter(Boolean);
+    if (order.join() !== reported) {
+      reported = order.join();
+      saveWorkspace(order).catch(() => {});
+    }
+  });
+}
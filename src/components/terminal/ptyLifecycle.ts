@@ -1,16 +1,29 @@
 import type { Terminal } from "@xterm/xterm";
-import { getHomeDir } from "../../commands/fs";
 import {
+  ackPty,
+  attachPty,
+  type CellSize,
   closePty,
+  type LaunchArgs,
   createPtySession,
+  listPtySessions,
+  onPtyCwd,
+  onPlaybackResize,
   onPtyExit,
-  onPtyOutput,
+  onPtyTitle,
+  type PtyExitInfo,
+  playRecording,
   resizePty,
+  stopPlayback,
   writePty,
 } from "../../commands/pty";
 
+// Acks are batched; the backend only pauses after far more than this is outstanding
+const ACK_BATCH_BYTES = 64 * 1024;
+
 interface PtyCallbacks {
-  onConnected: (sessionId: string) => void;
+  /** `null` for a playback, which has no session to write to or close */
+  onConnected: (sessionId: string | null) => void;
   onSessionEnded: () => void;
   onError: (message: string) => void;
   onTitleChange?: (title: string) => void;
@@ -19,32 +32,164 @@ interface PtyCallbacks {
   copyOnSelect: boolean;
 }
 
+/** Where a terminal's session comes from */
+export interface PtySource {
+  cwd?: string;
+  /** Show this existing session rather than starting a new one */
+  attachSessionId?: string;
+  /** Output from before a restart, written ahead of the attached session's */
+  scrollback?: string;
+  /** Replay this recording read-only rather than starting a session */
+  recordingPath?: string;
+  /** Start the new session as the command line asked */
+  launch?: LaunchArgs;
+}
+
 interface PtyCleanupRefs {
   unlistenOutput: (() => void) | null;
   unlistenExit: (() => void) | null;
+  unlistenCwd?: (() => void) | null;
+  unlistenTitle?: (() => void) | null;
+  unlistenResize?: (() => void) | null;
+}
+
+interface TerminalInternals {
+  _core?: { _renderService?: { dimensions?: { css?: { cell?: CellSize } } } };
+}
+
+/** Cell size in CSS pixels, from xterm internals since the public API doesn't expose it */
+export function cellSize(terminal: Terminal): CellSize | undefined {
+  const internals = terminal as unknown as TerminalInternals;
+  const cell = internals._core?._renderService?.dimensions?.css?.cell;
+  if (!cell || !(cell.width > 0) || !(cell.height > 0)) {
+    return undefined;
+  }
+  return { width: Math.round(cell.width), height: Math.round(cell.height) };
+}
+
+export function describeExit(info: PtyExitInfo): string {
+  if (info.readerError) {
+    return `Lost terminal output: ${info.readerError}`;
+  }
+  if (info.signal !== null) {
+    return `Process terminated by ${info.signalName ?? `signal ${info.signal}`}`;
+  }
+  if (info.exitCode !== null && info.exitCode !== 0) {
+    return `Process exited with code ${info.exitCode}`;
+  }
+  return "Process completed";
+}
+
+/** Replay a recording into the terminal. Input is ignored, and the playback stops with the tab */
+export async function initPlayback(
+  terminal: Terminal,
+  path: string,
+  callbacks: PtyCallbacks,
+  cleanupRefs: PtyCleanupRefs,
+): Promise<string> {
+  terminal.options.disableStdin = true;
+  let receiving = true;
+  const playback = await playRecording(path, undefined, (data) => {
+    if (receiving) terminal.write(data);
+  });
+  // Shown at the size it was recorded at, so full-screen programs replay as they looked
+  terminal.resize(playback.cols, playback.rows);
+  cleanupRefs.unlistenResize = await onPlaybackResize(playback.id, ({ cols, rows }) => {
+    terminal.resize(cols, rows);
+  });
+  let finished = false;
+  cleanupRefs.unlistenOutput = () => {
+    receiving = false;
+    if (!finished) {
+      stopPlayback(playback.id).catch(() => {});
+    }
+  };
+  callbacks.onConnected(null);
+  cleanupRefs.unlistenExit = await onPtyExit(playback.id, (info) => {
+    finished = true;
+    const message = info.readerError ?? "Playback finished";
+    terminal.write(`\r\n\x1b[90m[${message}]\x1b[0m\r\n`);
+    callbacks.onSessionEnded();
+  });
+  return playback.id;
 }
 
 export async function initPty(
   terminal: Terminal,
   cols: number,
   rows: number,
-  cwd: string | undefined,
+  source: PtySource,
   callbacks: PtyCallbacks,
   cleanupRefs: PtyCleanupRefs,
 ): Promise<string> {
-  const sessionId = await createPtySession(cols, rows, cwd);
-  callbacks.onConnected(sessionId);
+  if (source.recordingPath) {
+    return initPlayback(terminal, source.recordingPath, callbacks, cleanupRefs);
+  }
+  let receiving = true;
+  let ackSessionId: string | null = null;
+  let unacked = 0;
+  const flushAcks = () => {
+    if (ackSessionId && unacked >= ACK_BATCH_BYTES) {
+      ackPty(ackSessionId, unacked);
+      unacked = 0;
+    }
+  };
 
-  const unlisten1 = await onPtyOutput(sessionId, (data) => {
-    terminal.write(data);
-  });
-  cleanupRefs.unlistenOutput = unlisten1;
+  const onOutput = (data: Uint8Array) => {
+    if (receiving) {
+      // Ack once xterm has parsed the data, so a slow renderer slows the shell down
+      terminal.write(data, () => {
+        unacked += data.length;
+        flushAcks();
+      });
+    } else {
+      // Still acked, so a session that outlives its terminal doesn't stall
+      unacked += data.length;
+      flushAcks();
+    }
+  };
+  let sessionId: string;
+  if (source.attachSessionId) {
+    sessionId = source.attachSessionId;
+    if (source.scrollback) {
+      terminal.write(source.scrollback);
+    }
+    await attachPty(sessionId, onOutput);
+  } else {
+    sessionId = await createPtySession(
+      cols,
+      rows,
+      source.cwd,
+      onOutput,
+      cellSize(terminal),
+      source.launch,
+    );
+  }
+  ackSessionId = sessionId;
+  flushAcks();
+  cleanupRefs.unlistenOutput = () => {
+    receiving = false;
+  };
+  callbacks.onConnected(sessionId);
 
-  const unlisten2 = await onPtyExit(sessionId, () => {
-    terminal.write("\r\n\x1b[90m[Process completed]\x1b[0m\r\n");
+  let exitShown = false;
+  const showExit = (info: PtyExitInfo) => {
+    if (exitShown) return;
+    exitShown = true;
+    terminal.write(`\r\n\x1b[90m[${describeExit(info)}]\x1b[0m\r\n`);
     callbacks.onSessionEnded();
+  };
+  cleanupRefs.unlistenExit = await onPtyExit(sessionId, showExit);
+  // A session that ended before the listener was registered, e.g. `madsterm -e true`
+  const summary = (await listPtySessions()).find((session) => session.id === sessionId);
+  if (summary?.exit) {
+    showExit(summary.exit);
+  }
+
+  // The backend follows the shell's cwd via OSC 7 and the foreground process
+  cleanupRefs.unlistenCwd = await onPtyCwd(sessionId, (dir) => {
+    callbacks.updateTabCwd(callbacks.tabId, dir);
   });
-  cleanupRefs.unlistenExit = unlisten2;
 
   terminal.onData((data) => {
     writePty(sessionId, data);
@@ -55,36 +200,20 @@ export async function initPty(
   });
 
   terminal.onResize(({ cols, rows }) => {
-    resizePty(sessionId, cols, rows);
+    resizePty(sessionId, cols, rows, cellSize(terminal));
   });
 
-  // Send an explicit resize to ensure PTY and terminal are synced
+  // Send an explicit resize to ensure PTY and terminal are synced; an attached
+  // session still has the size of the window it was last shown in
   const currentCols = terminal.cols;
   const currentRows = terminal.rows;
-  if (currentCols !== cols || currentRows !== rows) {
-    resizePty(sessionId, currentCols, currentRows);
+  if (source.attachSessionId || currentCols !== cols || currentRows !== rows) {
+    resizePty(sessionId, currentCols, currentRows, cellSize(terminal));
   }
 
-  // Fetch home dir for tilde expansion in CWD tracking
-  const homeDir = await getHomeDir();
-
-  // Track CWD from terminal title (most shells set title to user@host:cwd)
-  terminal.onTitleChange((title) => {
+  // From the backend rather than xterm, so titles stay current while a tab is hidden
+  cleanupRefs.unlistenTitle = await onPtyTitle(sessionId, (title) => {
     callbacks.onTitleChange?.(title);
-    // Extract path from title: try "user@host: ~/path" or "host: /path" format first,
-    // then fall back to bare path starting with ~ or /
-    const colonMatch = title.match(/:\s*(.+?)\s*$/);
-    const pathMatch = title.match(/^(~\/.*|\/\S+)/);
-    let extracted = colonMatch?.[1]?.trim() || pathMatch?.[1] || null;
-    if (extracted) {
-      // Expand tilde to home directory
-      if (extracted.startsWith("~")) {
-        extracted = homeDir + extracted.slice(1);
-      }
-      if (extracted.startsWith("/")) {
-        callbacks.updateTabCwd(callbacks.tabId, extracted);
-      }
-    }
   });
 
   // Copy on select
@@ -106,6 +235,9 @@ export function cleanupPty(
 ): void {
   cleanupRefs.unlistenOutput?.();
   cleanupRefs.unlistenExit?.();
+  cleanupRefs.unlistenCwd?.();
+  cleanupRefs.unlistenTitle?.();
+  cleanupRefs.unlistenResize?.();
   if (sessionIdRef.current) {
     closePty(sessionIdRef.current);
     sessionIdRef.current = null;
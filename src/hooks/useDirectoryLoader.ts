@@ -1,7 +1,7 @@
 import { useCallback, useEffect, useState } from "react";
 import { getHomeDir, openFile, readDirectory } from "../commands/fs";
 import { useFileExplorerStore } from "../stores/fileExplorerStore";
-import { useTabStore } from "../stores/tabStore";
+import { createTabId, useTabStore } from "../stores/tabStore";
 import type { FileNode } from "../types";
 
 export function useDirectoryLoader() {
@@ -170,6 +170,16 @@ export function useDirectoryLoader() {
     async (node: FileNode) => {
       if (node.isDir) {
         handleExpand(node);
+      } else if (node.name.endsWith(".cast")) {
+        // Terminal recordings play back in a read-only tab
+        useTabStore.getState().addTab({
+          id: createTabId(),
+          title: node.name,
+          sessionId: "",
+          cwd: "",
+          isActive: true,
+          recordingPath: node.path,
+        });
       } else {
         try {
           await openFile(node.path);
@@ -15,6 +15,15 @@ interface TabStore {
   moveTab: (fromIndex: number, toIndex: number) => void;
 }
 
+export function createTabId(): string {
+  return Math.random().toString(36).substring(2, 10);
+}
+
+/** Tab title for a working directory: its last component */
+export function folderTitle(cwd: string): string {
+  return cwd.split("/").filter(Boolean).pop() || "Terminal";
+}
+
 export const useTabStore = create<TabStore>((set, get) => ({
   tabs: [],
   activeTabId: null,
@@ -60,12 +69,9 @@ export const useTabStore = create<TabStore>((set, get) => ({
     })),
 
   updateTabCwd: (tabId, cwd) =>
-    set((state) => {
-      const folderName = cwd.split("/").filter(Boolean).pop() || "Terminal";
-      return {
-        tabs: state.tabs.map((t) => (t.id === tabId ? { ...t, cwd, title: folderName } : t)),
-      };
-    }),
+    set((state) => ({
+      tabs: state.tabs.map((t) => (t.id === tabId ? { ...t, cwd, title: folderTitle(cwd) } : t)),
+    })),
 
   reopenLastClosedTab: () => {
     const state = get();
@@ -5,8 +5,13 @@ const mockInvoke = vi.fn();
 const mockListen = vi.fn(() => Promise.resolve(() => {}));
 const mockEmit = vi.fn();
 
+class MockChannel<T> {
+  onmessage: (response: T) => void = () => {};
+}
+
 vi.mock("@tauri-apps/api/core", () => ({
   invoke: mockInvoke,
+  Channel: MockChannel,
 }));
 
 vi.mock("@tauri-apps/api/event", () => ({
@@ -1,9 +1,19 @@
+import type { LaunchArgs } from "../commands/pty";
+
 export interface Tab {
   id: string;
   title: string;
   sessionId: string;
   cwd: string;
   isActive: boolean;
+  /** Existing backend session to show instead of starting one, e.g. kept by the daemon */
+  attachSessionId?: string;
+  /** Output saved before the app restarted, shown ahead of the attached session's */
+  restoredScrollback?: string;
+  /** `.cast` recording replayed read-only instead of a session */
+  recordingPath?: string;
+  /** What the command line asked the tab's new session to run */
+  launch?: LaunchArgs;
 }
 
 export interface FileNode {